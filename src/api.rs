@@ -0,0 +1,427 @@
+//! Library API for embedding yek.
+//!
+//! ```no_run
+//! let result = yek::Yek::builder()
+//!     .path("src")
+//!     .max_tokens(128_000)
+//!     .ignore("*.snap")
+//!     .build()?
+//!     .serialize()?;
+//! println!("{} files, {} bytes", result.stats.files_included, result.stats.total_bytes);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Unlike the CLI, nothing is printed and nothing is written to disk.
+
+use crate::{
+    category::CategoryWeights,
+    config::YekConfig,
+    count_tokens,
+    defaults::DEFAULT_OUTPUT_TEMPLATE,
+    models::{BundleEntry, Dedupe, ExcludedFile, FollowSymlinks, NotebookOutputs, Submodules},
+    priority::PriorityRule,
+    serialize_repo_with_report,
+};
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// Size budget for the rendered output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Budget {
+    /// Maximum size in bytes
+    Bytes(u64),
+    /// Maximum number of tokens
+    Tokens(usize),
+}
+
+/// Format of the rendered output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Files rendered with the output template (`>>>> FILE_PATH\nFILE_CONTENT` by default)
+    #[default]
+    Text,
+    /// A JSON array of `{ "filename", "content" }` objects
+    Json,
+}
+
+/// A configured serializer, created with [`Yek::builder`]
+#[derive(Debug, Clone)]
+pub struct Yek {
+    config: YekConfig,
+}
+
+/// Builder for [`Yek`]
+#[derive(Debug, Clone, Default)]
+pub struct YekBuilder {
+    paths: Vec<PathBuf>,
+    budget: Option<Budget>,
+    format: OutputFormat,
+    line_numbers: bool,
+    tree_header: bool,
+    tree_only: bool,
+    output_template: Option<String>,
+    ignore_patterns: Vec<String>,
+    unignore_patterns: Vec<String>,
+    include_patterns: Vec<String>,
+    summarize_patterns: Vec<String>,
+    summary_rows: Option<usize>,
+    max_file_size: Option<u64>,
+    max_file_lines: Option<usize>,
+    include_generated: bool,
+    follow_symlinks: FollowSymlinks,
+    legacy_encoding: Option<String>,
+    strip_bom: bool,
+    normalize_newlines: bool,
+    notebook_outputs: NotebookOutputs,
+    submodules: Submodules,
+    dedupe: Dedupe,
+    strip_headers: bool,
+    boilerplate_patterns: Vec<String>,
+    priority_rules: Vec<PriorityRule>,
+    binary_extensions: Vec<String>,
+    git_boost_max: Option<i32>,
+    max_git_depth: Option<i32>,
+    category_weights: Option<CategoryWeights>,
+}
+
+/// Everything produced by [`Yek::serialize`]
+#[derive(Debug, Clone)]
+pub struct YekOutput {
+    /// Rendered output
+    pub output: String,
+    /// Files included in the output, in output order, with their position in `output`
+    pub selected: Vec<BundleEntry>,
+    /// Files that were seen but left out, with the reason why
+    pub excluded: Vec<ExcludedFile>,
+    /// Summary numbers for the run
+    pub stats: Stats,
+    /// Checksum of the selected files and the settings that shaped the output
+    pub checksum: String,
+    /// Problems worth reporting, such as input paths that do not exist
+    pub warnings: Vec<String>,
+}
+
+/// Summary numbers for a serialization run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+    /// Files that were read and passed the ignore and binary checks
+    pub files_processed: usize,
+    /// Files that made it into the output
+    pub files_included: usize,
+    /// Files that were left out
+    pub files_excluded: usize,
+    /// Size of the output in bytes
+    pub total_bytes: usize,
+    /// Number of lines in the output
+    pub total_lines: usize,
+    /// Number of tokens in the output (only counted with a token budget)
+    pub total_tokens: Option<usize>,
+    /// Bytes saved by rendering duplicate files as a note
+    pub bytes_saved: usize,
+    /// Tokens saved by rendering duplicate files as a note (only counted with a token budget)
+    pub tokens_saved: Option<usize>,
+}
+
+impl Yek {
+    /// Start configuring a serializer. Defaults match the CLI without a config file.
+    pub fn builder() -> YekBuilder {
+        YekBuilder::default()
+    }
+
+    /// The resolved configuration used for serialization
+    pub fn config(&self) -> &YekConfig {
+        &self.config
+    }
+
+    /// Serialize the configured paths
+    pub fn serialize(&self) -> Result<YekOutput> {
+        let report = serialize_repo_with_report(&self.config)?;
+        let total_tokens = self.config.token_mode.then(|| count_tokens(&report.output));
+
+        Ok(YekOutput {
+            stats: Stats {
+                files_processed: report.files.len(),
+                files_included: report.entries.len(),
+                files_excluded: report.excluded.len(),
+                total_bytes: report.output.len(),
+                total_lines: report.output.lines().count(),
+                total_tokens,
+                bytes_saved: report.bytes_saved,
+                tokens_saved: report.tokens_saved,
+            },
+            output: report.output,
+            selected: report.entries,
+            excluded: report.excluded,
+            checksum: report.checksum,
+            warnings: report.warnings,
+        })
+    }
+}
+
+impl YekBuilder {
+    /// Add a file, directory or glob pattern to serialize (default: the current directory)
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    /// Add several files, directories or glob patterns to serialize
+    pub fn paths<I, P>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.paths.extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    /// Limit the output to a number of bytes (default: 10MB)
+    pub fn max_bytes(mut self, bytes: u64) -> Self {
+        self.budget = Some(Budget::Bytes(bytes));
+        self
+    }
+
+    /// Limit the output to a number of tokens
+    pub fn max_tokens(mut self, tokens: usize) -> Self {
+        self.budget = Some(Budget::Tokens(tokens));
+        self
+    }
+
+    /// Skip files larger than a number of bytes, without reading them
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    /// Skip files with more lines than this
+    pub fn max_file_lines(mut self, lines: usize) -> Self {
+        self.max_file_lines = Some(lines);
+        self
+    }
+
+    /// Keep generated and minified files, which are skipped by default
+    pub fn include_generated(mut self, enabled: bool) -> Self {
+        self.include_generated = enabled;
+        self
+    }
+
+    /// Choose which symbolic links found in directories are followed
+    pub fn follow_symlinks(mut self, follow: FollowSymlinks) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Decode files that are not valid UTF-8 with this encoding, e.g. "windows-1252"
+    pub fn legacy_encoding(mut self, label: impl Into<String>) -> Self {
+        self.legacy_encoding = Some(label.into());
+        self
+    }
+
+    /// Remove the byte order mark from the start of files
+    pub fn strip_bom(mut self, enabled: bool) -> Self {
+        self.strip_bom = enabled;
+        self
+    }
+
+    /// Turn CRLF line endings into LF
+    pub fn normalize_newlines(mut self, enabled: bool) -> Self {
+        self.normalize_newlines = enabled;
+        self
+    }
+
+    /// Choose what is kept of Jupyter notebook outputs
+    pub fn notebook_outputs(mut self, outputs: NotebookOutputs) -> Self {
+        self.notebook_outputs = outputs;
+        self
+    }
+
+    /// Choose whether git submodules are walked
+    pub fn submodules(mut self, submodules: Submodules) -> Self {
+        self.submodules = submodules;
+        self
+    }
+
+    /// Choose whether files repeating an earlier file are rendered as a note
+    pub fn dedupe(mut self, dedupe: Dedupe) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+
+    /// Remove license headers repeated at the start of many files, showing each once at the top
+    pub fn strip_headers(mut self, enabled: bool) -> Self {
+        self.strip_headers = enabled;
+        self
+    }
+
+    /// Remove leading comment blocks matching a regex, showing each once at the top
+    pub fn boilerplate(mut self, pattern: impl Into<String>) -> Self {
+        self.boilerplate_patterns.push(pattern.into());
+        self
+    }
+
+    /// Set the output budget
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Set the output format
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Prefix each line with its line number
+    pub fn line_numbers(mut self, enabled: bool) -> Self {
+        self.line_numbers = enabled;
+        self
+    }
+
+    /// Start the output with a directory tree (text output only)
+    pub fn tree_header(mut self, enabled: bool) -> Self {
+        self.tree_header = enabled;
+        self
+    }
+
+    /// Only output the directory tree (text output only)
+    pub fn tree_only(mut self, enabled: bool) -> Self {
+        self.tree_only = enabled;
+        self
+    }
+
+    /// Template for each file, using `FILE_PATH` and `FILE_CONTENT` placeholders
+    pub fn output_template(mut self, template: impl Into<String>) -> Self {
+        self.output_template = Some(template.into());
+        self
+    }
+
+    /// Ignore files matching a glob pattern, in addition to `.gitignore` and the built-in patterns
+    pub fn ignore(mut self, pattern: impl Into<String>) -> Self {
+        self.ignore_patterns.push(pattern.into());
+        self
+    }
+
+    /// Include files matching a glob pattern even if a built-in pattern ignores them
+    pub fn unignore(mut self, pattern: impl Into<String>) -> Self {
+        self.unignore_patterns.push(pattern.into());
+        self
+    }
+
+    /// Only include files matching a glob pattern, relative to the input paths.
+    /// Files named directly as a path are always included.
+    pub fn include(mut self, pattern: impl Into<String>) -> Self {
+        self.include_patterns.push(pattern.into());
+        self
+    }
+
+    /// Include files matching a glob pattern as a summary instead of verbatim,
+    /// even if a built-in pattern ignores them (e.g. "Cargo.lock" or "*.csv")
+    pub fn summarize(mut self, pattern: impl Into<String>) -> Self {
+        self.summarize_patterns.push(pattern.into());
+        self
+    }
+
+    /// Rows or array items kept when summarizing CSV and JSON files
+    pub fn summary_rows(mut self, rows: usize) -> Self {
+        self.summary_rows = Some(rows);
+        self
+    }
+
+    /// Add `score` to the priority of files whose path matches the `pattern` regex.
+    /// Higher priority files are placed later in the output.
+    pub fn priority_rule(mut self, pattern: impl Into<String>, score: i32) -> Self {
+        self.priority_rules.push(PriorityRule {
+            pattern: pattern.into(),
+            score,
+        });
+        self
+    }
+
+    /// Treat files with this extension as binary, in addition to the built-in list
+    pub fn binary_extension(mut self, extension: impl Into<String>) -> Self {
+        let extension = extension.into();
+        self.binary_extensions
+            .push(extension.trim_start_matches('.').to_string());
+        self
+    }
+
+    /// Maximum priority boost for recently changed files (default: 100)
+    pub fn git_boost_max(mut self, boost: i32) -> Self {
+        self.git_boost_max = Some(boost);
+        self
+    }
+
+    /// Number of commits to look at for the recency boost (default: 100)
+    pub fn max_git_depth(mut self, depth: i32) -> Self {
+        self.max_git_depth = Some(depth);
+        self
+    }
+
+    /// Priority offsets for file categories (source, test, configuration, ...)
+    pub fn category_weights(mut self, weights: CategoryWeights) -> Self {
+        self.category_weights = Some(weights);
+        self
+    }
+
+    /// Validate the options and create the serializer
+    pub fn build(self) -> Result<Yek> {
+        let defaults = YekConfig::default();
+        let input_paths = if self.paths.is_empty() {
+            vec![".".to_string()]
+        } else {
+            self.paths
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect()
+        };
+        let (max_size, tokens) = match self.budget {
+            None => (defaults.max_size.clone(), String::new()),
+            Some(Budget::Bytes(bytes)) => (format!("{}B", bytes), String::new()),
+            Some(Budget::Tokens(tokens)) => (defaults.max_size.clone(), tokens.to_string()),
+        };
+
+        let mut config = YekConfig {
+            input_paths,
+            max_size,
+            token_mode: !tokens.is_empty(),
+            tokens,
+            json: self.format == OutputFormat::Json,
+            line_numbers: self.line_numbers,
+            tree_header: self.tree_header,
+            tree_only: self.tree_only,
+            output_template: Some(
+                self.output_template
+                    .unwrap_or_else(|| DEFAULT_OUTPUT_TEMPLATE.to_string()),
+            ),
+            ignore_patterns: self.ignore_patterns,
+            unignore_patterns: self.unignore_patterns,
+            include_patterns: self.include_patterns,
+            summarize_patterns: self.summarize_patterns,
+            summary_rows: self.summary_rows.unwrap_or(defaults.summary_rows),
+            max_file_size: self.max_file_size.map(|bytes| format!("{}B", bytes)),
+            max_file_lines: self.max_file_lines,
+            include_generated: self.include_generated,
+            follow_symlinks: self.follow_symlinks,
+            legacy_encoding: self.legacy_encoding,
+            strip_bom: self.strip_bom,
+            normalize_newlines: self.normalize_newlines,
+            notebook_outputs: self.notebook_outputs,
+            submodules: self.submodules,
+            dedupe: self.dedupe,
+            strip_headers: self.strip_headers,
+            boilerplate_patterns: self.boilerplate_patterns,
+            priority_rules: self.priority_rules,
+            binary_extensions: self.binary_extensions,
+            git_boost_max: self.git_boost_max.or(defaults.git_boost_max),
+            max_git_depth: self.max_git_depth.unwrap_or(defaults.max_git_depth),
+            category_weights: self.category_weights,
+            // Output is returned to the caller, never written to disk
+            stream: true,
+            ..defaults
+        };
+        config.apply_builtin_defaults();
+        config.validate()?;
+
+        Ok(Yek { config })
+    }
+}
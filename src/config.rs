@@ -1,15 +1,149 @@
 use anyhow::{anyhow, Result};
 use bytesize::ByteSize;
-use clap_config_file::ClapConfigFile;
+use clap::{parser::ValueSource, ArgMatches, CommandFactory, FromArgMatches};
+use regex::Regex;
 use sha2::{Digest, Sha256};
 use std::io::{self, BufRead, BufReader, IsTerminal};
-use std::{fs, path::Path, process::Command, str::FromStr, time::UNIX_EPOCH};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    time::UNIX_EPOCH,
+};
 
 use crate::{
     defaults::{BINARY_FILE_EXTENSIONS, DEFAULT_IGNORE_PATTERNS, DEFAULT_OUTPUT_TEMPLATE},
+    encoding::legacy_encoding,
+    init::InitArgs,
+    mcp::McpArgs,
+    models::{Dedupe, FollowSymlinks, NotebookOutputs, Submodules},
+    pipeline::input_root,
     priority::PriorityRule,
+    repository::GitCheckout,
+    serve::ServeArgs,
+    stages::{build_stages, StageConfig},
 };
 
+/// Extensions tried, in order, when looking for a `yek.*` config file
+const CONFIG_FILE_EXTENSIONS: &[&str] = &["toml", "yaml", "yml", "json"];
+
+/// Fields of [`YekConfig`] that a profile cannot set: computed fields,
+/// command-line-only flags, `allow_exec` and the profiles themselves
+const NON_PROFILE_KEYS: &[&str] = &[
+    "allow_exec",
+    "version",
+    "update",
+    "include",
+    "profile",
+    "profiles",
+    "stream",
+    "token_mode",
+    "output_file_full_path",
+];
+
+/// Prefix of the environment variables overriding config options
+const ENV_PREFIX: &str = "YEK_";
+
+/// Where the value of a config option comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigOrigin {
+    Default,
+    /// `~/.config/yek/config.*`
+    UserConfig(PathBuf),
+    /// A `yek.*` file of the repository
+    ProjectConfig(PathBuf),
+    /// The file given with `--config-file`
+    ConfigFile(PathBuf),
+    /// A profile, by name
+    Profile(String),
+    /// A `YEK_*` environment variable, by name
+    Env(String),
+    CommandLine,
+}
+
+impl fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigOrigin::Default => f.write_str("default"),
+            ConfigOrigin::UserConfig(path) => write!(f, "user config {}", path.display()),
+            ConfigOrigin::ProjectConfig(path) => write!(f, "project config {}", path.display()),
+            ConfigOrigin::ConfigFile(path) => write!(f, "config file {}", path.display()),
+            ConfigOrigin::Profile(name) => write!(f, "profile '{}'", name),
+            ConfigOrigin::Env(var) => write!(f, "env {}", var),
+            ConfigOrigin::CommandLine => f.write_str("command line"),
+        }
+    }
+}
+
+/// The origin of each option not left at its default, by field name
+pub type ConfigOrigins = BTreeMap<String, ConfigOrigin>;
+
+/// Options for subcommands (e.g. `yek mcp`) that load the config without the main CLI parser
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct SubcommandConfigArgs {
+    /// Do not use a config file
+    #[arg(long = "no-config")]
+    pub no_config: bool,
+
+    /// Path to the config file
+    #[arg(long = "config-file")]
+    pub config_file: Option<PathBuf>,
+
+    /// Apply a profile from the config file's `profiles` on top of the other settings
+    #[arg(long = "profile")]
+    pub profile: Option<String>,
+
+    /// Run `exec` stages declared in the repository's `yek.*` files
+    #[arg(long = "allow-exec")]
+    pub allow_exec: bool,
+
+    /// Input files and/or directories to process
+    pub input_paths: Vec<String>,
+}
+
+impl SubcommandConfigArgs {
+    /// Load the config files (unless disabled), apply the built-in defaults and validate.
+    /// Output is never written to disk, so the config is always in streaming mode.
+    pub fn load(&self) -> Result<YekConfig> {
+        let mut cfg = YekConfig {
+            input_paths: self.input_paths.clone(),
+            profile: self.profile.clone(),
+            allow_exec: self.allow_exec,
+            ..YekConfig::default()
+        };
+        let mut cli_keys: HashSet<String> = self.profile.iter().map(|_| "profile".into()).collect();
+        if self.allow_exec {
+            cli_keys.insert("allow_exec".to_string());
+        }
+        cfg.apply_layers(
+            self.no_config,
+            self.config_file.as_deref(),
+            &cli_keys,
+            std::env::vars(),
+        )?;
+
+        if !self.input_paths.is_empty() {
+            cfg.input_paths = self.input_paths.clone();
+        }
+        if cfg.input_paths.is_empty() {
+            cfg.input_paths.push(".".to_string());
+        }
+
+        cfg.token_mode = !cfg.tokens.is_empty();
+        cfg.stream = true;
+        if cfg.output_template.is_none() {
+            cfg.output_template = Some(DEFAULT_OUTPUT_TEMPLATE.to_string());
+        }
+        cfg.apply_builtin_defaults();
+        cfg.output_file_full_path = None;
+        cfg.validate()?;
+
+        Ok(cfg)
+    }
+}
+
 #[derive(Clone, Debug, Default, clap::ValueEnum, serde::Serialize, serde::Deserialize)]
 pub enum ConfigFormat {
     #[default]
@@ -18,97 +152,263 @@ pub enum ConfigFormat {
     Json,
 }
 
-#[derive(ClapConfigFile, Clone)]
-#[config_file_name = "yek"]
-#[config_file_formats = "toml,yaml,json"]
+// The command line: a subcommand, or the options of a run on the input paths.
+// A plain clap parser, as config files are found and layered by
+// `YekConfig::apply_layers`: a config-file derive such as clap-config-file
+// looks up its own file from the current directory, with no supported way to
+// turn that off. Once a run option or input path is given, later words are
+// input paths, so `yek src serve` serializes `src` and `serve`.
+#[derive(clap::Parser, Debug)]
+#[command(about, args_conflicts_with_subcommands = true)]
+pub struct YekArgs {
+    #[command(subcommand)]
+    pub command: Option<YekCommand>,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+/// Subcommands of yek; without one, yek serializes its input paths
+#[derive(clap::Subcommand, Debug)]
+pub enum YekCommand {
+    /// Serve repository context over the Model Context Protocol (stdio)
+    Mcp(McpArgs),
+    /// Serve bundles over HTTP on localhost
+    Serve(ServeArgs),
+    /// Write a yek config file tailored to a repository
+    Init(InitArgs),
+    /// Inspect the config of a run
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+/// Subcommands of `yek config`
+#[derive(clap::Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the config a run with the same options would use, and where each option comes from
+    Show(Box<RunArgs>),
+}
+
+/// The options of a run, and how to find its config files
+#[derive(clap::Args, Debug)]
+pub struct RunArgs {
+    /// Do not use a config file
+    #[arg(long = "no-config")]
+    pub no_config: bool,
+
+    /// Path to the config file
+    #[arg(long = "config-file")]
+    pub config_file: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub config: YekConfig,
+
+    /// The options given on the command line, as config field names
+    #[arg(skip)]
+    pub cli_keys: HashSet<String>,
+}
+
+impl YekArgs {
+    /// Parse the command line, exiting with a usage message if it is invalid
+    pub fn from_command_line() -> Self {
+        let matches = Self::command().get_matches();
+        let mut args = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        match &mut args.command {
+            None => args.run.cli_keys = cli_keys(&matches),
+            Some(YekCommand::Config(ConfigCommand::Show(run))) => {
+                let show = matches
+                    .subcommand_matches("config")
+                    .and_then(|config| config.subcommand_matches("show"))
+                    .expect("parsed as config show");
+                run.cli_keys = cli_keys(show);
+            }
+            Some(_) => {}
+        }
+        args
+    }
+}
+
+#[derive(clap::Args, Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct YekConfig {
     /// Input files and/or directories to process
-    #[config_arg(positional)]
     pub input_paths: Vec<String>,
 
     /// Print version of yek
-    #[config_arg(long = "version", short = 'V')]
+    #[arg(long, short = 'V')]
     pub version: bool,
 
     /// Update yek to the latest version
-    #[config_arg(long = "update")]
+    #[arg(long)]
     pub update: bool,
 
     /// Max size per chunk. e.g. "10MB" or "128K" or when using token counting mode, "100" or "128K"
-    #[config_arg(default_value = "10MB")]
+    #[arg(long, default_value = "10MB")]
     pub max_size: String,
 
     /// Use token mode instead of byte mode
-    #[config_arg()]
+    #[arg(long, default_value = "", hide_default_value = true)]
     pub tokens: String,
 
     /// Enable JSON output
-    #[config_arg()]
+    #[arg(long)]
     pub json: bool,
 
     /// Enable debug output
-    #[config_arg()]
+    #[arg(long)]
     pub debug: bool,
 
     /// Include line numbers in output
-    #[config_arg(long = "line-numbers")]
+    #[arg(long)]
     pub line_numbers: bool,
 
     /// Output directory. If none is provided & stdout is a TTY, we pick a temp dir
-    #[config_arg()]
+    #[arg(long)]
     pub output_dir: Option<String>,
 
     /// Output filename. If provided, write output to this file in current directory
-    #[config_arg(long = "output-name")]
+    #[arg(long)]
     pub output_name: Option<String>,
 
     /// Output template. Defaults to ">>>> FILE_PATH\nFILE_CONTENT"
-    #[config_arg()]
+    #[arg(long)]
     pub output_template: Option<String>,
 
     /// Ignore patterns
-    #[config_arg(long = "ignore-patterns", multi_value_behavior = "extend")]
+    #[arg(long, num_args = 1..)]
     pub ignore_patterns: Vec<String>,
 
     /// Unignore patterns. Yek has some built-in ignore patterns, but you can override them here.
-    #[config_arg(long = "unignore-patterns", multi_value_behavior = "extend")]
+    #[arg(long, num_args = 1..)]
     pub unignore_patterns: Vec<String>,
 
+    /// Only include files matching this pattern (gitignore syntax, relative to each input directory). e.g. "src/**/*.rs"
+    #[arg(long, num_args = 1..)]
+    pub include: Vec<String>,
+
+    /// Only include files matching these patterns. `--include` values are added to them
+    #[arg(skip)]
+    pub include_patterns: Vec<String>,
+
+    /// Include files matching these patterns as a summary: lockfiles as name@version lists, CSV and JSON as schema plus first rows
+    #[arg(long, num_args = 1..)]
+    pub summarize_patterns: Vec<String>,
+
+    /// Rows or array items kept when summarizing CSV and JSON files
+    #[arg(long, default_value_t = 10)]
+    pub summary_rows: usize,
+
+    /// Skip files larger than this, without reading them. e.g. "1MB" or "500K"
+    #[arg(long)]
+    pub max_file_size: Option<String>,
+
+    /// Skip files with more lines than this
+    #[arg(long)]
+    pub max_file_lines: Option<usize>,
+
+    /// Include generated and minified files, which are skipped by default
+    #[arg(long)]
+    pub include_generated: bool,
+
+    /// Symbolic links to follow while walking directories: never, within-root or always
+    #[arg(long, value_enum, default_value_t)]
+    pub follow_symlinks: FollowSymlinks,
+
+    /// Encoding of files that are not valid UTF-8, e.g. "windows-1252" (default: decode as UTF-8)
+    #[arg(long)]
+    pub legacy_encoding: Option<String>,
+
+    /// Remove the byte order mark from the start of files
+    #[arg(long)]
+    pub strip_bom: bool,
+
+    /// Turn CRLF line endings into LF
+    #[arg(long)]
+    pub normalize_newlines: bool,
+
+    /// Outputs of Jupyter notebook code cells: summarize or drop
+    #[arg(long, value_enum, default_value_t)]
+    pub notebook_outputs: NotebookOutputs,
+
+    /// Git submodules: recurse into them or skip them
+    #[arg(long, value_enum, default_value_t)]
+    pub submodules: Submodules,
+
+    /// Files repeating an earlier file: off, exact or near
+    #[arg(long, value_enum, default_value_t)]
+    pub dedupe: Dedupe,
+
+    /// Remove license headers repeated at the start of many files, showing each once at the top
+    #[arg(long)]
+    pub strip_headers: bool,
+
+    /// Remove leading comment blocks matching these regexes, e.g. "Copyright .* Acme Corp"
+    #[arg(long, num_args = 1..)]
+    pub boilerplate_patterns: Vec<String>,
+
     /// Priority rules
-    #[config_arg(accept_from = "config_only")]
+    #[arg(skip)]
     pub priority_rules: Vec<PriorityRule>,
 
     /// Binary file extensions to ignore
-    #[config_arg(accept_from = "config_only", default_value = BINARY_FILE_EXTENSIONS)]
+    #[arg(skip)]
     pub binary_extensions: Vec<String>,
 
     /// Maximum additional boost from Git commit times (0..1000)
-    #[config_arg(accept_from = "config_only")]
+    #[arg(skip)]
     pub git_boost_max: Option<i32>,
 
     /// Category-based priority weights
-    #[config_arg(accept_from = "config_only")]
+    #[arg(skip)]
     pub category_weights: Option<crate::category::CategoryWeights>,
 
+    /// Processing stages that transform file content before output
+    #[arg(skip)]
+    pub stages: Vec<StageConfig>,
+
+    /// Run `exec` stages declared in the repository's `yek.*` files. Only read
+    /// from the command line, the user config and `--config-file`
+    #[arg(long)]
+    pub allow_exec: bool,
+
     /// Include directory tree header in output (incompatible with JSON output)
-    #[config_arg(long = "tree-header", short = 't')]
+    #[arg(long, short = 't')]
     pub tree_header: bool,
 
     /// Show only the directory tree (no file contents, incompatible with JSON output)
-    #[config_arg(long = "tree-only")]
+    #[arg(long)]
     pub tree_only: bool,
 
+    /// List files skipped by max_file_size or max_file_lines in the tree header
+    #[arg(long)]
+    pub tree_include_too_large: bool,
+
+    /// Write a JSON manifest describing the run next to the output file
+    #[arg(long)]
+    pub manifest: bool,
+
+    /// Apply a profile from the config file's `profiles` on top of the other settings
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Named sets of options, each overriding the rest of the config file when selected with `--profile`
+    #[arg(skip)]
+    pub profiles: BTreeMap<String, serde_json::Map<String, serde_json::Value>>,
+
     /// True if we should stream output to stdout (computed)
+    #[arg(skip)]
     pub stream: bool,
 
     /// True if we should count tokens, not bytes (computed)
+    #[arg(skip)]
     pub token_mode: bool,
 
     /// Final resolved output file path (only used if not streaming)
+    #[arg(skip)]
     pub output_file_full_path: Option<String>,
 
     /// Maximum depth to search for Git commit times
-    #[config_arg(accept_from = "config_only", default_value = "100")]
+    #[arg(skip)]
     pub max_git_depth: i32,
 }
 
@@ -129,6 +429,22 @@ impl Default for YekConfig {
             output_template: Some(DEFAULT_OUTPUT_TEMPLATE.to_string()),
             ignore_patterns: Vec::new(),
             unignore_patterns: Vec::new(),
+            include: Vec::new(),
+            include_patterns: Vec::new(),
+            summarize_patterns: Vec::new(),
+            summary_rows: 10,
+            max_file_size: None,
+            max_file_lines: None,
+            include_generated: false,
+            follow_symlinks: FollowSymlinks::Never,
+            legacy_encoding: None,
+            strip_bom: false,
+            normalize_newlines: false,
+            notebook_outputs: NotebookOutputs::Summarize,
+            submodules: Submodules::Recurse,
+            dedupe: Dedupe::Off,
+            strip_headers: false,
+            boilerplate_patterns: Vec::new(),
             priority_rules: Vec::new(),
             binary_extensions: BINARY_FILE_EXTENSIONS
                 .iter()
@@ -136,10 +452,16 @@ impl Default for YekConfig {
                 .collect(),
             git_boost_max: Some(100),
             category_weights: None,
+            stages: Vec::new(),
+            allow_exec: false,
 
             // computed fields
             tree_header: false,
             tree_only: false,
+            tree_include_too_large: false,
+            manifest: false,
+            profile: None,
+            profiles: BTreeMap::new(),
             stream: false,
             token_mode: false,
             output_file_full_path: None,
@@ -201,10 +523,25 @@ impl YekConfig {
         Ok(output_dir)
     }
 
+    /// Parse the command line, without loading config files
+    pub fn parse() -> Self {
+        <YekArgs as clap::Parser>::parse().run.config
+    }
+
     /// Parse from CLI + config file, fill in computed fields, and validate.
     pub fn init_config() -> Self {
-        // 1) parse from CLI and optional config file:
-        let (mut cfg, _config_path, _config_format) = YekConfig::parse_info();
+        YekArgs::from_command_line().run.into_config(false).0
+    }
+}
+
+impl RunArgs {
+    /// The config of the run, with where each option comes from: the parsed
+    /// options on top of the config files, profile and environment, computed
+    /// fields filled in and validated. With `show`, for `yek config show`,
+    /// nothing is read from stdin or created on disk.
+    pub fn into_config(self, show: bool) -> (YekConfig, ConfigOrigins) {
+        // 1) start from the options parsed from the command line:
+        let mut cfg = self.config;
 
         // Handle version flag
         if cfg.version {
@@ -223,8 +560,29 @@ impl YekConfig {
             }
         }
 
-        // 2) compute derived fields:
+        // 2) layer the config files, the selected profile and the environment,
+        // keeping options given on the command line
+        let applied = cfg.apply_layers(
+            self.no_config,
+            self.config_file.as_deref(),
+            &self.cli_keys,
+            std::env::vars(),
+        );
+        let mut origins = match applied {
+            Ok(origins) => origins,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        };
+        if !cfg.input_paths.is_empty() {
+            origins.insert("input_paths".to_string(), ConfigOrigin::CommandLine);
+        }
+
+        // 3) compute derived fields:
         cfg.token_mode = !cfg.tokens.is_empty();
+        let cli_includes = std::mem::take(&mut cfg.include);
+        cfg.include_patterns.extend(cli_includes);
         let force_tty = std::env::var("FORCE_TTY").is_ok();
 
         cfg.stream = !std::io::stdout().is_terminal() && !force_tty;
@@ -236,7 +594,7 @@ impl YekConfig {
 
         // Check if we should read input paths from stdin
         if cfg.input_paths.is_empty() {
-            if !std::io::stdin().is_terminal() {
+            if !show && !std::io::stdin().is_terminal() {
                 // Read file paths from stdin (one per line)
                 match cfg.read_input_paths_from_stdin() {
                     Ok(stdin_paths) => {
@@ -258,32 +616,10 @@ impl YekConfig {
             }
         }
 
-        // Extend binary extensions with the built-in list:
-        let mut merged_bins = BINARY_FILE_EXTENSIONS
-            .iter()
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>();
-        merged_bins.append(&mut cfg.binary_extensions);
-        cfg.binary_extensions = merged_bins
-            .into_iter()
-            .collect::<std::collections::HashSet<_>>()
-            .into_iter()
-            .collect();
-
-        // Always start with default ignore patterns, then add user's:
-        let mut ignore = DEFAULT_IGNORE_PATTERNS
-            .iter()
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>();
-        ignore.extend(cfg.ignore_patterns);
-        cfg.ignore_patterns = ignore;
-
-        // Apply unignore patterns (turn them into negative globs "!…")
-        cfg.ignore_patterns
-            .extend(cfg.unignore_patterns.iter().map(|pat| format!("!{}", pat)));
+        cfg.apply_builtin_defaults();
 
         // Handle output directory setup
-        if !cfg.stream {
+        if !cfg.stream && !show {
             match cfg.ensure_output_dir() {
                 Ok(dir) => cfg.output_dir = Some(dir),
                 Err(e) => {
@@ -296,71 +632,464 @@ impl YekConfig {
         // By default, we start with no final output_file_full_path:
         cfg.output_file_full_path = None;
 
-        // 3) Validate
+        // 4) Validate
         if let Err(e) = cfg.validate() {
             eprintln!("Error: {}", e);
             std::process::exit(1);
         }
 
-        cfg
+        (cfg, origins)
     }
+}
 
-    /// Compute a quick checksum for the input paths (files and directories).
-    /// For directories, it uses the top-level listing. For files, it uses the file metadata.
-    pub fn get_checksum(input_paths: &[String]) -> String {
-        let mut hasher = Sha256::new();
-        for path_str in input_paths {
-            let base_path = Path::new(path_str);
-            if !base_path.exists() {
-                continue;
+impl YekConfig {
+    /// Apply the config files (unless `no_config`), the selected profile and
+    /// the `YEK_*` variables of `env` on top of the options parsed from the
+    /// command line, returning where each option comes from. `explicit` is the
+    /// file given with `--config-file`, and `cli_keys` are the options given on
+    /// the command line.
+    pub fn apply_layers(
+        &mut self,
+        no_config: bool,
+        explicit: Option<&Path>,
+        cli_keys: &HashSet<String>,
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<ConfigOrigins> {
+        let mut origins = ConfigOrigins::new();
+        let mut layered = serde_json::Map::new();
+        if !no_config {
+            let files = Self::config_files(&self.input_paths, explicit);
+            let user_config = Self::user_config_file();
+            let mut loaded = Vec::new();
+            for file in &files {
+                let origin = if Some(file) == user_config.as_ref() {
+                    ConfigOrigin::UserConfig(file.clone())
+                } else if Some(file.as_path()) == explicit {
+                    ConfigOrigin::ConfigFile(file.clone())
+                } else {
+                    ConfigOrigin::ProjectConfig(file.clone())
+                };
+                loaded.push((origin, Self::load_config_value(file)?));
             }
 
-            // If it's a file, hash the file metadata directly
-            if base_path.is_file() {
-                if let Ok(meta) = fs::metadata(base_path) {
-                    hasher.update(path_str.as_bytes());
-                    hasher.update(meta.len().to_le_bytes());
-
-                    if let Ok(mod_time) = meta.modified() {
-                        if let Ok(dur) = mod_time.duration_since(UNIX_EPOCH) {
-                            hasher.update(dur.as_secs().to_le_bytes());
-                            hasher.update(dur.subsec_nanos().to_le_bytes());
-                        }
+            // A repository's files cannot run commands unless the user allows it
+            let allow_exec = self.allow_exec
+                || loaded.iter().any(|(origin, options)| {
+                    matches!(origin, ConfigOrigin::UserConfig(_))
+                        && options.get("allow_exec") == Some(&serde_json::Value::Bool(true))
+                });
+            for (origin, options) in &mut loaded {
+                if let ConfigOrigin::ProjectConfig(file) = origin {
+                    for command in restrict_project_options(options, allow_exec) {
+                        eprintln!(
+                            "Warning: {}: skipping exec stage '{}' (pass --allow-exec to run it)",
+                            file.display(),
+                            command
+                        );
                     }
                 }
-                continue;
+                if let serde_json::Value::Object(options) = options {
+                    for key in options.keys() {
+                        origins.insert(key.clone(), origin.clone());
+                    }
+                }
+            }
+            layered = merge_config_values(loaded.into_iter().map(|(_, options)| options));
+            self.apply_overrides(layered.clone(), &serde_json::Value::Null, cli_keys)
+                .map_err(|e| anyhow!("config_file: {}", e))?;
+        }
+
+        let (env_options, env_vars) = Self::env_overrides(env)?;
+        if self.profile.is_none() {
+            if let Some(serde_json::Value::String(name)) = env_options.get("profile") {
+                self.profile = Some(name.clone());
+                origins.insert(
+                    "profile".to_string(),
+                    ConfigOrigin::Env(env_vars["profile"].clone()),
+                );
+            }
+        }
+        if let Some(name) = self.profile.clone() {
+            self.apply_profile(&name, &serde_json::Value::Object(layered.clone()), cli_keys)?;
+            for (key, value) in &self.profiles[&name] {
+                origins.insert(key.clone(), ConfigOrigin::Profile(name.clone()));
+                layered.insert(key.clone(), value.clone());
             }
+        }
 
-            // If it's a directory, hash its contents
-            let entries = match fs::read_dir(base_path) {
-                Ok(iter) => iter.filter_map(|e| e.ok()).collect::<Vec<_>>(),
-                Err(_) => continue,
+        let mut env_options = env_options;
+        env_options.remove("profile");
+        for key in env_options.keys() {
+            origins.insert(key.clone(), ConfigOrigin::Env(env_vars[key].clone()));
+        }
+        self.apply_overrides(env_options, &serde_json::Value::Object(layered), cli_keys)
+            .map_err(|e| anyhow!("env: {}", e))?;
+
+        let fields = serde_json::to_value(Self::default())
+            .map_err(|e| anyhow!("Failed to serialize config: {}", e))?;
+        for key in cli_keys {
+            if fields.get(key).is_some() {
+                origins.insert(key.clone(), ConfigOrigin::CommandLine);
+            }
+        }
+        Ok(origins)
+    }
+
+    /// The options set by the `YEK_*` variables of `env`, with the variable
+    /// setting each. `YEK_TOKENS` sets `tokens`, and so on for every option a
+    /// profile can set, plus `YEK_PROFILE`. Lists are comma-separated or written
+    /// as a YAML list; empty variables and unknown names are skipped.
+    pub fn env_overrides(
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<(
+        serde_json::Map<String, serde_json::Value>,
+        BTreeMap<String, String>,
+    )> {
+        let fields = serde_json::to_value(Self::default())
+            .map_err(|e| anyhow!("Failed to serialize config: {}", e))?;
+        let mut options = serde_json::Map::new();
+        let mut vars = BTreeMap::new();
+        for (var, raw) in env {
+            let Some(key) = var.strip_prefix(ENV_PREFIX).map(str::to_lowercase) else {
+                continue;
+            };
+            let settable = key == "profile"
+                || !(NON_PROFILE_KEYS.contains(&key.as_str()) || key == "input_paths");
+            let Some(default) = fields.get(&key).filter(|_| settable) else {
+                continue;
+            };
+            let raw = raw.trim();
+            if raw.is_empty() {
+                continue;
+            }
+            let value = if default.is_array() {
+                if raw.starts_with('[') {
+                    serde_yaml::from_str(raw)
+                        .map_err(|e| anyhow!("{}: Invalid list: {}", var, e))?
+                } else {
+                    raw.split(',')
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                        .map(|item| serde_json::Value::String(item.to_string()))
+                        .collect()
+                }
+            } else {
+                // Read back like a config file value, so "true" or "20" work too
+                serde_json::Value::String(raw.to_string())
             };
+            options.insert(key.clone(), value);
+            vars.insert(key, var);
+        }
+        Ok((options, vars))
+    }
 
-            // Sort deterministically by path name
-            let mut sorted = entries;
-            sorted.sort_by_key(|a| a.path());
-
-            for entry in sorted {
-                let p = entry.path();
-                if let Ok(meta) = fs::metadata(&p) {
-                    let path_str = p.to_string_lossy();
-                    hasher.update(path_str.as_bytes());
-                    hasher.update(meta.len().to_le_bytes());
-
-                    if let Ok(mod_time) = meta.modified() {
-                        if let Ok(dur) = mod_time.duration_since(UNIX_EPOCH) {
-                            hasher.update(dur.as_secs().to_le_bytes());
-                            hasher.update(dur.subsec_nanos().to_le_bytes());
-                        }
+    /// The resolved options, one per line with where each comes from, for `yek config show`
+    pub fn describe(&self, origins: &ConfigOrigins) -> Result<String> {
+        let value =
+            serde_json::to_value(self).map_err(|e| anyhow!("Failed to serialize config: {}", e))?;
+        let options = value.as_object().expect("config serializes to an object");
+        let mut out = String::new();
+        for (key, value) in options {
+            if NON_PROFILE_KEYS.contains(&key.as_str())
+                && !matches!(key.as_str(), "profile" | "profiles" | "allow_exec")
+            {
+                continue;
+            }
+            let origin = origins.get(key).unwrap_or(&ConfigOrigin::Default);
+            out.push_str(&format!("{}: {}  ({})\n", key, value, origin));
+        }
+        Ok(out)
+    }
+
+    /// Merge the built-in binary extensions and ignore patterns into the user's lists
+    pub(crate) fn apply_builtin_defaults(&mut self) {
+        // Extend binary extensions with the built-in list:
+        let mut merged_bins = BINARY_FILE_EXTENSIONS
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        merged_bins.append(&mut self.binary_extensions);
+        self.binary_extensions = merged_bins
+            .into_iter()
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        // Always start with default ignore patterns, then add user's:
+        let mut ignore = DEFAULT_IGNORE_PATTERNS
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        ignore.append(&mut self.ignore_patterns);
+        self.ignore_patterns = ignore;
+
+        // Apply unignore patterns (turn them into negative globs "!…"). Files to
+        // summarize, such as lockfiles, are unignored too
+        self.ignore_patterns.extend(
+            self.unignore_patterns
+                .iter()
+                .chain(&self.summarize_patterns)
+                .map(|pat| format!("!{}", pat)),
+        );
+    }
+
+    /// Find the nearest `yek.{toml,yaml,yml,json}` in the current directory or its parents
+    pub fn find_config_file() -> Option<PathBuf> {
+        std::env::current_dir()
+            .ok()?
+            .ancestors()
+            .find_map(|dir| find_config_in(dir, "yek"))
+    }
+
+    /// Load a config file without parsing the command line.
+    /// Fields missing from the file keep their default values.
+    pub fn load_config_file(path: &Path) -> Result<Self> {
+        Self::read_config_file(path)
+    }
+
+    /// Load a config file as it is written, without defaults for missing fields
+    pub fn load_config_value(path: &Path) -> Result<serde_json::Value> {
+        Self::read_config_file(path)
+    }
+
+    fn read_config_file<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+        let format = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => config::FileFormat::Toml,
+            Some("json") => config::FileFormat::Json,
+            _ => config::FileFormat::Yaml,
+        };
+        config::Config::builder()
+            .add_source(config::File::from(path).format(format))
+            .build()
+            .and_then(|built| built.try_deserialize())
+            .map_err(|e| anyhow!("config_file: cannot load '{}': {}", path.display(), e))
+    }
+
+    /// Override options with the profile `name`, the same way as [`YekConfig::apply_overrides`]
+    pub fn apply_profile(
+        &mut self,
+        name: &str,
+        file: &serde_json::Value,
+        cli_keys: &HashSet<String>,
+    ) -> Result<()> {
+        let overrides = self.profiles.get(name).cloned().ok_or_else(|| {
+            let defined: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            anyhow!(
+                "profile: Unknown profile '{}' (defined: {})",
+                name,
+                if defined.is_empty() {
+                    "none".to_string()
+                } else {
+                    defined.join(", ")
+                }
+            )
+        })?;
+
+        let profiles = std::mem::take(&mut self.profiles);
+        let applied = self.apply_overrides(overrides, file, cli_keys);
+        self.profiles = profiles;
+        applied.map_err(|e| anyhow!("profiles.{}: {}", name, e))?;
+        self.profile = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Override options with `overrides`. `file` is the config file the options
+    /// were loaded from, and `cli_keys` the options given on the command line,
+    /// which keep their value. Lists extended on the command line keep the
+    /// added values after the overriding list.
+    pub fn apply_overrides(
+        &mut self,
+        overrides: serde_json::Map<String, serde_json::Value>,
+        file: &serde_json::Value,
+        cli_keys: &HashSet<String>,
+    ) -> Result<()> {
+        let mut value = serde_json::to_value(&*self)
+            .map_err(|e| anyhow!("Failed to serialize config: {}", e))?;
+        let object = value
+            .as_object_mut()
+            .expect("config serializes to an object");
+        for (key, override_value) in overrides {
+            let merged = match (override_value, object.get(&key)) {
+                (serde_json::Value::Array(mut list), Some(serde_json::Value::Array(current))) => {
+                    let base = file
+                        .get(&key)
+                        .and_then(serde_json::Value::as_array)
+                        .map_or(&[][..], Vec::as_slice);
+                    if !current.starts_with(base) {
+                        // Replaced on the command line
+                        continue;
                     }
+                    list.extend(current[base.len()..].iter().cloned());
+                    serde_json::Value::Array(list)
                 }
+                _ if cli_keys.contains(&key) => continue,
+                (override_value, _) => override_value,
+            };
+            object.insert(key, merged);
+        }
+
+        // Read back like a config file, so e.g. `tokens: 1000` is taken as a string
+        *self = config::Config::try_from(&value)
+            .and_then(|built| built.try_deserialize())
+            .map_err(|e| anyhow!("{}", e))?;
+        Ok(())
+    }
+
+    /// The config file with user-wide defaults: `$XDG_CONFIG_HOME/yek/config.*`,
+    /// by default `~/.config/yek/config.*`
+    pub fn user_config_file() -> Option<PathBuf> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        find_config_in(&config_dir.join("yek"), "config")
+    }
+
+    /// The `yek.*` files from the top of the repository containing `dir` down
+    /// to `dir`, outermost first. Outside a repository only `dir` is searched,
+    /// so a stray `yek.yaml` in e.g. `/tmp` or the home directory is not used.
+    pub fn project_config_files(dir: &Path) -> Vec<PathBuf> {
+        let dirs: Vec<&Path> = dir.ancestors().collect();
+        let top = dirs
+            .iter()
+            .position(|dir| GitCheckout::detect(dir).is_some())
+            .unwrap_or(0);
+        dirs[..=top]
+            .iter()
+            .rev()
+            .filter_map(|dir| find_config_in(dir, "yek"))
+            .collect()
+    }
+
+    /// The config files for a run on `input_paths`, lowest precedence first: the
+    /// user config, then `explicit` if given, otherwise the project config files
+    /// from the top of the repository down to the first input
+    pub fn config_files(input_paths: &[String], explicit: Option<&Path>) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = Self::user_config_file().into_iter().collect();
+        match explicit {
+            Some(path) => files.push(path.to_path_buf()),
+            None => {
+                let first = input_paths.first().map_or(".", String::as_str);
+                files.extend(Self::project_config_files(&input_root(first)));
+            }
+        }
+        files
+    }
+
+    /// The options of `files`, each file overriding the options of the files
+    /// before it. Profiles are merged by name.
+    pub fn merge_config_files(
+        files: &[PathBuf],
+    ) -> Result<serde_json::Map<String, serde_json::Value>> {
+        let values = files
+            .iter()
+            .map(|file| Self::load_config_value(file))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(merge_config_values(values))
+    }
+
+    /// Compute a quick checksum of the input paths (files and directories), to
+    /// tell whether they may have changed without reading them. Directories are
+    /// walked recursively (skipping hidden and gitignored files); the path, size
+    /// and modification time of every file are hashed in sorted order.
+    /// The checksum of the output itself is [`crate::output_checksum`].
+    pub fn get_checksum(input_paths: &[String]) -> String {
+        hash_input_files(input_paths, |hasher, path| {
+            if let Ok(meta) = fs::metadata(path) {
+                let modified = meta
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .unwrap_or_default();
+                hasher.update(meta.len().to_le_bytes());
+                hasher.update(modified.as_secs().to_le_bytes());
+                hasher.update(modified.subsec_nanos().to_le_bytes());
+            }
+        })
+    }
+
+    /// Like [`YekConfig::get_checksum`], but hashing what every file contains
+    /// instead of its size and modification time, so any edit changes it
+    pub fn get_content_checksum(input_paths: &[String]) -> String {
+        hash_input_files(input_paths, |hasher, path| {
+            if let Ok(content) = fs::read(path) {
+                hasher.update((content.len() as u64).to_le_bytes());
+                hasher.update(content);
+            }
+        })
+    }
+
+    /// Settings that affect the rendered output, as canonical JSON.
+    /// Used together with the selected files to compute the output checksum.
+    pub fn checksum_settings(&self) -> Result<String> {
+        let mut value =
+            serde_json::to_value(self).map_err(|e| anyhow!("Failed to serialize config: {}", e))?;
+        if let Some(object) = value.as_object_mut() {
+            // Where the output goes and how the run is reported does not change its content
+            for key in [
+                "input_paths",
+                "version",
+                "update",
+                "debug",
+                "output_dir",
+                "output_name",
+                "output_file_full_path",
+                "manifest",
+                "stream",
+                "profile",
+                "profiles",
+            ] {
+                object.remove(key);
             }
         }
-        let result = hasher.finalize();
-        // Convert the 32-byte result to hex, but only keep the first 8 characters
-        let hex = format!("{:x}", result);
-        hex[..8].to_owned()
+        Ok(value.to_string())
+    }
+
+    /// `boilerplate_patterns` compiled
+    pub fn boilerplate_regexes(&self) -> Result<Vec<Regex>> {
+        self.boilerplate_patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| {
+                    anyhow!("boilerplate_patterns: Invalid regex '{}': {}", pattern, e)
+                })
+            })
+            .collect()
+    }
+
+    /// Every profile only sets options a config file can set, with values of the right type
+    fn validate_profiles(&self) -> Result<()> {
+        let known = serde_json::to_value(YekConfig::default())
+            .map_err(|e| anyhow!("Failed to serialize config: {}", e))?;
+        for (name, overrides) in &self.profiles {
+            if let Some(key) = overrides.keys().find(|key| {
+                NON_PROFILE_KEYS.contains(&key.as_str()) || known.get(key.as_str()).is_none()
+            }) {
+                return Err(anyhow!(
+                    "profiles: Unknown option '{}' in profile '{}'",
+                    key,
+                    name
+                ));
+            }
+            let mut probe = YekConfig {
+                profiles: BTreeMap::from([(name.clone(), overrides.clone())]),
+                ..YekConfig::default()
+            };
+            probe.apply_profile(name, &serde_json::Value::Null, &HashSet::new())?;
+        }
+        Ok(())
+    }
+
+    /// `max_file_size` in bytes, or None when not set
+    pub fn max_file_size_bytes(&self) -> Result<Option<u64>> {
+        self.max_file_size
+            .as_deref()
+            .map(|size| {
+                ByteSize::from_str(size)
+                    .map(|size| size.as_u64())
+                    .map_err(|e| anyhow!("max_file_size: Invalid size format: {}", e))
+            })
+            .transpose()
     }
 
     /// Validate the final config.
@@ -420,6 +1149,34 @@ impl YekConfig {
             glob::Pattern::new(pattern)
                 .map_err(|e| anyhow!("ignore_patterns: Invalid pattern '{}': {}", pattern, e))?;
         }
+        for pattern in &self.include_patterns {
+            glob::Pattern::new(pattern)
+                .map_err(|e| anyhow!("include_patterns: Invalid pattern '{}': {}", pattern, e))?;
+        }
+        for pattern in &self.summarize_patterns {
+            glob::Pattern::new(pattern)
+                .map_err(|e| anyhow!("summarize_patterns: Invalid pattern '{}': {}", pattern, e))?;
+        }
+        self.boilerplate_regexes()?;
+        self.validate_profiles()?;
+        if self.summary_rows == 0 {
+            return Err(anyhow!("summary_rows: cannot be 0"));
+        }
+
+        if let Some(max_file_size) = &self.max_file_size {
+            self.max_file_size_bytes()?;
+            if max_file_size == "0" {
+                return Err(anyhow!("max_file_size: cannot be 0"));
+            }
+        }
+        if self.max_file_lines == Some(0) {
+            return Err(anyhow!("max_file_lines: cannot be 0"));
+        }
+        if let Some(label) = &self.legacy_encoding {
+            if legacy_encoding(label).is_none() {
+                return Err(anyhow!("legacy_encoding: Unknown encoding '{}'", label));
+            }
+        }
 
         // Validate priority rules
         for rule in &self.priority_rules {
@@ -434,6 +1191,8 @@ impl YekConfig {
             })?;
         }
 
+        build_stages(&self.stages)?;
+
         // Validate tree options are mutually exclusive
         if self.tree_header && self.tree_only {
             return Err(anyhow!("tree_header and tree_only cannot both be enabled"));
@@ -677,3 +1436,124 @@ impl YekConfig {
         Err(anyhow!("Could not extract version from release info"))
     }
 }
+
+/// Options set on the command line, as config field names
+fn cli_keys(matches: &ArgMatches) -> HashSet<String> {
+    matches
+        .ids()
+        .filter(|id| matches.value_source(id.as_str()) == Some(ValueSource::CommandLine))
+        .map(|id| id.to_string())
+        .collect()
+}
+
+/// Hash the path of every file of `input_paths`, walked as described in
+/// [`YekConfig::get_checksum`], followed by what `hash_file` adds for it
+fn hash_input_files(
+    input_paths: &[String],
+    mut hash_file: impl FnMut(&mut Sha256, &Path),
+) -> String {
+    let mut hasher = Sha256::new();
+    let mut hash_file = |rel_path: &str, path: &Path| {
+        hasher.update(rel_path.as_bytes());
+        hasher.update([0]);
+        hash_file(&mut hasher, path);
+    };
+    for path_str in input_paths {
+        let base_path = Path::new(path_str);
+        if !base_path.exists() {
+            continue;
+        }
+
+        if base_path.is_file() {
+            hash_file(path_str, base_path);
+            continue;
+        }
+
+        let walker = ignore::WalkBuilder::new(base_path)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .build();
+        for entry in walker.filter_map(|e| e.ok()) {
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            let rel_path = entry
+                .path()
+                .strip_prefix(base_path)
+                .unwrap_or(entry.path())
+                .to_string_lossy()
+                .to_string();
+            hash_file(&rel_path, entry.path());
+        }
+    }
+    let result = hasher.finalize();
+    // Convert the 32-byte result to hex, but only keep the first 8 characters
+    let hex = format!("{:x}", result);
+    hex[..8].to_owned()
+}
+
+/// Merge config file options the way [`YekConfig::merge_config_files`] does
+fn merge_config_values(
+    values: impl IntoIterator<Item = serde_json::Value>,
+) -> serde_json::Map<String, serde_json::Value> {
+    let mut merged = serde_json::Map::new();
+    for value in values {
+        let serde_json::Value::Object(options) = value else {
+            continue;
+        };
+        for (key, value) in options {
+            match (key.as_str(), value, merged.get_mut(&key)) {
+                (
+                    "profiles",
+                    serde_json::Value::Object(profiles),
+                    Some(serde_json::Value::Object(existing)),
+                ) => existing.extend(profiles),
+                (_, value, _) => {
+                    merged.insert(key, value);
+                }
+            }
+        }
+    }
+    merged
+}
+
+/// Remove what a repository's config file cannot set: `allow_exec` itself, and
+/// `exec` stages (in the file or its profiles) unless `allow_exec`. Returns the
+/// commands of the removed stages.
+fn restrict_project_options(options: &mut serde_json::Value, allow_exec: bool) -> Vec<String> {
+    let Some(options) = options.as_object_mut() else {
+        return Vec::new();
+    };
+    options.remove("allow_exec");
+    if allow_exec {
+        return Vec::new();
+    }
+
+    let mut removed = Vec::new();
+    let mut strip = |stages: Option<&mut serde_json::Value>| {
+        if let Some(serde_json::Value::Array(stages)) = stages {
+            stages.retain(|stage| {
+                if stage.get("type").and_then(serde_json::Value::as_str) != Some("exec") {
+                    return true;
+                }
+                let command = stage.get("command").and_then(serde_json::Value::as_str);
+                removed.push(command.unwrap_or_default().to_string());
+                false
+            });
+        }
+    };
+    strip(options.get_mut("stages"));
+    if let Some(serde_json::Value::Object(profiles)) = options.get_mut("profiles") {
+        for profile in profiles.values_mut() {
+            strip(profile.get_mut("stages"));
+        }
+    }
+    removed
+}
+
+/// The `{name}.*` config file in `dir`, trying the extensions in order
+pub(crate) fn find_config_in(dir: &Path, name: &str) -> Option<PathBuf> {
+    CONFIG_FILE_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .find(|candidate| candidate.is_file())
+}
@@ -0,0 +1,117 @@
+//! Duplicate file detection.
+//!
+//! Monorepos often hold many copies of the same file. With `dedupe` set, a
+//! file whose content matches a file earlier in the output is rendered as a
+//! short note pointing at that file. In `near` mode, files with the same name
+//! whose lines are mostly the same are rendered as a diff against the earlier
+//! file instead.
+
+use crate::models::Dedupe;
+use sha2::{Digest, Sha256};
+use similar::TextDiff;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Files smaller than this are always rendered in full, a note would not be shorter
+const MIN_DEDUPE_BYTES: usize = 64;
+
+/// Lowest share of matching lines for two files to count as near-duplicates
+const NEAR_DUPLICATE_MIN_RATIO: f32 = 0.9;
+
+/// Files with more lines than this are not compared for near-duplicates
+const NEAR_DUPLICATE_MAX_LINES: usize = 5000;
+
+/// How a file repeats an earlier one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Duplicate {
+    /// Same content as the file at this index
+    Exact { of: usize },
+    /// Content differing from the file at this index by `diff`
+    Near { of: usize, diff: String },
+}
+
+impl Duplicate {
+    /// Index of the file this one repeats
+    pub fn of(&self) -> usize {
+        match self {
+            Duplicate::Exact { of } | Duplicate::Near { of, .. } => *of,
+        }
+    }
+
+    /// The text rendered in place of the file's content
+    pub fn note(&self, original: &str) -> String {
+        match self {
+            Duplicate::Exact { .. } => format!("[same content as {}]\n", original),
+            Duplicate::Near { diff, .. } => {
+                format!("[same content as {}, except:]\n{}", original, diff)
+            }
+        }
+    }
+}
+
+/// For each of `files` (path and content, in output order), the earlier file
+/// it duplicates. Duplicates always point at a file that is not a duplicate.
+pub fn find_duplicates(files: &[(&str, &str)], mode: Dedupe) -> Vec<Option<Duplicate>> {
+    let mut by_hash: HashMap<[u8; 32], usize> = HashMap::new();
+    let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut duplicates = Vec::with_capacity(files.len());
+
+    for (index, (rel_path, content)) in files.iter().enumerate() {
+        if mode == Dedupe::Off || content.len() < MIN_DEDUPE_BYTES {
+            duplicates.push(None);
+            continue;
+        }
+
+        let hash: [u8; 32] = Sha256::digest(content.as_bytes()).into();
+        if let Some(&of) = by_hash.get(&hash) {
+            duplicates.push(Some(Duplicate::Exact { of }));
+            continue;
+        }
+
+        let file_name = Path::new(rel_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(rel_path);
+        let near = match mode {
+            Dedupe::Near => by_name.get(file_name).and_then(|earlier| {
+                earlier
+                    .iter()
+                    .find_map(|&of| near_duplicate(files[of].1, content, of))
+            }),
+            _ => None,
+        };
+        if near.is_none() {
+            by_hash.insert(hash, index);
+            by_name.entry(file_name).or_default().push(index);
+        }
+        duplicates.push(near);
+    }
+    duplicates
+}
+
+/// `content` as a near-duplicate of `original`, if most of their lines match
+fn near_duplicate(original: &str, content: &str, of: usize) -> Option<Duplicate> {
+    let (shorter, longer) = if original.len() < content.len() {
+        (original.len(), content.len())
+    } else {
+        (content.len(), original.len())
+    };
+    // Only files of about the same size are worth diffing
+    if (shorter as f32) < longer as f32 * NEAR_DUPLICATE_MIN_RATIO
+        || original.lines().count() > NEAR_DUPLICATE_MAX_LINES
+        || content.lines().count() > NEAR_DUPLICATE_MAX_LINES
+    {
+        return None;
+    }
+
+    let diff = TextDiff::from_lines(original, content);
+    if diff.ratio() < NEAR_DUPLICATE_MIN_RATIO {
+        return None;
+    }
+    let diff = diff
+        .unified_diff()
+        .context_radius(1)
+        .missing_newline_hint(false)
+        .to_string();
+    Some(Duplicate::Near { of, diff })
+}
@@ -118,6 +118,7 @@ pub const DEFAULT_IGNORE_PATTERNS: &[&str] = &[
     "coverage/**",
     "test-results/**",
     ".gitignore",
+    ".yekignore",
     "pnpm-lock.yaml",
     "yek.toml",
     "yek.yaml",
@@ -155,3 +156,6 @@ pub const DEFAULT_IGNORE_PATTERNS: &[&str] = &[
 ];
 
 pub const DEFAULT_OUTPUT_TEMPLATE: &str = ">>>> FILE_PATH\nFILE_CONTENT";
+
+/// Seconds an `exec` stage may run on a single file before it is killed
+pub const DEFAULT_STAGE_TIMEOUT_SECS: u64 = 30;
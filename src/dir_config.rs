@@ -0,0 +1,74 @@
+//! Per-directory config files.
+//!
+//! A `yek.*` file in a directory below an input directory only applies to the
+//! files inside that directory, which lets each team of a monorepo tune its
+//! own part of the tree:
+//!
+//! - `ignore_patterns` work like a `.yekignore` in that directory
+//! - `priority_rules` match paths relative to that directory
+//!
+//! Other options in such a file are not used. Config files in the input
+//! directory and above it are merged into the main config instead, see
+//! [`YekConfig::config_files`](crate::config::YekConfig::config_files).
+
+use crate::{
+    config::{find_config_in, YekConfig},
+    priority::{get_file_priority, PriorityRule},
+};
+use anyhow::{anyhow, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+use tracing::debug;
+
+/// The options of a per-directory config file
+pub struct DirConfig {
+    /// Ignore patterns, relative to the directory
+    pub ignore: Option<Gitignore>,
+    /// Priority rules, matched against paths relative to the directory
+    pub priority_rules: Vec<PriorityRule>,
+}
+
+impl DirConfig {
+    /// The config file in `dir`, or None if there is none or it cannot be read
+    pub fn load(dir: &Path) -> Option<Self> {
+        let file = find_config_in(dir, "yek")?;
+        match Self::read(dir, &file) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                debug!("Problem reading {}: {}", file.display(), e);
+                None
+            }
+        }
+    }
+
+    fn read(dir: &Path, file: &Path) -> Result<Self> {
+        let options = YekConfig::load_config_value(file)?;
+        let field = |name: &str| options.get(name).cloned().unwrap_or_default();
+
+        let patterns: Vec<String> = match field("ignore_patterns") {
+            serde_json::Value::Null => Vec::new(),
+            value => {
+                serde_json::from_value(value).map_err(|e| anyhow!("ignore_patterns: {}", e))?
+            }
+        };
+        let mut builder = GitignoreBuilder::new(dir);
+        for pattern in &patterns {
+            builder.add_line(Some(file.to_path_buf()), pattern)?;
+        }
+        let ignore = builder.build()?;
+
+        let priority_rules = match field("priority_rules") {
+            serde_json::Value::Null => Vec::new(),
+            value => serde_json::from_value(value).map_err(|e| anyhow!("priority_rules: {}", e))?,
+        };
+        Ok(Self {
+            ignore: (!ignore.is_empty()).then_some(ignore),
+            priority_rules,
+        })
+    }
+
+    /// Priority of `rel_path`, relative to the directory, from `priority_rules`
+    pub fn priority(&self, rel_path: &str) -> i32 {
+        get_file_priority(rel_path, &self.priority_rules)
+    }
+}
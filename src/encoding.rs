@@ -0,0 +1,175 @@
+//! Text encoding detection for file contents.
+//!
+//! Byte order marks and BOM-less UTF-16 are recognized before the binary
+//! check, since UTF-16 text is full of NUL bytes. A byte order mark is kept
+//! as U+FEFF at the start of the text unless `strip_bom` is set. Files that are not valid
+//! UTF-8 are decoded with `legacy_encoding` when it is set (e.g.
+//! `windows-1252` for Latin-1 files), otherwise lossily as UTF-8.
+
+use content_inspector::{inspect, ContentType};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
+
+/// Minimum share of NUL bytes in one half of the byte pairs for BOM-less UTF-16
+const UTF16_MIN_NUL_RATIO: f64 = 0.4;
+
+/// Maximum share of NUL bytes in the other half of the byte pairs
+const UTF16_MAX_OTHER_NUL_RATIO: f64 = 0.05;
+
+/// How a file's bytes were turned into text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    /// The text, starting with U+FEFF if the file has a byte order mark
+    pub text: String,
+    /// The detected encoding, or None for plain UTF-8
+    pub encoding: Option<String>,
+}
+
+/// Unicode encodings that can be told from their first bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unicode {
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl Unicode {
+    fn name(self) -> &'static str {
+        match self {
+            Unicode::Utf8Bom => "UTF-8 with BOM",
+            Unicode::Utf16Le => "UTF-16LE",
+            Unicode::Utf16Be => "UTF-16BE",
+            Unicode::Utf32Le => "UTF-32LE",
+            Unicode::Utf32Be => "UTF-32BE",
+        }
+    }
+}
+
+/// The Unicode encoding and BOM length of `bytes`, from a byte order mark
+/// or, for UTF-16, from the position of the NUL bytes
+fn detect_unicode(bytes: &[u8]) -> Option<(Unicode, usize)> {
+    const BOMS: [(&[u8], Unicode); 5] = [
+        // UTF-32LE must be checked before UTF-16LE, which shares its first bytes
+        (&[0xFF, 0xFE, 0x00, 0x00], Unicode::Utf32Le),
+        (&[0x00, 0x00, 0xFE, 0xFF], Unicode::Utf32Be),
+        (&[0xEF, 0xBB, 0xBF], Unicode::Utf8Bom),
+        (&[0xFF, 0xFE], Unicode::Utf16Le),
+        (&[0xFE, 0xFF], Unicode::Utf16Be),
+    ];
+    if let Some((bom, unicode)) = BOMS.iter().find(|(bom, _)| bytes.starts_with(bom)) {
+        return Some((*unicode, bom.len()));
+    }
+
+    if bytes.len() < 4 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let pairs = (bytes.len() / 2) as f64;
+    let nul_ratio = |offset: usize| {
+        bytes
+            .iter()
+            .skip(offset)
+            .step_by(2)
+            .filter(|&&b| b == 0)
+            .count() as f64
+            / pairs
+    };
+    let (even, odd) = (nul_ratio(0), nul_ratio(1));
+    if odd >= UTF16_MIN_NUL_RATIO && even <= UTF16_MAX_OTHER_NUL_RATIO {
+        Some((Unicode::Utf16Le, 0))
+    } else if even >= UTF16_MIN_NUL_RATIO && odd <= UTF16_MAX_OTHER_NUL_RATIO {
+        Some((Unicode::Utf16Be, 0))
+    } else {
+        None
+    }
+}
+
+fn decode_utf32(bytes: &[u8], little_endian: bool) -> String {
+    bytes
+        .chunks(4)
+        .map(|chunk| {
+            let mut word = [0u8; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            let value = if little_endian {
+                u32::from_le_bytes(word)
+            } else {
+                u32::from_be_bytes(word)
+            };
+            char::from_u32(value).unwrap_or(char::REPLACEMENT_CHARACTER)
+        })
+        .collect()
+}
+
+/// Control characters that do not occur in text files
+fn is_binary_control(c: char) -> bool {
+    c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x0c')
+}
+
+/// Whether `bytes` look like binary data rather than text in some encoding
+pub fn is_binary(bytes: &[u8]) -> bool {
+    decode(bytes, None).is_none()
+}
+
+/// Decode the contents of a file, or None if they are binary. `legacy` is
+/// used for files that are not valid UTF-8 and have no byte order mark.
+pub fn decode(bytes: &[u8], legacy: Option<&'static Encoding>) -> Option<Decoded> {
+    if let Some((unicode, bom_len)) = detect_unicode(bytes) {
+        // The byte order mark decodes to U+FEFF in every encoding
+        let text = match unicode {
+            Unicode::Utf8Bom => String::from_utf8_lossy(bytes).into_owned(),
+            Unicode::Utf16Le => UTF_16LE.decode_without_bom_handling(bytes).0.into_owned(),
+            Unicode::Utf16Be => UTF_16BE.decode_without_bom_handling(bytes).0.into_owned(),
+            Unicode::Utf32Le => decode_utf32(bytes, true),
+            Unicode::Utf32Be => decode_utf32(bytes, false),
+        };
+        // A byte order mark in front of binary data, or binary data that only
+        // looked like BOM-less UTF-16
+        if text.contains('\0') || (bom_len == 0 && text.chars().any(is_binary_control)) {
+            return None;
+        }
+        return Some(Decoded {
+            text,
+            encoding: Some(unicode.name().to_string()),
+        });
+    }
+
+    if inspect(bytes) == ContentType::BINARY {
+        return None;
+    }
+    Some(match (std::str::from_utf8(bytes), legacy) {
+        (Ok(text), _) => Decoded {
+            text: text.to_string(),
+            encoding: None,
+        },
+        (Err(_), Some(legacy)) => Decoded {
+            text: legacy.decode_without_bom_handling(bytes).0.into_owned(),
+            encoding: Some(legacy.name().to_string()),
+        },
+        (Err(_), None) => Decoded {
+            text: String::from_utf8_lossy(bytes).into_owned(),
+            encoding: Some("invalid UTF-8".to_string()),
+        },
+    })
+}
+
+/// The encoding named by `label`, such as `latin1`, `windows-1252` or `shift_jis`
+pub fn legacy_encoding(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.trim().as_bytes())
+}
+
+/// `text` without the byte order mark it starts with, if any
+pub fn strip_bom(text: String) -> String {
+    match text.strip_prefix('\u{FEFF}') {
+        Some(rest) => rest.to_string(),
+        None => text,
+    }
+}
+
+/// `text` with CRLF line endings turned into LF
+pub fn normalize_newlines(text: String) -> String {
+    if text.contains("\r\n") {
+        text.replace("\r\n", "\n")
+    } else {
+        text
+    }
+}
@@ -0,0 +1,75 @@
+//! Heuristics for generated and minified files.
+//!
+//! Such files are rarely useful context and can take a large part of the
+//! budget, so they are skipped unless `include_generated` is set.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Only the start of a file is searched for a generated marker
+const MARKER_SEARCH_LINES: usize = 20;
+
+/// Doc comments, which describe code rather than mark the file itself
+const DOC_COMMENTS: &[&str] = &["///", "//!"];
+
+/// Files smaller than this are never considered minified
+const MINIFIED_MIN_BYTES: usize = 1024;
+
+/// Average length of non-empty lines from which a file may be minified
+const MINIFIED_MIN_AVG_LINE_LENGTH: usize = 300;
+
+/// Highest share of spaces and tabs in a minified file
+const MINIFIED_MAX_WHITESPACE_RATIO: f64 = 0.1;
+
+/// The marker identifying `content` as generated, as found in the file.
+/// Markers only count in comment lines, so code or prose mentioning them does not.
+pub fn generated_marker(content: &str) -> Option<&str> {
+    static MARKER: OnceLock<Regex> = OnceLock::new();
+    let marker = MARKER.get_or_init(|| {
+        Regex::new(
+            r"(?i)^\s*(?://|#|/\*|\*|<!--|--).*?(@generated\b|code generated .* do not edit|<auto-generated|(?:this|the) file (?:is|was|has been) (?:auto(?:matically)?[- ]?)generated)",
+        )
+        .expect("valid regex")
+    });
+    content
+        .lines()
+        .take(MARKER_SEARCH_LINES)
+        .filter(|line| {
+            let line = line.trim_start();
+            !DOC_COMMENTS.iter().any(|doc| line.starts_with(doc))
+        })
+        .find_map(|line| marker.captures(line)?.get(1))
+        .map(|found| found.as_str())
+}
+
+/// Why `content` looks minified: very long lines with little whitespace
+pub fn minified_reason(content: &str) -> Option<String> {
+    if content.len() < MINIFIED_MIN_BYTES {
+        return None;
+    }
+    let (lines, chars) = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .fold((0, 0), |(lines, chars), line| {
+            (lines + 1, chars + line.len())
+        });
+    if lines == 0 {
+        return None;
+    }
+    let avg_line_length = chars / lines;
+    let whitespace = content
+        .bytes()
+        .filter(|b| *b == b' ' || *b == b'\t')
+        .count();
+    let whitespace_ratio = whitespace as f64 / content.len() as f64;
+
+    (avg_line_length >= MINIFIED_MIN_AVG_LINE_LENGTH
+        && whitespace_ratio <= MINIFIED_MAX_WHITESPACE_RATIO)
+        .then(|| {
+            format!(
+                "average line length {}, {:.0}% whitespace",
+                avg_line_length,
+                whitespace_ratio * 100.0
+            )
+        })
+}
@@ -0,0 +1,165 @@
+//! License headers and boilerplate banners.
+//!
+//! Many repositories start every file with the same license comment. With
+//! `strip_headers` set, a leading comment block found in at least
+//! `MIN_HEADER_FILES` files is removed from each of them. Blocks matching one
+//! of the `boilerplate_patterns` regexes are removed even if only one file
+//! has them. Each removed header is shown once at the top of the output.
+//!
+//! Headers are compared with comment markers, decoration and numbers left
+//! out, so the same license in `//` and `#` comments, or with different
+//! copyright years, counts as one header.
+
+use crate::models::ProcessedFile;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Files a leading comment block must appear in to count as a repeated header
+const MIN_HEADER_FILES: usize = 3;
+
+/// Lines of text a repeated header needs, so short comments are left alone
+const MIN_HEADER_LINES: usize = 3;
+
+/// Line comment markers a header can be written with
+const LINE_COMMENTS: [&str; 5] = ["//", "#", "--", ";", "%"];
+
+/// A header removed from the start of one or more files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemovedHeader {
+    /// The header as it appears in the first of `files`
+    pub text: String,
+    /// Files the header was removed from, in path order
+    pub files: Vec<String>,
+}
+
+/// The comment block at the start of `content`, after any shebang line and
+/// blank lines, as its byte range. Blank lines following the block are part of it.
+pub fn leading_comment(content: &str) -> Option<(usize, usize)> {
+    let mut start = 0;
+    let mut lines = content.split_inclusive('\n').peekable();
+    while let Some(line) = lines.peek() {
+        if !(line.trim().is_empty() || (start == 0 && line.starts_with("#!"))) {
+            break;
+        }
+        start += line.len();
+        lines.next();
+    }
+
+    let mut end = start;
+    let first = lines.peek()?.trim_start();
+    if let Some((open, close)) = [("/*", "*/"), ("<!--", "-->")]
+        .into_iter()
+        .find(|(open, _)| first.starts_with(open))
+    {
+        let after_open = start + content[start..].find(open)? + open.len();
+        end = after_open + content[after_open..].find(close)? + close.len();
+        // The rest of the closing line goes with the comment, if it is blank
+        let line_end = content[end..]
+            .find('\n')
+            .map_or(content.len(), |i| end + i + 1);
+        if !content[end..line_end].trim().is_empty() {
+            return None;
+        }
+        end = line_end;
+    } else {
+        for line in lines.by_ref() {
+            if !is_line_comment(line) {
+                break;
+            }
+            end += line.len();
+        }
+        if end == start {
+            return None;
+        }
+    }
+
+    for line in content[end..].split_inclusive('\n') {
+        if !line.trim().is_empty() {
+            break;
+        }
+        end += line.len();
+    }
+    Some((start, end))
+}
+
+fn is_line_comment(line: &str) -> bool {
+    let line = line.trim_start();
+    LINE_COMMENTS.iter().any(|marker| {
+        line.strip_prefix(marker).is_some_and(|rest| {
+            // Rules out `#include`, `#[derive]` and the like
+            *marker != "#" || rest.trim().is_empty() || rest.starts_with([' ', '\t', '#'])
+        })
+    })
+}
+
+/// The words of a comment block, without comment markers, decoration and numbers
+fn normalize(comment: &str) -> Vec<String> {
+    static NUMBERS: OnceLock<Regex> = OnceLock::new();
+    let numbers = NUMBERS.get_or_init(|| Regex::new(r"\d+").expect("valid regex"));
+    comment
+        .lines()
+        .map(|line| {
+            let text = line
+                .trim()
+                .trim_start_matches(['/', '*', '#', '-', ';', '%', '<', '!'])
+                .trim_end_matches(['/', '*', '-', '>'])
+                .trim();
+            numbers.replace_all(text, "0").into_owned()
+        })
+        .filter(|line| line.chars().any(char::is_alphanumeric))
+        .collect()
+}
+
+/// Remove repeated headers (when `repeated` is set) and headers matching
+/// `boilerplate` from `files`, returning each distinct header removed
+pub fn strip_headers(
+    files: &mut [ProcessedFile],
+    repeated: bool,
+    boilerplate: &[Regex],
+) -> Vec<RemovedHeader> {
+    let comments: Vec<Option<(usize, usize, Vec<String>)>> = files
+        .iter()
+        .map(|file| {
+            let (start, end) = leading_comment(&file.content)?;
+            let words = normalize(&file.content[start..end]);
+            (!words.is_empty()).then_some((start, end, words))
+        })
+        .collect();
+
+    let mut counts: HashMap<&[String], usize> = HashMap::new();
+    for (_, _, words) in comments.iter().flatten() {
+        *counts.entry(words.as_slice()).or_default() += 1;
+    }
+
+    let mut order: Vec<usize> = (0..files.len()).collect();
+    order.sort_by(|&a, &b| files[a].rel_path.cmp(&files[b].rel_path));
+
+    let mut removed: Vec<RemovedHeader> = Vec::new();
+    let mut by_words: HashMap<&[String], usize> = HashMap::new();
+    for index in order {
+        let Some((start, end, words)) = &comments[index] else {
+            continue;
+        };
+        let file = &mut files[index];
+        let text = &file.content[*start..*end];
+        let is_repeated = repeated
+            && words.len() >= MIN_HEADER_LINES
+            && counts[words.as_slice()] >= MIN_HEADER_FILES;
+        if !is_repeated && !boilerplate.iter().any(|re| re.is_match(text)) {
+            continue;
+        }
+
+        let header = *by_words.entry(words.as_slice()).or_insert_with(|| {
+            removed.push(RemovedHeader {
+                text: text.trim_end().to_string() + "\n",
+                files: Vec::new(),
+            });
+            removed.len() - 1
+        });
+        removed[header].files.push(file.rel_path.clone());
+        let content = format!("{}{}", &file.content[..*start], &file.content[*end..]);
+        file.set_content(content);
+    }
+    removed
+}
@@ -0,0 +1,402 @@
+//! Git-compatible ignore rules.
+//!
+//! A path is checked against these sources, first match wins:
+//!
+//! 1. the configured ignore patterns (`ignore_patterns`, `--ignore-patterns`),
+//!    which no `!` line in an ignore file can override
+//! 2. `.yekignore` files, from the path's own directory up to the top of the
+//!    repository (or of the input when it is not in a repository)
+//! 3. the `export-ignore` and `linguist-vendored` attributes from
+//!    `.gitattributes`, which leave files out of `git archive` and GitHub's
+//!    language statistics
+//! 4. `.gitignore` files, in the same order as `.yekignore` files
+//! 5. `.git/info/exclude`
+//! 6. the file named by git's `core.excludesFile` (default `~/.config/git/ignore`)
+//! 7. the `ignore_patterns` of per-directory `yek.*` files below the input
+//!    directory, nearest first (see [`crate::dir_config`])
+//! 8. all configured patterns again, built-in ones included, so a `.gitignore`
+//!    negation can re-include a file that yek would skip by default
+//!
+//! `.yekignore` uses the `.gitignore` syntax and holds excludes that only matter
+//! to yek; a `!` line in it can re-include a file `.gitignore` ignores.
+//! Inside a source the last matching line wins. Like git, a file inside an
+//! ignored directory stays ignored even if a later pattern negates it, and
+//! patterns ending in `/` only match directories. Sources 5 and 6 only apply
+//! inside a git repository.
+
+use crate::defaults::DEFAULT_IGNORE_PATTERNS;
+use crate::dir_config::DirConfig;
+use crate::repository::{ArchiveFileSystem, FileSystem, GitAttributes};
+use anyhow::Result;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tracing::debug;
+
+/// `.gitattributes` attributes that make a path ignored
+const IGNORE_ATTRIBUTES: [&str; 2] = ["export-ignore", "linguist-vendored"];
+
+/// The rule that made a path ignored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreRule {
+    /// The pattern as written
+    pub pattern: String,
+    /// The file the pattern comes from (None for configured ignore patterns)
+    pub source: Option<PathBuf>,
+}
+
+impl fmt::Display for IgnoreRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            Some(source) => write!(f, "{}: {}", source.display(), self.pattern),
+            None => f.write_str(&self.pattern),
+        }
+    }
+}
+
+/// Ignore rules for the files below one directory
+pub struct IgnoreRules {
+    /// Highest directory whose `.yekignore` and `.gitignore` apply
+    top: PathBuf,
+    /// Directory whose subdirectories' `yek.*` files apply (None for archives)
+    start: Option<PathBuf>,
+    /// `.git/info/exclude` and `core.excludesFile`, in that order
+    repo_excludes: Vec<Gitignore>,
+    /// The configured ignore patterns, built-in ones included
+    patterns: Arc<Gitignore>,
+    /// The ignore patterns from the config and the command line, which win over
+    /// ignore files
+    user_patterns: Arc<Gitignore>,
+    /// Ignore files of each directory seen so far
+    dirs: Mutex<HashMap<PathBuf, Arc<DirIgnores>>>,
+    /// `.gitattributes` of the same directories
+    attributes: GitAttributes,
+    /// The archive the ignore files are read from, when the rules are for one
+    archive: Option<Arc<ArchiveFileSystem>>,
+}
+
+/// The ignore files found in one directory
+struct DirIgnores {
+    yekignore: Option<Gitignore>,
+    gitignore: Option<Gitignore>,
+    /// The directory's `yek.*` file, for directories below `start`
+    config: Option<DirConfig>,
+}
+
+impl IgnoreRules {
+    /// Rules for the files below `start`. `.gitignore` files apply from the
+    /// top of the repository containing `start`, otherwise from `fallback_top`
+    /// when it contains `start`, otherwise from `start` itself.
+    pub fn new(start: &Path, fallback_top: &Path, patterns: Arc<Gitignore>) -> Self {
+        let repo = git2::Repository::discover(start).ok().and_then(|repo| {
+            // Rebuild from components to drop the trailing separator git2 leaves on workdir()
+            let workdir: PathBuf = repo.workdir()?.components().collect();
+            start.starts_with(&workdir).then_some((repo, workdir))
+        });
+
+        let (top, repo_excludes, attributes) = match repo {
+            Some((repo, workdir)) => {
+                let common_dir = common_git_dir(&repo);
+                let excludes = repo_exclude_files(&repo, &common_dir)
+                    .into_iter()
+                    .filter_map(|file| load_ignore_file(&workdir, &file))
+                    .collect();
+                let attributes = GitAttributes::new(&workdir, Some(&common_dir));
+                (workdir, excludes, attributes)
+            }
+            None => {
+                let top = if start.starts_with(fallback_top) {
+                    fallback_top
+                } else {
+                    start
+                };
+                (top.to_path_buf(), Vec::new(), GitAttributes::new(top, None))
+            }
+        };
+
+        Self {
+            top,
+            start: Some(start.to_path_buf()),
+            repo_excludes,
+            patterns,
+            user_patterns: Arc::new(Gitignore::empty()),
+            dirs: Mutex::new(HashMap::new()),
+            attributes,
+            archive: None,
+        }
+    }
+
+    /// Rules for the entries of an archive, using the `.yekignore` and
+    /// `.gitignore` files inside it
+    pub fn for_archive(archive: Arc<ArchiveFileSystem>, patterns: Arc<Gitignore>) -> Self {
+        let top = archive.root().to_path_buf();
+        Self {
+            attributes: GitAttributes::new(&top, None),
+            top,
+            start: None,
+            repo_excludes: Vec::new(),
+            patterns,
+            user_patterns: Arc::new(Gitignore::empty()),
+            dirs: Mutex::new(HashMap::new()),
+            archive: Some(archive),
+        }
+    }
+
+    /// Check `user_patterns` before any ignore file, so a `!` line cannot
+    /// re-include what the user left out
+    pub fn with_user_patterns(mut self, user_patterns: Arc<Gitignore>) -> Self {
+        self.user_patterns = user_patterns;
+        self
+    }
+
+    /// The configured patterns without the built-in ones, which
+    /// `YekConfig::apply_builtin_defaults` puts first
+    pub fn without_builtin(patterns: &[glob::Pattern]) -> &[glob::Pattern] {
+        let builtin = patterns.len() >= DEFAULT_IGNORE_PATTERNS.len()
+            && patterns
+                .iter()
+                .zip(DEFAULT_IGNORE_PATTERNS)
+                .all(|(pattern, default)| pattern.as_str() == *default);
+        if builtin {
+            &patterns[DEFAULT_IGNORE_PATTERNS.len()..]
+        } else {
+            patterns
+        }
+    }
+
+    /// Build a matcher for the configured ignore patterns, rooted at `root`
+    pub fn build_patterns(root: &Path, patterns: &[glob::Pattern]) -> Result<Gitignore> {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in patterns {
+            builder.add_line(None, pattern.as_str())?;
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Highest directory whose `.yekignore` and `.gitignore` apply
+    pub fn top(&self) -> &Path {
+        &self.top
+    }
+
+    /// The `.gitattributes` that apply to the same files
+    pub fn attributes(&self) -> &GitAttributes {
+        &self.attributes
+    }
+
+    /// The rule ignoring `path`, if any. Directories between the top and
+    /// `path` are checked too, since nothing inside an ignored directory can
+    /// be re-included.
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<IgnoreRule> {
+        if let Ok(rel) = path.strip_prefix(&self.top) {
+            let mut dir = self.top.clone();
+            let parents = rel.parent().map(|p| p.components().count()).unwrap_or(0);
+            for component in rel.components().take(parents) {
+                dir.push(component);
+                if let Some(rule) = self.matched_single(&dir, true) {
+                    return Some(rule);
+                }
+            }
+        }
+        self.matched_single(path, is_dir)
+    }
+
+    /// Check `path` itself, without looking at its parent directories
+    fn matched_single(&self, path: &Path, is_dir: bool) -> Option<IgnoreRule> {
+        if let Some(Some(rule)) = decide(&self.user_patterns, path, is_dir) {
+            return Some(rule);
+        }
+        let dirs: Vec<_> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.top))
+            .map(|dir| self.dir_ignores(dir))
+            .collect();
+        for matcher in dirs.iter().filter_map(|dir| dir.yekignore.as_ref()) {
+            if let Some(decision) = decide(matcher, path, is_dir) {
+                return decision;
+            }
+        }
+        if let Some(found) = IGNORE_ATTRIBUTES
+            .iter()
+            .find_map(|attr| self.attributes.set_by(path, is_dir, attr))
+        {
+            return Some(IgnoreRule {
+                pattern: found.line,
+                source: Some(found.source),
+            });
+        }
+        for matcher in dirs.iter().filter_map(|dir| dir.gitignore.as_ref()) {
+            if let Some(decision) = decide(matcher, path, is_dir) {
+                return decision;
+            }
+        }
+        let scoped = dirs
+            .iter()
+            .filter_map(|dir| dir.config.as_ref()?.ignore.as_ref());
+        for matcher in self
+            .repo_excludes
+            .iter()
+            .chain(scoped)
+            .chain([self.patterns.as_ref()])
+        {
+            if let Some(decision) = decide(matcher, path, is_dir) {
+                return decision;
+            }
+        }
+        None
+    }
+
+    /// Priority of `path` from the `priority_rules` of the per-directory
+    /// `yek.*` files above it, each matched against the path relative to its directory
+    pub fn scoped_priority(&self, path: &Path) -> i32 {
+        let Some(start) = &self.start else {
+            return 0;
+        };
+        path.ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(start) && *dir != start)
+            .filter_map(|dir| {
+                let ignores = self.dir_ignores(dir);
+                let config = ignores.config.as_ref()?;
+                let rel_path = path.strip_prefix(dir).ok()?.to_string_lossy();
+                Some(config.priority(&rel_path.replace('\\', "/")))
+            })
+            .sum()
+    }
+
+    /// The ignore files of `dir`, read once
+    fn dir_ignores(&self, dir: &Path) -> Arc<DirIgnores> {
+        let mut cache = self.dirs.lock().unwrap();
+        cache
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                let load = |name| match &self.archive {
+                    Some(archive) => load_archived_ignore_file(archive, dir, &dir.join(name)),
+                    None => load_ignore_file(dir, &dir.join(name)),
+                };
+                let below_start = self
+                    .start
+                    .as_ref()
+                    .is_some_and(|start| dir.starts_with(start) && dir != start);
+                Arc::new(DirIgnores {
+                    yekignore: load(".yekignore"),
+                    gitignore: load(".gitignore"),
+                    config: below_start.then(|| DirConfig::load(dir)).flatten(),
+                })
+            })
+            .clone()
+    }
+}
+
+/// Patterns selecting the files to include, in gitignore syntax. The last
+/// pattern matching a file or one of its directories decides, so a `!` line
+/// can take files back out of an earlier pattern.
+pub struct IncludePatterns {
+    patterns: Vec<Gitignore>,
+}
+
+impl IncludePatterns {
+    /// Patterns relative to `root`
+    pub fn new(root: &Path, patterns: &[glob::Pattern]) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| IgnoreRules::build_patterns(root, std::slice::from_ref(pattern)))
+            .collect::<Result<_>>()?;
+        Ok(Self { patterns })
+    }
+
+    /// Whether `path`, a file below the root, is selected
+    pub fn matches(&self, path: &Path) -> bool {
+        self.patterns
+            .iter()
+            .rev()
+            .find_map(
+                |pattern| match pattern.matched_path_or_any_parents(path, false) {
+                    Match::None => None,
+                    Match::Ignore(_) => Some(true),
+                    Match::Whitelist(_) => Some(false),
+                },
+            )
+            .unwrap_or(false)
+    }
+}
+
+/// `Some(rule)` if `matcher` ignores `path`, `Some(None)` if it re-includes it
+/// and `None` if no pattern matches
+fn decide(matcher: &Gitignore, path: &Path, is_dir: bool) -> Option<Option<IgnoreRule>> {
+    match matcher.matched(path, is_dir) {
+        Match::None => None,
+        Match::Whitelist(_) => Some(None),
+        Match::Ignore(glob) => Some(Some(IgnoreRule {
+            pattern: glob.original().to_string(),
+            source: glob.from().map(Path::to_path_buf),
+        })),
+    }
+}
+
+/// Matcher for an ignore file with patterns relative to `root`,
+/// or None if the file is missing or has no patterns
+fn load_ignore_file(root: &Path, file: &Path) -> Option<Gitignore> {
+    if !file.is_file() {
+        return None;
+    }
+    let mut builder = GitignoreBuilder::new(root);
+    if let Some(e) = builder.add(file) {
+        debug!("Problem reading {}: {}", file.display(), e);
+    }
+    match builder.build() {
+        Ok(matcher) if !matcher.is_empty() => Some(matcher),
+        Ok(_) => None,
+        Err(e) => {
+            debug!("Problem reading {}: {}", file.display(), e);
+            None
+        }
+    }
+}
+
+/// Same as [`load_ignore_file`] for an ignore file inside an archive
+fn load_archived_ignore_file(
+    archive: &ArchiveFileSystem,
+    root: &Path,
+    file: &Path,
+) -> Option<Gitignore> {
+    let content = archive.read_file(file).ok()?;
+    let mut builder = GitignoreBuilder::new(root);
+    for line in String::from_utf8_lossy(&content).lines() {
+        if let Err(e) = builder.add_line(Some(file.to_path_buf()), line) {
+            debug!("Problem reading {}: {}", file.display(), e);
+        }
+    }
+    builder.build().ok().filter(|matcher| !matcher.is_empty())
+}
+
+/// The git directory holding `info/`; linked worktrees share the main repository's
+fn common_git_dir(repo: &git2::Repository) -> PathBuf {
+    let git_dir = repo.path();
+    std::fs::read_to_string(git_dir.join("commondir"))
+        .map(|dir| git_dir.join(dir.trim()))
+        .unwrap_or_else(|_| git_dir.to_path_buf())
+}
+
+/// `.git/info/exclude` and the `core.excludesFile` of a repository
+fn repo_exclude_files(repo: &git2::Repository, common_dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![common_dir.join("info").join("exclude")];
+    let configured = repo
+        .config()
+        .and_then(|config| config.get_path("core.excludesFile"))
+        .ok();
+    let global = configured.or_else(|| {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|config_dir| config_dir.join("git").join("ignore"))
+    });
+    files.extend(global);
+    files
+}
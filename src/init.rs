@@ -0,0 +1,506 @@
+//! `yek init`: a starter config file tailored to a repository.
+//!
+//! The repository is scanned with the same ignore rules as a run. The file
+//! categories of [`crate::category`] give the mix of source, tests and docs,
+//! which sets `category_weights`; the directories holding most of the source
+//! get `priority_rules`; and vendored or generated directories that the
+//! built-in patterns and `.gitignore` miss are suggested as `ignore_patterns`.
+
+use crate::{
+    category::{categorize_file, CategoryWeights, FileCategory},
+    config::{find_config_in, ConfigFormat},
+    defaults::{BINARY_FILE_EXTENSIONS, DEFAULT_IGNORE_PATTERNS},
+    generated::{generated_marker, minified_reason},
+    ignore_rules::IgnoreRules,
+};
+use anyhow::{anyhow, Result};
+use bytesize::ByteSize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+/// Directory names holding third-party code
+const VENDORED_DIRS: &[&str] = &[
+    "third_party",
+    "third-party",
+    "thirdparty",
+    "external",
+    "extern",
+];
+
+/// Directory names holding generated code
+const GENERATED_DIRS: &[&str] = &["generated", "__generated__", "gen", "autogen"];
+
+/// Files a directory needs before its generated share is looked at
+const MIN_GENERATED_FILES: usize = 3;
+
+/// Share of generated or minified files from which a directory is suggested for ignoring
+const GENERATED_SHARE: f64 = 0.8;
+
+/// Top-level directories suggested for `priority_rules`
+const MAX_SOURCE_DIRS: usize = 3;
+
+/// Share of a directory's bytes that must be source for a priority rule
+const SOURCE_DIR_SHARE: f64 = 0.5;
+
+/// Share of the source in top-level directories a directory needs for a priority rule
+const MIN_SOURCE_SHARE: f64 = 0.1;
+
+/// Share of all bytes above which a category other than source gets half its weight
+const HEAVY_CATEGORY_SHARE: f64 = 1.0 / 3.0;
+
+/// Largest top-level directories listed in the file's comments
+const LARGEST_DIRS_SHOWN: usize = 5;
+
+/// The categories, in the order they are shown
+const CATEGORIES: [FileCategory; 5] = [
+    FileCategory::Source,
+    FileCategory::Test,
+    FileCategory::Documentation,
+    FileCategory::Configuration,
+    FileCategory::Other,
+];
+
+/// Arguments for `yek init`
+#[derive(clap::Args, Debug, Clone)]
+pub struct InitArgs {
+    /// Format of the config file to write
+    #[arg(long, value_enum, default_value = "yaml")]
+    pub format: ConfigFormat,
+
+    /// Overwrite an existing config file
+    #[arg(long)]
+    pub force: bool,
+
+    /// Repository to analyze; the config file is written there
+    #[arg(default_value = ".")]
+    pub path: PathBuf,
+}
+
+/// A directory suggested for `ignore_patterns`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedDir {
+    /// Path relative to the repository, with `/` separators
+    pub path: String,
+    /// Why it is suggested
+    pub reason: String,
+}
+
+/// What `yek init` found in a repository
+#[derive(Debug, Clone, Default)]
+pub struct RepoAnalysis {
+    /// Text files found
+    pub files: usize,
+    /// Bytes of text per category
+    pub category_bytes: BTreeMap<&'static str, u64>,
+    /// Top-level directories by bytes of text, largest first
+    pub largest_dirs: Vec<(String, u64)>,
+    /// Top-level directories holding most of the source, largest first
+    pub source_dirs: Vec<String>,
+    /// Vendored and generated directories not ignored yet
+    pub skipped_dirs: Vec<SkippedDir>,
+    /// Whether the repository has a `.gitignore` at its top
+    pub gitignore: bool,
+}
+
+/// Totals of the files directly in one directory
+#[derive(Default)]
+struct DirStats {
+    files: usize,
+    generated: usize,
+}
+
+/// Scan the text files below `root`, skipping what a run would ignore
+pub fn analyze(root: &Path) -> Result<RepoAnalysis> {
+    let defaults: Vec<glob::Pattern> = DEFAULT_IGNORE_PATTERNS
+        .iter()
+        .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+        .collect();
+    let patterns = Arc::new(IgnoreRules::build_patterns(root, &defaults)?);
+    let rules = IgnoreRules::new(root, root, patterns);
+
+    let mut analysis = RepoAnalysis {
+        gitignore: root.join(".gitignore").is_file(),
+        ..RepoAnalysis::default()
+    };
+    let mut scanned: Vec<(String, FileCategory, u64)> = Vec::new();
+    let mut dir_stats: BTreeMap<PathBuf, DirStats> = BTreeMap::new();
+    let mut named_dirs = Vec::new();
+
+    // Vendored and generated directories are not entered
+    let walker = WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            if entry.depth() == 0 {
+                return true;
+            }
+            let is_dir = entry.file_type().is_dir();
+            if entry.file_name().to_string_lossy().starts_with('.')
+                || rules.matched(entry.path(), is_dir).is_some()
+            {
+                return false;
+            }
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            let reason = if !is_dir {
+                None
+            } else if VENDORED_DIRS.contains(&name.as_str()) {
+                Some("vendored code")
+            } else if GENERATED_DIRS.contains(&name.as_str()) {
+                Some("generated code")
+            } else {
+                None
+            };
+            match reason {
+                Some(reason) => {
+                    named_dirs.push(SkippedDir {
+                        path: rel_path(entry.path(), root),
+                        reason: reason.to_string(),
+                    });
+                    false
+                }
+                None => true,
+            }
+        });
+    for entry in walker.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        let is_binary = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| BINARY_FILE_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+        if is_binary || !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(path) else {
+            continue;
+        };
+
+        let rel = rel_path(path, root);
+        let category = categorize_file(&rel);
+        scanned.push((rel, category, content.len() as u64));
+        let stats = dir_stats
+            .entry(path.parent().unwrap_or(root).to_path_buf())
+            .or_default();
+        stats.files += 1;
+        if generated_marker(&content).is_some() || minified_reason(&content).is_some() {
+            stats.generated += 1;
+        }
+    }
+
+    analysis.skipped_dirs = named_dirs;
+    for (dir, stats) in dir_stats {
+        if dir != root
+            && stats.files >= MIN_GENERATED_FILES
+            && stats.generated as f64 >= stats.files as f64 * GENERATED_SHARE
+        {
+            analysis.skipped_dirs.push(SkippedDir {
+                path: rel_path(&dir, root),
+                reason: format!("{} of {} files generated", stats.generated, stats.files),
+            });
+        }
+    }
+    analysis.skipped_dirs.sort_by(|a, b| a.path.cmp(&b.path));
+
+    // The mix is that of the files left once the suggestions are applied
+    let mut dir_bytes: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    for (rel, category, bytes) in scanned {
+        let skipped = analysis
+            .skipped_dirs
+            .iter()
+            .any(|dir| rel.starts_with(&format!("{}/", dir.path)));
+        if skipped {
+            continue;
+        }
+        analysis.files += 1;
+        *analysis.category_bytes.entry(category.name()).or_default() += bytes;
+        if let Some((top, _)) = rel.split_once('/') {
+            let totals = dir_bytes.entry(top.to_string()).or_default();
+            totals.0 += bytes;
+            if category == FileCategory::Source {
+                totals.1 += bytes;
+            }
+        }
+    }
+
+    let mut largest: Vec<(String, u64, u64)> = dir_bytes
+        .into_iter()
+        .map(|(dir, (bytes, source))| (dir, bytes, source))
+        .collect();
+    largest.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let total_source: u64 = largest.iter().map(|(_, _, source)| source).sum();
+    analysis.source_dirs = largest
+        .iter()
+        .filter(|(_, bytes, source)| {
+            *source > 0
+                && *source as f64 >= *bytes as f64 * SOURCE_DIR_SHARE
+                && *source as f64 >= total_source as f64 * MIN_SOURCE_SHARE
+        })
+        .take(MAX_SOURCE_DIRS)
+        .map(|(dir, _, _)| dir.clone())
+        .collect();
+    analysis.largest_dirs = largest
+        .into_iter()
+        .take(LARGEST_DIRS_SHOWN)
+        .map(|(dir, bytes, _)| (dir, bytes))
+        .collect();
+    Ok(analysis)
+}
+
+impl RepoAnalysis {
+    /// Total bytes of text
+    pub fn total_bytes(&self) -> u64 {
+        self.category_bytes.values().sum()
+    }
+
+    /// Share of the bytes in `category`, from 0 to 1
+    pub fn share(&self, category: FileCategory) -> f64 {
+        let total = self.total_bytes();
+        if total == 0 {
+            return 0.0;
+        }
+        self.category_bytes
+            .get(category.name())
+            .copied()
+            .unwrap_or(0) as f64
+            / total as f64
+    }
+
+    /// Suggested `ignore_patterns`
+    pub fn ignore_patterns(&self) -> Vec<String> {
+        self.skipped_dirs
+            .iter()
+            .map(|dir| format!("{}/**", dir.path))
+            .collect()
+    }
+
+    /// Suggested `priority_rules`, as pattern and score
+    pub fn priority_rules(&self) -> Vec<(String, i32)> {
+        self.source_dirs
+            .iter()
+            .map(|dir| (format!("^{}/", regex::escape(dir)), 100))
+            .collect()
+    }
+
+    /// The default weights, with categories other than source that take a
+    /// large share of the repository halved, so they do not crowd out the source
+    pub fn category_weights(&self) -> CategoryWeights {
+        let mut weights = CategoryWeights::default();
+        for category in CATEGORIES {
+            if category == FileCategory::Source || self.share(category) <= HEAVY_CATEGORY_SHARE {
+                continue;
+            }
+            let weight = match category {
+                FileCategory::Test => &mut weights.test,
+                FileCategory::Documentation => &mut weights.documentation,
+                FileCategory::Configuration => &mut weights.configuration,
+                FileCategory::Other => &mut weights.other,
+                FileCategory::Source => unreachable!(),
+            };
+            *weight /= 2;
+        }
+        weights
+    }
+
+    /// Comment lines describing the analysis, without comment markers
+    fn summary(&self) -> Vec<String> {
+        let mix: Vec<String> = CATEGORIES
+            .iter()
+            .filter(|category| self.category_bytes.contains_key(category.name()))
+            .map(|category| format!("{} {:.0}%", category.name(), self.share(*category) * 100.0))
+            .collect();
+        let mut lines = vec![format!(
+            "{} text files, {}: {}",
+            self.files,
+            ByteSize(self.total_bytes()),
+            if mix.is_empty() {
+                "nothing to analyze".to_string()
+            } else {
+                mix.join(", ")
+            }
+        )];
+        if !self.largest_dirs.is_empty() {
+            let dirs: Vec<String> = self
+                .largest_dirs
+                .iter()
+                .map(|(dir, bytes)| format!("{}/ ({})", dir, ByteSize(*bytes)))
+                .collect();
+            lines.push(format!("Largest directories: {}", dirs.join(", ")));
+        }
+        lines.push(if self.gitignore {
+            ".gitignore is respected, so its patterns are not repeated here".to_string()
+        } else {
+            "No .gitignore found: add build outputs and other untracked files to ignore_patterns"
+                .to_string()
+        });
+        lines
+    }
+
+    /// The config file in `format`, with comments where the format allows them
+    pub fn render(&self, format: &ConfigFormat) -> String {
+        match format {
+            ConfigFormat::Yaml => self.render_yaml(),
+            ConfigFormat::Toml => self.render_toml(),
+            ConfigFormat::Json => self.render_json(),
+        }
+    }
+
+    fn render_yaml(&self) -> String {
+        let mut out = String::from("# Generated by `yek init`\n");
+        for line in self.summary() {
+            let _ = writeln!(out, "# {}", line);
+        }
+
+        out.push_str(
+            "\n# Vendored and generated directories (in addition to the built-in patterns)\n",
+        );
+        if self.skipped_dirs.is_empty() {
+            out.push_str("ignore_patterns: []\n");
+        } else {
+            out.push_str("ignore_patterns:\n");
+            for (pattern, dir) in self.ignore_patterns().iter().zip(&self.skipped_dirs) {
+                let _ = writeln!(out, "  - {} # {}", quote(pattern), dir.reason);
+            }
+        }
+
+        out.push_str(
+            "\n# Directories holding most of the source; higher scores come later in the output\n",
+        );
+        let rules = self.priority_rules();
+        if rules.is_empty() {
+            out.push_str("priority_rules: []\n");
+        } else {
+            out.push_str("priority_rules:\n");
+            for (pattern, score) in rules {
+                let _ = writeln!(
+                    out,
+                    "  - pattern: {}\n    score: {}",
+                    quote(&pattern),
+                    score
+                );
+            }
+        }
+
+        out.push_str("\n# Priority offset of each file category\n");
+        out.push_str("category_weights:\n");
+        for (name, weight) in weight_entries(&self.category_weights()) {
+            let _ = writeln!(out, "  {}: {}", name, weight);
+        }
+        out
+    }
+
+    fn render_toml(&self) -> String {
+        let mut out = String::from("# Generated by `yek init`\n");
+        for line in self.summary() {
+            let _ = writeln!(out, "# {}", line);
+        }
+
+        out.push_str(
+            "\n# Vendored and generated directories (in addition to the built-in patterns)\n",
+        );
+        if self.skipped_dirs.is_empty() {
+            out.push_str("ignore_patterns = []\n");
+        } else {
+            out.push_str("ignore_patterns = [\n");
+            for (pattern, dir) in self.ignore_patterns().iter().zip(&self.skipped_dirs) {
+                let _ = writeln!(out, "  {}, # {}", quote(pattern), dir.reason);
+            }
+            out.push_str("]\n");
+        }
+
+        out.push_str(
+            "\n# Directories holding most of the source; higher scores come later in the output\n",
+        );
+        let rules = self.priority_rules();
+        if rules.is_empty() {
+            out.push_str("priority_rules = []\n");
+        }
+        for (pattern, score) in rules {
+            let _ = writeln!(
+                out,
+                "[[priority_rules]]\npattern = {}\nscore = {}\n",
+                quote(&pattern),
+                score
+            );
+        }
+
+        out.push_str("\n# Priority offset of each file category\n");
+        out.push_str("[category_weights]\n");
+        for (name, weight) in weight_entries(&self.category_weights()) {
+            let _ = writeln!(out, "{} = {}", name, weight);
+        }
+        out
+    }
+
+    fn render_json(&self) -> String {
+        let rules: Vec<serde_json::Value> = self
+            .priority_rules()
+            .into_iter()
+            .map(|(pattern, score)| serde_json::json!({"pattern": pattern, "score": score}))
+            .collect();
+        let config = serde_json::json!({
+            "ignore_patterns": self.ignore_patterns(),
+            "priority_rules": rules,
+            "category_weights": self.category_weights(),
+        });
+        serde_json::to_string_pretty(&config).expect("config serializes") + "\n"
+    }
+}
+
+/// Analyze the repository and write its config file, returning the file's path
+pub fn run(args: &InitArgs) -> Result<PathBuf> {
+    if !args.path.is_dir() {
+        return Err(anyhow!(
+            "init: '{}' is not a directory",
+            args.path.display()
+        ));
+    }
+    let ext = match args.format {
+        ConfigFormat::Toml => "toml",
+        ConfigFormat::Yaml => "yaml",
+        ConfigFormat::Json => "json",
+    };
+    let target = args.path.join(format!("yek.{}", ext));
+    if let Some(existing) = find_config_in(&args.path, "yek") {
+        if !args.force {
+            return Err(anyhow!(
+                "init: '{}' already exists (use --force to overwrite)",
+                existing.display()
+            ));
+        }
+        if existing != target {
+            eprintln!(
+                "Warning: '{}' is also present; only one yek.* file per directory is read",
+                existing.display()
+            );
+        }
+    }
+
+    let analysis = analyze(&args.path)?;
+    std::fs::write(&target, analysis.render(&args.format))
+        .map_err(|e| anyhow!("init: cannot write '{}': {}", target.display(), e))?;
+    Ok(target)
+}
+
+fn weight_entries(weights: &CategoryWeights) -> [(&'static str, i32); 5] {
+    [
+        ("source", weights.source),
+        ("test", weights.test),
+        ("configuration", weights.configuration),
+        ("documentation", weights.documentation),
+        ("other", weights.other),
+    ]
+}
+
+/// `value` as a double-quoted string, valid in YAML and TOML
+fn quote(value: &str) -> String {
+    serde_json::Value::String(value.to_string()).to_string()
+}
+
+fn rel_path(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
@@ -1,10 +1,9 @@
 use anyhow::anyhow;
 use anyhow::Result;
 use bytesize::ByteSize;
-use content_inspector::{inspect, ContentType};
-use rayon::prelude::*;
+use sha2::{Digest, Sha256};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs::File,
     io::{self, Read},
     path::Path,
@@ -13,29 +12,52 @@ use std::{
 };
 use tiktoken_rs::CoreBPE;
 
+pub mod api;
 pub mod category;
 pub mod config;
+pub mod dedupe;
 pub mod defaults;
+pub mod dir_config;
+pub mod encoding;
 pub mod error;
+pub mod generated;
+pub mod headers;
+pub mod ignore_rules;
+pub mod init;
+pub mod manifest;
+pub mod mcp;
 pub mod models;
+pub mod notebook;
 pub mod parallel;
 pub mod pipeline;
 pub mod priority;
 pub mod repository;
+pub mod roots;
+pub mod serve;
+pub mod stages;
+pub mod summarize;
 pub mod tree;
 
+pub use api::{Budget, OutputFormat, Stats, Yek, YekBuilder, YekOutput};
 use config::YekConfig;
-use models::ProcessedFile;
-use parallel::process_files_parallel;
-use priority::compute_recentness_boost;
-use tree::generate_tree;
+use dedupe::find_duplicates;
+use headers::{strip_headers, RemovedHeader};
+use models::{BundleEntry, ExcludedFile, ExclusionReason, ProcessedFile, StageStats};
+use pipeline::{
+    CategoryWeightingStage, FileDiscoveryStage, ProcessingContext, ProcessingPipeline,
+    ProcessingStage,
+};
+use tree::generate_tree_with_symlinks;
+
+/// Model whose tokenizer is used for token counting
+pub const TOKENIZER_MODEL: &str = "gpt-3.5-turbo";
 
 // Add a static BPE encoder for reuse
 static TOKENIZER: OnceLock<CoreBPE> = OnceLock::new();
 
 fn get_tokenizer() -> &'static CoreBPE {
     TOKENIZER.get_or_init(|| {
-        tiktoken_rs::get_bpe_from_model("gpt-3.5-turbo").expect("Failed to load tokenizer")
+        tiktoken_rs::get_bpe_from_model(TOKENIZER_MODEL).expect("Failed to load tokenizer")
     })
 }
 
@@ -56,11 +78,78 @@ pub fn is_text_file(path: &Path, user_binary_extensions: &[String]) -> io::Resul
     let n = file.read(&mut buf)?;
     buf.truncate(n);
 
-    Ok(inspect(&buf) != ContentType::BINARY)
+    Ok(!encoding::is_binary(&buf))
+}
+
+/// Everything produced by a single serialization run
+#[derive(Debug, Clone)]
+pub struct SerializationReport {
+    /// Final rendered output
+    pub output: String,
+    /// All processed files, sorted by priority
+    pub files: Vec<ProcessedFile>,
+    /// Position of each included file inside `output`
+    pub entries: Vec<BundleEntry>,
+    /// Files that were seen but left out of `output`, sorted by path
+    pub excluded: Vec<ExcludedFile>,
+    /// Checksum of the included files and the settings that shaped `output`
+    pub checksum: String,
+    /// Problems worth telling the user about, such as input paths that do not exist
+    pub warnings: Vec<String>,
+    /// Numbers for each pipeline stage, in the order they ran
+    pub stages: Vec<StageStats>,
+    /// Bytes saved by rendering duplicate files as a note
+    pub bytes_saved: usize,
+    /// Tokens saved by rendering duplicate files as a note (only counted with a token budget)
+    pub tokens_saved: Option<usize>,
+}
+
+/// What the tree header shows besides the selected files
+#[derive(Debug, Clone, Default)]
+pub struct TreeExtras {
+    /// Paths of skipped files that are listed anyway
+    pub paths: Vec<String>,
+    /// Symbolic links followed during discovery, by relative path, with their targets
+    pub symlinks: BTreeMap<String, String>,
 }
 
-/// Main entrypoint for serialization, used by CLI and tests
+/// Rendered output along with the layout of the files it contains
+#[derive(Debug, Clone, Default)]
+pub struct RenderedBundle {
+    /// Final rendered output
+    pub output: String,
+    /// Position of each included file inside `output`
+    pub entries: Vec<BundleEntry>,
+    /// Files that did not fit in the size or token budget
+    pub over_budget: Vec<String>,
+    /// Bytes saved by rendering duplicate files as a note
+    pub bytes_saved: usize,
+    /// Tokens saved by rendering duplicate files as a note (only counted with a token budget)
+    pub tokens_saved: Option<usize>,
+}
+
+/// Main entrypoint for serialization, used by CLI and tests.
+/// Warnings are printed to stderr; use [`serialize_repo_with_report`] to handle them yourself.
 pub fn serialize_repo(config: &YekConfig) -> Result<(String, Vec<ProcessedFile>)> {
+    let report = serialize_repo_with_report(config)?;
+    for warning in &report.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    Ok((report.output, report.files))
+}
+
+/// Serialize the configured inputs and report where each file ended up.
+/// Nothing is printed; warnings are returned in the report.
+pub fn serialize_repo_with_report(config: &YekConfig) -> Result<SerializationReport> {
+    serialize_repo_with_stages(config, Vec::new())
+}
+
+/// Same as [`serialize_repo_with_report`], running `extra_stages` on the discovered files
+/// after the stages from the config. Stages can rewrite, drop or reprioritize files.
+pub fn serialize_repo_with_stages(
+    config: &YekConfig,
+    extra_stages: Vec<Box<dyn ProcessingStage>>,
+) -> Result<SerializationReport> {
     // Validate input paths and warn about non-existent ones
     let mut non_existent_paths = Vec::new();
 
@@ -73,87 +162,167 @@ pub fn serialize_repo(config: &YekConfig) -> Result<(String, Vec<ProcessedFile>)
     }
 
     // If we have non-existent paths, warn the user
-    if !non_existent_paths.is_empty() {
-        for path in &non_existent_paths {
-            eprintln!("Warning: Path '{}' does not exist", path);
+    let mut warnings: Vec<String> = non_existent_paths
+        .iter()
+        .map(|path| format!("Path '{}' does not exist", path))
+        .collect();
+
+    // Discover, read and prioritize files, then run the configured and extra stages
+    let context = ProcessingContext::from_config(config);
+    let mut pipeline = ProcessingPipeline::with_stages(
+        context,
+        vec![
+            Box::new(FileDiscoveryStage::new()),
+            Box::new(CategoryWeightingStage),
+        ],
+    );
+    for stage in stages::build_stages(&config.stages)?
+        .into_iter()
+        .chain(extra_stages)
+    {
+        pipeline.add_stage(stage);
+    }
+    let mut files = pipeline.process()?;
+    // Headers are taken out before rendering so they count toward the budget only once
+    let headers = strip_headers(
+        &mut files,
+        config.strip_headers,
+        &config.boilerplate_regexes()?,
+    );
+    let mut excluded = pipeline.get_excluded_files();
+    let stage_stats = pipeline.get_stats().stages;
+    for stats in &stage_stats {
+        if let Some(first) = stats.errors.first() {
+            warnings.push(format!(
+                "Stage '{}' failed on {} file(s), keeping their content. First error: {}",
+                stats.name,
+                stats.errors.len(),
+                first
+            ));
         }
     }
 
-    // Gather commit times from each input path that is a directory
-    let combined_commit_times = config
-        .input_paths
-        .par_iter()
-        .filter_map(|path_str| {
-            let repo_path = Path::new(path_str);
-            if repo_path.is_dir() {
-                priority::get_recent_commit_times_git2(
-                    repo_path,
-                    config.max_git_depth.try_into().unwrap_or(0),
-                )
-            } else {
-                None
-            }
-        })
-        .flatten()
-        .collect::<HashMap<String, u64>>();
-
-    // Compute a recentness-based boost
-    let recentness_boost =
-        compute_recentness_boost(&combined_commit_times, config.git_boost_max.unwrap_or(100));
-
-    // Process files in parallel for each input path
-    let merged_files = config
-        .input_paths
-        .par_iter()
-        .map(|path_str| {
-            let path = Path::new(path_str);
-            process_files_parallel(path, config, &recentness_boost)
-        })
-        .collect::<Result<Vec<Vec<ProcessedFile>>>>()?
-        .into_iter()
-        .flatten()
-        .collect::<Vec<ProcessedFile>>();
-
-    let mut files = merged_files;
-
-    // Sort final (priority asc, then file_index asc)
-    files.par_sort_by(|a, b| {
-        a.priority
-            .cmp(&b.priority)
-            .then_with(|| a.rel_path.cmp(&b.rel_path))
-    });
-
     // If no files were processed and we had non-existent paths, provide additional context
     if files.is_empty() && !non_existent_paths.is_empty() {
-        eprintln!("Warning: No files were processed. All specified paths were non-existent or contained no valid files.");
+        warnings.push("No files were processed. All specified paths were non-existent or contained no valid files.".to_string());
     }
 
     // Build the final output string
-    let output_string = concat_files(&files, config)?;
+    let tree = TreeExtras {
+        paths: if config.tree_include_too_large {
+            excluded
+                .iter()
+                .filter(|e| e.reason == ExclusionReason::TooLarge)
+                .map(|e| e.rel_path.clone())
+                .collect()
+        } else {
+            Vec::new()
+        },
+        symlinks: pipeline.get_symlinks(),
+    };
+    let bundle = render_bundle_with_headers(&files, &tree, &headers, config)?;
 
     // Only count tokens if debug logging is enabled
     if tracing::Level::DEBUG <= tracing::level_filters::STATIC_MAX_LEVEL {
-        tracing::debug!("{} tokens generated", count_tokens(&output_string));
+        tracing::debug!("{} tokens generated", count_tokens(&bundle.output));
     }
 
-    Ok((output_string, files))
+    excluded.extend(
+        bundle
+            .over_budget
+            .iter()
+            .map(|rel_path| ExcludedFile::new(rel_path.clone(), ExclusionReason::OverBudget)),
+    );
+    excluded.sort_by(|a, b| a.rel_path.cmp(&b.rel_path));
+
+    // The files written to the bundle, in the order they were rendered
+    let included: Vec<&ProcessedFile> = if config.tree_only {
+        files.iter().collect()
+    } else {
+        let by_path: HashMap<&str, &ProcessedFile> =
+            files.iter().map(|f| (f.rel_path.as_str(), f)).collect();
+        bundle
+            .entries
+            .iter()
+            .filter_map(|entry| by_path.get(entry.rel_path.as_str()).copied())
+            .collect()
+    };
+    let checksum = output_checksum(&included, config)?;
+
+    Ok(SerializationReport {
+        output: bundle.output,
+        files,
+        entries: bundle.entries,
+        excluded,
+        checksum,
+        warnings,
+        stages: stage_stats,
+        bytes_saved: bundle.bytes_saved,
+        tokens_saved: bundle.tokens_saved,
+    })
+}
+
+/// Checksum over the paths and contents of the selected files plus the effective config.
+/// Identical inputs always produce the same checksum, regardless of file timestamps.
+pub fn output_checksum(files: &[&ProcessedFile], config: &YekConfig) -> Result<String> {
+    let mut hasher = Sha256::new();
+    for file in files {
+        hasher.update(file.rel_path.as_bytes());
+        hasher.update([0]);
+        hasher.update(file.content.as_bytes());
+        hasher.update([0]);
+    }
+    hasher.update(config.checksum_settings()?.as_bytes());
+
+    let hex = format!("{:x}", hasher.finalize());
+    Ok(hex[..8].to_owned())
 }
 
 pub fn concat_files(files: &[ProcessedFile], config: &YekConfig) -> anyhow::Result<String> {
+    Ok(render_bundle(files, config)?.output)
+}
+
+/// Render files into the final output, keeping track of where each file lands
+pub fn render_bundle(files: &[ProcessedFile], config: &YekConfig) -> Result<RenderedBundle> {
+    render_bundle_with_tree(files, &TreeExtras::default(), config)
+}
+
+/// Same as [`render_bundle`], adding `tree` to the tree header
+pub fn render_bundle_with_tree(
+    files: &[ProcessedFile],
+    tree: &TreeExtras,
+    config: &YekConfig,
+) -> Result<RenderedBundle> {
+    render_bundle_with_headers(files, tree, &[], config)
+}
+
+/// Same as [`render_bundle_with_tree`], showing `headers` removed from the files
+/// once before them
+pub fn render_bundle_with_headers(
+    files: &[ProcessedFile],
+    tree: &TreeExtras,
+    headers: &[RemovedHeader],
+    config: &YekConfig,
+) -> Result<RenderedBundle> {
     // Generate tree header if requested
     let tree_header = if config.tree_header || config.tree_only {
         let file_paths: Vec<std::path::PathBuf> = files
             .iter()
-            .map(|f| std::path::PathBuf::from(&f.rel_path))
+            .map(|f| &f.rel_path)
+            .chain(&tree.paths)
+            .map(std::path::PathBuf::from)
             .collect();
-        generate_tree(&file_paths)
+        generate_tree_with_symlinks(&file_paths, &tree.symlinks)
     } else {
         String::new()
     };
 
     // If tree_only is requested, return just the tree
     if config.tree_only {
-        return Ok(tree_header);
+        return Ok(RenderedBundle {
+            output: tree_header,
+            ..Default::default()
+        });
     }
 
     let mut accumulated = 0_usize;
@@ -178,6 +347,18 @@ pub fn concat_files(files: &[ProcessedFile], config: &YekConfig) -> anyhow::Resu
 
     accumulated += tree_header_size;
 
+    let header_texts: Vec<String> = headers
+        .iter()
+        .map(|header| render_header(header, config))
+        .collect::<Result<_>>()?;
+    for text in &header_texts {
+        accumulated += if config.token_mode {
+            count_tokens(text)
+        } else {
+            text.len()
+        };
+    }
+
     // Sort by priority (asc) and file_index (asc)
     let mut sorted_files: Vec<_> = files.iter().collect();
     sorted_files.sort_by(|a, b| {
@@ -186,82 +367,214 @@ pub fn concat_files(files: &[ProcessedFile], config: &YekConfig) -> anyhow::Resu
             .then_with(|| a.rel_path.cmp(&b.rel_path))
     });
 
+    // Files repeating an earlier file are rendered as a note pointing at it
+    let duplicates = find_duplicates(
+        &sorted_files
+            .iter()
+            .map(|f| (f.rel_path.as_str(), f.content.as_str()))
+            .collect::<Vec<_>>(),
+        config.dedupe,
+    );
+    let duplicate_of: Vec<Option<&str>> = duplicates
+        .iter()
+        .map(|d| d.as_ref().map(|d| sorted_files[d.of()].rel_path.as_str()))
+        .collect();
+    let bodies: Vec<String> = sorted_files
+        .iter()
+        .zip(duplicates.iter().zip(&duplicate_of))
+        .map(|(file, duplicate)| match duplicate {
+            (Some(duplicate), Some(original)) => duplicate.note(original),
+            _ => format_content_with_line_numbers(&file.content, config.line_numbers),
+        })
+        .collect();
+
     let mut files_to_include = Vec::new();
-    for file in sorted_files {
+    let mut token_counts = Vec::new();
+    let mut over_budget = Vec::new();
+    let mut bytes_saved = 0;
+    let mut tokens_saved = config.token_mode.then_some(0);
+    for (i, file) in sorted_files.into_iter().enumerate() {
+        if !over_budget.is_empty() {
+            over_budget.push(file.rel_path.clone());
+            continue;
+        }
+
+        let content = &bodies[i];
         let content_size = if config.token_mode {
             // Format the file content with template first, then count tokens
-            let content = format_content_with_line_numbers(&file.content, config.line_numbers);
             let formatted = if config.json {
-                serde_json::to_string(&serde_json::json!({
-                    "filename": &file.rel_path,
-                    "content": content,
-                }))
-                .map_err(|e| anyhow!("Failed to serialize JSON: {}", e))?
+                serde_json::to_string(&json_entry(&file.rel_path, content, duplicate_of[i]))
+                    .map_err(|e| anyhow!("Failed to serialize JSON: {}", e))?
             } else {
                 config
                     .output_template
                     .as_ref()
                     .expect("output_template should be set")
                     .replace("FILE_PATH", &file.rel_path)
-                    .replace("FILE_CONTENT", &content)
+                    .replace("FILE_CONTENT", content)
                     // Handle both literal "\n" and escaped "\\n"
                     .replace("\\\\\n", "\n") // First handle escaped newline
                     .replace("\\\\n", "\n") // Then handle escaped \n sequence
             };
             count_tokens(&formatted)
         } else {
-            let content = format_content_with_line_numbers(&file.content, config.line_numbers);
             content.len()
         };
 
         if accumulated + content_size <= cap {
             accumulated += content_size;
-            files_to_include.push(file);
+            if duplicate_of[i].is_some() {
+                let full = format_content_with_line_numbers(&file.content, config.line_numbers);
+                bytes_saved += full.len().saturating_sub(content.len());
+                if let Some(saved) = tokens_saved.as_mut() {
+                    *saved += count_tokens(&full).saturating_sub(count_tokens(content));
+                }
+            }
+            files_to_include.push((file, content, duplicate_of[i]));
+            token_counts.push(config.token_mode.then_some(content_size));
         } else {
-            break;
+            over_budget.push(file.rel_path.clone());
         }
     }
 
-    let main_content = if config.json {
-        // JSON array of objects
-        serde_json::to_string_pretty(
-            &files_to_include
-                .iter()
-                .map(|f| {
-                    let content = format_content_with_line_numbers(&f.content, config.line_numbers);
-                    serde_json::json!({
-                        "filename": &f.rel_path,
-                        "content": content,
+    let rendered: Vec<String> = if config.json {
+        files_to_include
+            .iter()
+            .map(|(f, content, duplicate_of)| {
+                serde_json::to_string_pretty(&json_entry(&f.rel_path, content, *duplicate_of))
+                    // Indent each object the same way a pretty-printed array would
+                    .map(|object| {
+                        object
+                            .lines()
+                            .map(|line| format!("  {}", line))
+                            .collect::<Vec<_>>()
+                            .join("\n")
                     })
-                })
-                .collect::<Vec<_>>(),
-        )?
+            })
+            .collect::<serde_json::Result<Vec<_>>>()?
     } else {
         // Use the user-defined template
         files_to_include
             .iter()
-            .map(|f| {
-                let content = format_content_with_line_numbers(&f.content, config.line_numbers);
+            .map(|(f, content, _)| {
                 config
                     .output_template
                     .as_ref()
                     .expect("output_template should be set")
                     .replace("FILE_PATH", &f.rel_path)
-                    .replace("FILE_CONTENT", &content)
+                    .replace("FILE_CONTENT", content)
                     // Handle both literal "\n" and escaped "\\n"
                     .replace("\\\\\n", "\n") // First handle escaped newline
                     .replace("\\\\n", "\n") // Then handle escaped \n sequence
             })
-            .collect::<Vec<_>>()
-            .join("\n")
+            .collect()
     };
 
-    // Combine tree header with main content
-    if config.tree_header {
-        Ok(format!("{}{}", tree_header, main_content))
+    // JSON output is an array of objects, text output joins entries with newlines.
+    // Removed headers come first, as entries of their own
+    let (prefix, separator, suffix) = if config.json {
+        if rendered.is_empty() && header_texts.is_empty() {
+            ("[".to_string(), "", "]")
+        } else {
+            let mut prefix = format!("[\n{}", header_texts.join(",\n"));
+            if !header_texts.is_empty() && !rendered.is_empty() {
+                prefix.push_str(",\n");
+            }
+            (prefix, ",\n", "\n]")
+        }
     } else {
-        Ok(main_content)
+        let mut prefix = if config.tree_header {
+            tree_header
+        } else {
+            String::new()
+        };
+        for text in &header_texts {
+            prefix.push_str(text);
+            prefix.push('\n');
+        }
+        (prefix, "\n", "")
+    };
+
+    let mut output = String::with_capacity(
+        prefix.len()
+            + rendered
+                .iter()
+                .map(|r| r.len() + separator.len())
+                .sum::<usize>(),
+    );
+    output.push_str(&prefix);
+    let mut line = 1 + prefix.matches('\n').count();
+    let mut entries = Vec::with_capacity(rendered.len());
+    for (i, ((text, (file, _, duplicate_of)), tokens)) in rendered
+        .iter()
+        .zip(&files_to_include)
+        .zip(token_counts)
+        .enumerate()
+    {
+        if i > 0 {
+            output.push_str(separator);
+            line += separator.matches('\n').count();
+        }
+        let line_span = text.trim_end_matches('\n').matches('\n').count();
+        entries.push(BundleEntry {
+            rel_path: file.rel_path.clone(),
+            byte_start: output.len(),
+            byte_end: output.len() + text.len(),
+            line_start: line,
+            line_end: line + line_span,
+            tokens,
+            encoding: file.encoding.clone(),
+            duplicate_of: duplicate_of.map(str::to_string),
+        });
+        output.push_str(text);
+        line += text.matches('\n').count();
+    }
+    output.push_str(suffix);
+
+    Ok(RenderedBundle {
+        output,
+        entries,
+        over_budget,
+        bytes_saved,
+        tokens_saved,
+    })
+}
+
+/// A removed header as shown before the files
+fn render_header(header: &RemovedHeader, config: &YekConfig) -> Result<String> {
+    if config.json {
+        let object = serde_json::to_string_pretty(&serde_json::json!({
+            "header": &header.text,
+            "removed_from": &header.files,
+        }))
+        .map_err(|e| anyhow!("Failed to serialize JSON: {}", e))?;
+        return Ok(object
+            .lines()
+            .map(|line| format!("  {}", line))
+            .collect::<Vec<_>>()
+            .join("\n"));
+    }
+    let title = format!("[header removed from {} files]", header.files.len());
+    Ok(config
+        .output_template
+        .as_ref()
+        .expect("output_template should be set")
+        .replace("FILE_PATH", &title)
+        .replace("FILE_CONTENT", &header.text)
+        .replace("\\\\\n", "\n")
+        .replace("\\\\n", "\n"))
+}
+
+/// A file as an object of the JSON output
+fn json_entry(rel_path: &str, content: &str, duplicate_of: Option<&str>) -> serde_json::Value {
+    let mut entry = serde_json::json!({
+        "filename": rel_path,
+        "content": content,
+    });
+    if let Some(original) = duplicate_of {
+        entry["duplicate_of"] = original.into();
     }
+    entry
 }
 
 /// Format file content with line numbers if requested
@@ -1,14 +1,35 @@
 use anyhow::Result;
 use bytesize::ByteSize;
-use rayon::join;
 use std::path::Path;
 use tracing::{debug, Level};
 use tracing_subscriber::fmt;
-use yek::{config::YekConfig, serialize_repo};
+use yek::{
+    config::{ConfigCommand, YekArgs, YekCommand, YekConfig},
+    init,
+    manifest::{manifest_path_for, Manifest},
+    mcp, serialize_repo_with_report, serve, SerializationReport,
+};
 
 fn main() -> Result<()> {
+    let args = YekArgs::from_command_line();
+    let run = match args.command {
+        Some(YekCommand::Mcp(args)) => return mcp::run_stdio(&args),
+        Some(YekCommand::Serve(args)) => return serve::run(&args),
+        Some(YekCommand::Init(args)) => {
+            println!("{}", init::run(&args)?.display());
+            return Ok(());
+        }
+        // Takes the same options as a run, and prints the config it would use
+        Some(YekCommand::Config(ConfigCommand::Show(run))) => {
+            let (config, origins) = run.into_config(true);
+            print!("{}", config.describe(&origins)?);
+            return Ok(());
+        }
+        None => args.run,
+    };
+
     // 1) Parse CLI + config files:
-    let mut full_config = YekConfig::init_config();
+    let mut full_config = run.into_config(false).0;
 
     let env_filter = if full_config.debug {
         "yek=debug,ignore=off"
@@ -38,10 +59,12 @@ fn main() -> Result<()> {
         debug!("Configuration:\n{}", config_str);
     }
 
-    // If streaming => skip checksum + read. Just do single-thread call to serialize_repo.
-    // If not streaming => run checksum + repo serialization in parallel.
+    // If streaming => print the output (or write it to output_name).
+    // If not streaming => write to a file named after the output checksum.
     if full_config.stream {
-        let (output, files) = serialize_repo(&full_config)?;
+        let report = serialize_repo_with_report(&full_config)?;
+        print_warnings(&report);
+        let (output, files) = (&report.output, &report.files);
         // If output_name provided, write to file, else print to stdout:
         if let Some(output_name) = &full_config.output_name {
             let final_output_path = if let Some(output_dir) = &full_config.output_dir {
@@ -56,8 +79,32 @@ fn main() -> Result<()> {
             };
             std::fs::write(&final_output_path, output.as_bytes())?;
             println!("{}", final_output_path);
+
+            if full_config.manifest {
+                let manifest_path = manifest_path_for(Path::new(&final_output_path));
+                write_manifest(
+                    &full_config,
+                    &report,
+                    Some(&final_output_path),
+                    &manifest_path,
+                )?;
+            }
         } else {
             println!("{}", output);
+
+            if full_config.manifest {
+                // Nothing is written to disk when streaming, so the manifest goes to the output dir
+                let output_dir = full_config
+                    .output_dir
+                    .clone()
+                    .map(std::path::PathBuf::from)
+                    .unwrap_or_else(|| std::env::temp_dir().join("yek-output"));
+                std::fs::create_dir_all(&output_dir)?;
+                let manifest_path =
+                    output_dir.join(format!("yek-manifest-{}.json", report.checksum));
+                write_manifest(&full_config, &report, None, &manifest_path)?;
+                eprintln!("Manifest written to {}", manifest_path.display());
+            }
         }
 
         if full_config.debug {
@@ -65,15 +112,11 @@ fn main() -> Result<()> {
             debug!("Output lines: {}", output.lines().count());
         }
     } else {
-        // Not streaming => run repo serialization & checksum in parallel
-        let (serialization_res, checksum_res) = join(
-            || serialize_repo(&full_config),
-            || YekConfig::get_checksum(&full_config.input_paths),
-        );
-
-        // Handle both results
-        let (output_string, files) = serialization_res?;
-        let checksum = checksum_res;
+        // Not streaming => the checksum covers the selected files and effective config
+        let report = serialize_repo_with_report(&full_config)?;
+        print_warnings(&report);
+        let (output_string, files) = (&report.output, &report.files);
+        let checksum = &report.checksum;
 
         // Now set the final output file
         let final_path = if let Some(output_name) = &full_config.output_name {
@@ -114,7 +157,32 @@ fn main() -> Result<()> {
 
         // Print path to stdout (like original code did)
         println!("{}", final_path);
+
+        if full_config.manifest {
+            let manifest_path = manifest_path_for(Path::new(&final_path));
+            write_manifest(&full_config, &report, Some(&final_path), &manifest_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the warnings collected during serialization to stderr
+fn print_warnings(report: &SerializationReport) {
+    for warning in &report.warnings {
+        eprintln!("Warning: {}", warning);
     }
+}
 
+/// Build the run manifest and write it to `manifest_path`
+fn write_manifest(
+    config: &YekConfig,
+    report: &SerializationReport,
+    output_file: Option<&str>,
+    manifest_path: &Path,
+) -> Result<()> {
+    let manifest = Manifest::new(config, report, output_file)?;
+    manifest.write(manifest_path)?;
+    debug!("Manifest written to {}", manifest_path.display());
     Ok(())
 }
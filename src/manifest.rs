@@ -0,0 +1,155 @@
+use crate::{
+    config::YekConfig,
+    count_tokens,
+    models::{BundleEntry, ExcludedFile, StageStats},
+    repository::{GitOperations, RealGitOperations},
+    SerializationReport, TOKENIZER_MODEL,
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Machine-readable description of a serialization run.
+///
+/// It is written next to the output file so tools can map positions in the
+/// output back to the source files they came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Version of yek that produced the output
+    pub yek_version: String,
+    /// Model whose tokenizer was used for token counts
+    pub tokenizer: String,
+    /// Input paths as given on the command line or in the config file
+    pub input_paths: Vec<String>,
+    /// Path of the output file, if the output was written to disk
+    pub output_file: Option<String>,
+    /// Fully resolved configuration used for the run
+    pub config: serde_json::Value,
+    /// Git state of every repository the input paths belong to
+    pub repositories: Vec<RepositoryState>,
+    /// Size of the output in bytes
+    pub total_bytes: usize,
+    /// Number of lines in the output
+    pub total_lines: usize,
+    /// Number of tokens in the output
+    pub total_tokens: usize,
+    /// Files included in the output, in output order
+    pub included: Vec<BundleEntry>,
+    /// Files that were seen but left out, with the reason why
+    pub excluded: Vec<ExcludedFile>,
+    /// Numbers for each processing stage, in the order they ran
+    pub stages: Vec<StageStats>,
+    /// Bytes saved by rendering duplicate files as a note
+    #[serde(default)]
+    pub bytes_saved: usize,
+    /// Tokens saved by rendering duplicate files as a note (only counted with a token budget)
+    #[serde(default)]
+    pub tokens_saved: Option<usize>,
+}
+
+/// Git state of a repository at the time of the run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepositoryState {
+    /// Repository working directory
+    pub root: String,
+    /// Commit HEAD points to (None if the repository has no commits)
+    pub head: Option<String>,
+    /// Whether tracked files have uncommitted changes
+    pub dirty: bool,
+}
+
+impl Manifest {
+    /// Build a manifest from a finished serialization run
+    pub fn new(
+        config: &YekConfig,
+        report: &SerializationReport,
+        output_file: Option<&str>,
+    ) -> Result<Self> {
+        // Token counts are only computed during rendering in token mode, fill in the rest
+        let included = report
+            .entries
+            .iter()
+            .map(|entry| {
+                let mut entry = entry.clone();
+                if entry.tokens.is_none() {
+                    entry.tokens = Some(count_tokens(
+                        &report.output[entry.byte_start..entry.byte_end],
+                    ));
+                }
+                entry
+            })
+            .collect();
+
+        Ok(Self {
+            yek_version: env!("CARGO_PKG_VERSION").to_string(),
+            tokenizer: TOKENIZER_MODEL.to_string(),
+            input_paths: config.input_paths.clone(),
+            output_file: output_file.map(|s| s.to_string()),
+            config: serde_json::to_value(config)
+                .map_err(|e| anyhow!("Failed to serialize config: {}", e))?,
+            repositories: repository_states(&config.input_paths),
+            total_bytes: report.output.len(),
+            total_lines: report.output.lines().count(),
+            total_tokens: count_tokens(&report.output),
+            included,
+            excluded: report.excluded.clone(),
+            stages: report.stages.clone(),
+            bytes_saved: report.bytes_saved,
+            tokens_saved: report.tokens_saved,
+        })
+    }
+
+    /// Write the manifest as pretty-printed JSON
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| anyhow!("Failed to serialize manifest: {}", e))?;
+        std::fs::write(path, json)
+            .map_err(|e| anyhow!("Failed to write manifest '{}': {}", path.display(), e))
+    }
+}
+
+/// Get the manifest path for an output file, e.g. `yek-output-1234.txt` -> `yek-output-1234.manifest.json`
+pub fn manifest_path_for(output_path: &Path) -> std::path::PathBuf {
+    let stem = output_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "yek-output".to_string());
+    output_path.with_file_name(format!("{}.manifest.json", stem))
+}
+
+/// Collect the git state of each distinct repository containing an input path
+fn repository_states(input_paths: &[String]) -> Vec<RepositoryState> {
+    let mut states: Vec<RepositoryState> = Vec::new();
+
+    for path_str in input_paths {
+        let path = Path::new(path_str);
+        // Glob patterns and files are looked up from their parent directory
+        let lookup = if path.is_dir() {
+            path
+        } else {
+            match path.parent() {
+                Some(parent) if parent != Path::new("") => parent,
+                _ => Path::new("."),
+            }
+        };
+
+        let Ok(git_ops) = RealGitOperations::discover(lookup) else {
+            continue;
+        };
+        let Ok(root) = git_ops.get_repository_root() else {
+            continue;
+        };
+        let root = root.to_string_lossy().to_string();
+        if states.iter().any(|s| s.root == root) {
+            continue;
+        }
+
+        states.push(RepositoryState {
+            root,
+            head: git_ops.get_head_commit().unwrap_or(None),
+            dirty: git_ops.is_dirty().unwrap_or(false),
+        });
+    }
+
+    states
+}
@@ -0,0 +1,304 @@
+use crate::{
+    config::{SubcommandConfigArgs, YekConfig},
+    encoding::{decode, legacy_encoding, normalize_newlines, strip_bom},
+    is_text_file, render_bundle,
+    roots::ServedRoots,
+    serialize_repo_with_report,
+    tree::generate_tree,
+};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+/// Protocol version reported when the client does not ask for one
+pub const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Arguments for `yek mcp`
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct McpArgs {
+    #[command(flatten)]
+    pub config: SubcommandConfigArgs,
+}
+
+/// MCP server exposing yek's serialization as tools.
+///
+/// Messages are newline-delimited JSON-RPC 2.0, as in the MCP stdio transport.
+/// Tools can only see files below the configured input paths.
+pub struct McpServer {
+    config: YekConfig,
+    roots: ServedRoots,
+}
+
+impl McpServer {
+    pub fn new(config: YekConfig) -> Self {
+        let roots = ServedRoots::new(&config.input_paths);
+        Self { config, roots }
+    }
+
+    /// Read requests from `reader` until EOF, writing one response line per request
+    pub fn run<R: BufRead, W: Write>(&self, reader: R, mut writer: W) -> Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => self.handle_message(&message),
+                Err(e) => Some(error_response(
+                    Value::Null,
+                    PARSE_ERROR,
+                    &format!("Parse error: {}", e),
+                )),
+            };
+
+            if let Some(response) = response {
+                writeln!(writer, "{}", response)?;
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle a single JSON-RPC message. Notifications get no response.
+    pub fn handle_message(&self, message: &Value) -> Option<Value> {
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            return Some(error_response(
+                id.unwrap_or(Value::Null),
+                INVALID_REQUEST,
+                "Invalid request: missing method",
+            ));
+        };
+        // Notifications (no id) such as `notifications/initialized` need no reply
+        let id = id?;
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+            "tools/call" => self.call_tool(&params),
+            _ => {
+                return Some(error_response(
+                    id,
+                    METHOD_NOT_FOUND,
+                    &format!("Method not found: {}", method),
+                ))
+            }
+        };
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error_response(id, INVALID_PARAMS, &e.to_string()),
+        })
+    }
+
+    fn initialize(&self, params: &Value) -> Value {
+        let protocol_version = params
+            .get("protocolVersion")
+            .and_then(Value::as_str)
+            .unwrap_or(MCP_PROTOCOL_VERSION);
+        json!({
+            "protocolVersion": protocol_version,
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "yek", "version": env!("CARGO_PKG_VERSION") },
+        })
+    }
+
+    /// Run a tool. Unknown tools are protocol errors; tool failures are reported in the result.
+    fn call_tool(&self, params: &Value) -> Result<Value> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("tools/call: missing tool name"))?;
+        let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+
+        let output = match name {
+            "serialize" => self.serialize(&arguments),
+            "list_files" => self.list_files(&arguments),
+            "read_file" => self.read_file(&arguments),
+            "tree" => self.tree(&arguments),
+            _ => return Err(anyhow!("tools/call: unknown tool '{}'", name)),
+        };
+
+        Ok(match output {
+            Ok(text) => json!({ "content": [{ "type": "text", "text": text }], "isError": false }),
+            Err(e) => json!({
+                "content": [{ "type": "text", "text": e.to_string() }],
+                "isError": true,
+            }),
+        })
+    }
+
+    fn serialize(&self, arguments: &Value) -> Result<String> {
+        let mut config = self.config_for(arguments)?;
+        if let Some(tokens) = arguments.get("tokens").and_then(Value::as_str) {
+            config.tokens = tokens.to_string();
+            config.token_mode = !tokens.is_empty();
+            config.validate()?;
+        }
+
+        let report = serialize_repo_with_report(&config)?;
+        let Some(query) = arguments.get("query").and_then(Value::as_str) else {
+            return Ok(report.output);
+        };
+
+        // Keep only files whose path or content mentions the query
+        let query = query.to_lowercase();
+        let matching: Vec<_> = report
+            .files
+            .into_iter()
+            .filter(|f| {
+                f.rel_path.to_lowercase().contains(&query)
+                    || f.content.to_lowercase().contains(&query)
+            })
+            .collect();
+        Ok(render_bundle(&matching, &config)?.output)
+    }
+
+    fn list_files(&self, arguments: &Value) -> Result<String> {
+        let mut paths = self.selected_paths(arguments)?;
+        paths.sort();
+        Ok(paths.join("\n"))
+    }
+
+    fn tree(&self, arguments: &Value) -> Result<String> {
+        let paths: Vec<PathBuf> = self
+            .selected_paths(arguments)?
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        Ok(generate_tree(&paths))
+    }
+
+    fn read_file(&self, arguments: &Value) -> Result<String> {
+        let path = arguments
+            .get("path")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("read_file: missing 'path' argument"))?;
+        let resolved = self.roots.resolve(path)?;
+        if !resolved.is_file() {
+            return Err(anyhow!("read_file: '{}' is not a file", path));
+        }
+        if !is_text_file(&resolved, &self.config.binary_extensions)? {
+            return Err(anyhow!("read_file: '{}' is a binary file", path));
+        }
+        let bytes = std::fs::read(&resolved)
+            .map_err(|e| anyhow!("read_file: cannot read '{}': {}", path, e))?;
+        let legacy = self
+            .config
+            .legacy_encoding
+            .as_deref()
+            .and_then(legacy_encoding);
+        let mut text = decode(&bytes, legacy)
+            .ok_or_else(|| anyhow!("read_file: '{}' is a binary file", path))?
+            .text;
+        if self.config.strip_bom {
+            text = strip_bom(text);
+        }
+        if self.config.normalize_newlines {
+            text = normalize_newlines(text);
+        }
+        Ok(text)
+    }
+
+    /// Relative paths of the files that would be serialized for the given arguments
+    fn selected_paths(&self, arguments: &Value) -> Result<Vec<String>> {
+        let config = self.config_for(arguments)?;
+        let report = serialize_repo_with_report(&config)?;
+        Ok(report.files.into_iter().map(|f| f.rel_path).collect())
+    }
+
+    /// Server config with the input paths replaced by the tool's `paths` argument, if any
+    fn config_for(&self, arguments: &Value) -> Result<YekConfig> {
+        let mut config = self.config.clone();
+        if let Some(paths) = arguments.get("paths").and_then(Value::as_array) {
+            let paths = paths
+                .iter()
+                .map(|p| {
+                    let p = p
+                        .as_str()
+                        .ok_or_else(|| anyhow!("paths: expected an array of strings"))?;
+                    self.roots.check_pattern(p)?;
+                    Ok(p.to_string())
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if !paths.is_empty() {
+                config.input_paths = paths;
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Load the config and serve MCP over stdin/stdout
+pub fn run_stdio(args: &McpArgs) -> Result<()> {
+    let config = args.config.load()?;
+    let server = McpServer::new(config);
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    server.run(stdin.lock(), stdout.lock())
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+fn tool_definitions() -> Value {
+    let paths = json!({
+        "type": "array",
+        "items": { "type": "string" },
+        "description": "Files, directories or glob patterns to use instead of the served paths",
+    });
+    json!([
+        {
+            "name": "serialize",
+            "description": "Serialize files into a single LLM-ready bundle, most important files last",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "paths": paths,
+                    "tokens": {
+                        "type": "string",
+                        "description": "Token budget, e.g. \"128k\"",
+                    },
+                    "query": {
+                        "type": "string",
+                        "description": "Only include files whose path or content contains this text (case-insensitive)",
+                    },
+                },
+            },
+        },
+        {
+            "name": "list_files",
+            "description": "List the files that would be serialized, one path per line",
+            "inputSchema": { "type": "object", "properties": { "paths": paths } },
+        },
+        {
+            "name": "read_file",
+            "description": "Read a single text file",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "path": { "type": "string", "description": "Path to the file" } },
+                "required": ["path"],
+            },
+        },
+        {
+            "name": "tree",
+            "description": "Show the directory tree of the files that would be serialized",
+            "inputSchema": { "type": "object", "properties": { "paths": paths } },
+        },
+    ])
+}
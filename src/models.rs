@@ -24,6 +24,9 @@ pub struct ProcessedFile {
     pub formatted_content: Option<String>,
     /// File category for improved sorting and organization
     pub category: FileCategory,
+    /// Encoding the content was decoded from, or None for plain UTF-8
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
 }
 
 impl Clone for ProcessedFile {
@@ -37,6 +40,7 @@ impl Clone for ProcessedFile {
             token_count: OnceLock::new(),
             formatted_content: self.formatted_content.clone(),
             category: self.category,
+            encoding: self.encoding.clone(),
         }
     }
 }
@@ -55,6 +59,7 @@ impl ProcessedFile {
             token_count: OnceLock::new(),
             formatted_content: None,
             category,
+            encoding: None,
         }
     }
 
@@ -76,9 +81,18 @@ impl ProcessedFile {
             token_count: OnceLock::new(),
             formatted_content: None,
             category,
+            encoding: None,
         }
     }
 
+    /// Replace the content, resetting everything derived from it
+    pub fn set_content(&mut self, content: String) {
+        self.size_bytes = content.len();
+        self.content = content;
+        self.token_count = OnceLock::new();
+        self.formatted_content = None;
+    }
+
     /// Get token count, computing it lazily if not already computed
     pub fn get_token_count(&self) -> usize {
         *self.token_count.get_or_init(|| self.compute_token_count())
@@ -169,6 +183,98 @@ impl ProcessedFile {
     }
 }
 
+/// Reason a discovered file did not make it into the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExclusionReason {
+    /// Matched an ignore pattern or a .gitignore rule
+    Ignored,
+    /// Has a known binary file extension
+    BinaryExtension,
+    /// Content was detected as binary, or `.gitattributes` marks the file binary
+    BinaryContent,
+    /// File could not be read
+    Unreadable,
+    /// Did not fit in the remaining size or token budget
+    OverBudget,
+    /// Larger than `max_file_size` or longer than `max_file_lines`
+    TooLarge,
+    /// Marked as generated in its header or in `.gitattributes`
+    Generated,
+    /// Looks minified
+    Minified,
+    /// A symbolic link that is not followed
+    Symlink,
+}
+
+impl ExclusionReason {
+    /// Get the reason as a string for display/debug purposes
+    pub fn name(self) -> &'static str {
+        match self {
+            ExclusionReason::Ignored => "ignored",
+            ExclusionReason::BinaryExtension => "binary_extension",
+            ExclusionReason::BinaryContent => "binary_content",
+            ExclusionReason::Unreadable => "unreadable",
+            ExclusionReason::OverBudget => "over_budget",
+            ExclusionReason::TooLarge => "too_large",
+            ExclusionReason::Generated => "generated",
+            ExclusionReason::Minified => "minified",
+            ExclusionReason::Symlink => "symlink",
+        }
+    }
+}
+
+/// A file that was seen during processing but left out of the output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExcludedFile {
+    /// Relative path from the input root
+    pub rel_path: String,
+    /// Why the file was excluded
+    pub reason: ExclusionReason,
+    /// Extra information, such as the matching pattern or the read error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl ExcludedFile {
+    pub fn new(rel_path: impl Into<String>, reason: ExclusionReason) -> Self {
+        Self {
+            rel_path: rel_path.into(),
+            reason,
+            detail: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// Location of a single file's entry inside the rendered output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleEntry {
+    /// Relative path of the file
+    pub rel_path: String,
+    /// Byte offset where the entry starts (inclusive)
+    pub byte_start: usize,
+    /// Byte offset where the entry ends (exclusive)
+    pub byte_end: usize,
+    /// First line of the entry (1-based)
+    pub line_start: usize,
+    /// Last line of the entry (1-based, inclusive)
+    pub line_end: usize,
+    /// Token count of the rendered entry, when it was computed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<usize>,
+    /// Encoding the file was decoded from, when it was not plain UTF-8
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// Path of the earlier file this one repeats, when rendered as a note
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<String>,
+}
+
 /// Represents file priority information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FilePriority {
@@ -211,6 +317,54 @@ impl RepositoryInfo {
     }
 }
 
+/// Which symbolic links found while walking a directory are followed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FollowSymlinks {
+    /// Skip every link
+    #[default]
+    Never,
+    /// Follow links whose target is inside the walked directory
+    WithinRoot,
+    /// Follow every link
+    Always,
+}
+
+/// What to do with git submodules found while walking a repository
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Submodules {
+    /// Walk into checked out submodules and use their history for priority
+    #[default]
+    Recurse,
+    /// Leave submodules out
+    Skip,
+}
+
+/// Which files repeating an earlier file are rendered as a note pointing at it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Dedupe {
+    /// Every file is rendered in full
+    #[default]
+    Off,
+    /// Files with the same content as an earlier file
+    Exact,
+    /// Also files with the same name and mostly the same lines, rendered as a diff
+    Near,
+}
+
+/// What is kept of the outputs of Jupyter notebook code cells
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotebookOutputs {
+    /// Text outputs cut to a few lines, other outputs named
+    #[default]
+    Summarize,
+    /// No outputs
+    Drop,
+}
+
 /// Configuration for input processing
 #[derive(Debug, Clone)]
 pub struct InputConfig {
@@ -218,6 +372,30 @@ pub struct InputConfig {
     pub input_paths: Vec<String>,
     /// Ignore patterns (compiled globs)
     pub ignore_patterns: Vec<glob::Pattern>,
+    /// Only files matching one of these are discovered when walking directories (empty means all)
+    pub include_patterns: Vec<glob::Pattern>,
+    /// Files larger than this many bytes are skipped without being read
+    pub max_file_size: Option<u64>,
+    /// Files with more lines than this are skipped
+    pub max_file_lines: Option<usize>,
+    /// Keep generated and minified files
+    pub include_generated: bool,
+    /// Which symbolic links to follow while walking directories
+    pub follow_symlinks: FollowSymlinks,
+    /// Encoding of files that are not valid UTF-8 and have no byte order mark
+    pub legacy_encoding: Option<&'static encoding_rs::Encoding>,
+    /// Remove the byte order mark from the start of files
+    pub strip_bom: bool,
+    /// Turn CRLF line endings into LF
+    pub normalize_newlines: bool,
+    /// Files included as a summary instead of verbatim
+    pub summarize_patterns: Vec<glob::Pattern>,
+    /// Rows or array items kept when summarizing data files
+    pub summary_rows: usize,
+    /// What is kept of notebook outputs
+    pub notebook_outputs: NotebookOutputs,
+    /// Whether git submodules are walked
+    pub submodules: Submodules,
     /// Binary file extensions to skip
     pub binary_extensions: std::collections::HashSet<String>,
     /// Maximum depth for git history traversal
@@ -231,6 +409,18 @@ impl Default for InputConfig {
         Self {
             input_paths: Vec::new(),
             ignore_patterns: Vec::new(),
+            include_patterns: Vec::new(),
+            max_file_size: None,
+            max_file_lines: None,
+            include_generated: false,
+            follow_symlinks: FollowSymlinks::Never,
+            legacy_encoding: None,
+            strip_bom: false,
+            normalize_newlines: false,
+            summarize_patterns: Vec::new(),
+            summary_rows: 10,
+            notebook_outputs: NotebookOutputs::Summarize,
+            submodules: Submodules::Recurse,
             binary_extensions: std::collections::HashSet::new(),
             max_git_depth: 100,
             git_boost_max: Some(100),
@@ -333,6 +523,25 @@ pub struct ProcessingStats {
     pub memory_usage_bytes: usize,
     /// Cache hit rate (0.0 to 1.0)
     pub cache_hit_rate: f64,
+    /// Numbers for each pipeline stage, in the order they ran
+    pub stages: Vec<StageStats>,
+}
+
+/// Statistics for a single pipeline stage
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StageStats {
+    /// Stage name
+    pub name: String,
+    /// Files passed into the stage
+    pub files_in: usize,
+    /// Files returned by the stage
+    pub files_out: usize,
+    /// Files whose content the stage changed
+    pub files_changed: usize,
+    /// Time spent in the stage, in milliseconds
+    pub duration_ms: u128,
+    /// Files the stage could not handle, as `path: reason`
+    pub errors: Vec<String>,
 }
 
 impl ProcessingStats {
@@ -0,0 +1,158 @@
+//! Jupyter notebooks rendered as readable source.
+//!
+//! A `.ipynb` file is JSON with every cell's source split into strings and
+//! outputs that often hold base64 images. Notebooks are turned into their
+//! cells, in order, each after a `# %%` marker as used by Jupytext and most
+//! editors. Markdown cells and outputs are commented out, so the result is
+//! valid source in the notebook's language (`//` for a JavaScript kernel):
+//!
+//! ```text
+//! # %% [markdown]
+//! # # Analysis
+//!
+//! # %%
+//! df.describe()
+//! # Output:
+//! #        a
+//! # count  3.0
+//! ```
+//!
+//! The rest of the pipeline then sees only that text.
+
+use crate::models::NotebookOutputs;
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// Text outputs longer than this are cut when summarized
+const MAX_OUTPUT_LINES: usize = 20;
+
+/// Whether `path` names a Jupyter notebook
+pub fn is_notebook(path: &str) -> bool {
+    path.to_lowercase().ends_with(".ipynb")
+}
+
+/// The cells of the notebook `json` as text, with outputs handled as `outputs` says
+pub fn render_notebook(json: &str, outputs: NotebookOutputs) -> Result<String> {
+    let notebook: Value =
+        serde_json::from_str(json).map_err(|e| anyhow!("invalid notebook JSON: {}", e))?;
+    let cells = notebook
+        .get("cells")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("notebook has no 'cells' list (only nbformat 4 is supported)"))?;
+
+    let comment = line_comment(&notebook);
+    let rendered: Vec<String> = cells
+        .iter()
+        .map(|cell| render_cell(cell, outputs, comment))
+        .collect();
+    // Every cell ends with a newline, so cells are separated by a blank line
+    Ok(rendered.join("\n"))
+}
+
+/// The line comment of the notebook's kernel language, `#` if it is not known
+fn line_comment(notebook: &Value) -> &'static str {
+    let language = notebook
+        .pointer("/metadata/kernelspec/language")
+        .or_else(|| notebook.pointer("/metadata/language_info/name"))
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_lowercase();
+    match language.as_str() {
+        "javascript" | "typescript" | "java" | "scala" | "kotlin" | "groovy" | "c" | "c++"
+        | "cpp" | "c#" | "csharp" | "go" | "rust" | "swift" => "//",
+        "sql" | "haskell" | "lua" => "--",
+        "clojure" | "scheme" | "racket" => ";;",
+        "matlab" | "octave" => "%",
+        _ => "#",
+    }
+}
+
+fn render_cell(cell: &Value, outputs: NotebookOutputs, comment: &str) -> String {
+    let cell_type = cell
+        .get("cell_type")
+        .and_then(Value::as_str)
+        .unwrap_or("code");
+    let mut text = match cell_type {
+        "code" => format!("{} %%\n", comment),
+        other => format!("{} %% [{}]\n", comment, other),
+    };
+    // Only code cells are source; markdown and raw cells are commented out
+    let prefix = if cell_type == "code" { "" } else { comment };
+    push_lines(&mut text, &multiline(cell.get("source")), prefix);
+
+    if cell_type == "code" && outputs == NotebookOutputs::Summarize {
+        let summary: Vec<String> = cell
+            .get("outputs")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .flat_map(summarize_output)
+            .collect();
+        if !summary.is_empty() {
+            text.push_str(&format!("{} Output:\n", comment));
+            push_lines(&mut text, &summary.join("\n"), comment);
+        }
+    }
+    text
+}
+
+/// Short text standing in for one output of a code cell
+fn summarize_output(output: &Value) -> Vec<String> {
+    match output.get("output_type").and_then(Value::as_str) {
+        Some("stream") => vec![truncate(&multiline(output.get("text")))],
+        Some("error") => {
+            let field = |name| output.get(name).and_then(Value::as_str).unwrap_or("");
+            vec![format!("{}: {}", field("ename"), field("evalue"))]
+        }
+        Some("execute_result") | Some("display_data") => {
+            let Some(data) = output.get("data").and_then(Value::as_object) else {
+                return Vec::new();
+            };
+            // Plain text stands in for rich outputs like HTML tables, but
+            // images are always worth a mention
+            let text = data
+                .get("text/plain")
+                .map(|text| truncate(&multiline(Some(text))));
+            let has_text = text.is_some();
+            let omitted = data
+                .keys()
+                .filter(|mime| !has_text || mime.starts_with("image/"))
+                .map(|mime| format!("[{} output omitted]", mime));
+            text.into_iter().chain(omitted).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Notebook strings are either one string or a list of lines
+fn multiline(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+fn truncate(text: &str) -> String {
+    let lines: Vec<&str> = text.trim_end_matches('\n').lines().collect();
+    if lines.len() <= MAX_OUTPUT_LINES {
+        return lines.join("\n");
+    }
+    format!(
+        "{}\n... {} more lines",
+        lines[..MAX_OUTPUT_LINES].join("\n"),
+        lines.len() - MAX_OUTPUT_LINES
+    )
+}
+
+/// Append every line of `lines` to `text`, after the comment `prefix` if any
+fn push_lines(text: &mut String, lines: &str, prefix: &str) {
+    for line in lines.trim_end_matches('\n').lines() {
+        text.push_str(prefix);
+        if !prefix.is_empty() && !line.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(line);
+        text.push('\n');
+    }
+}
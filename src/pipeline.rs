@@ -1,17 +1,33 @@
 use crate::{
+    category::FileCategory,
+    config::YekConfig,
+    encoding::{decode, legacy_encoding, normalize_newlines, strip_bom},
+    generated::{generated_marker, minified_reason},
+    ignore_rules::{IgnoreRule, IgnoreRules, IncludePatterns},
     models::{
-        InputConfig, OutputConfig, ProcessedFile, ProcessingConfig, ProcessingStats, RepositoryInfo,
+        ExcludedFile, ExclusionReason, FollowSymlinks, InputConfig, OutputConfig, ProcessedFile,
+        ProcessingConfig, ProcessingStats, RepositoryInfo, StageStats, Submodules,
     },
-    repository::{FileSystem, RepositoryFactory},
+    notebook::{is_notebook, render_notebook},
+    priority::{compute_recentness_boost, get_commit_times_by_submodule, get_file_priority},
+    repository::{
+        ArchiveFileSystem, AttrValue, FileSystem, GitCheckout, GitOperations, RealFileSystem,
+        RealGitOperations,
+    },
+    summarize::summarize as summarize_file,
 };
-use anyhow::{anyhow, Result};
-use glob;
+use anyhow::Result;
+use ignore::gitignore::Gitignore;
+use path_slash::PathBufExt;
+use rayon::prelude::*;
 use std::{
-    collections::HashMap,
-    path::Path,
+    collections::{BTreeMap, HashMap},
+    path::{Component, Path, PathBuf},
     sync::{Arc, Mutex},
     time::Instant,
 };
+use tracing::debug;
+use walkdir::WalkDir;
 
 /// Processing stage trait for the middleware pipeline
 pub trait ProcessingStage {
@@ -35,6 +51,10 @@ pub struct ProcessingContext {
     pub repository_info: Arc<RepositoryInfo>,
     pub stats: Arc<Mutex<ProcessingStats>>,
     pub file_system: Arc<dyn FileSystem + Send + Sync>,
+    /// Files that were discovered but left out, with the reason why
+    pub excluded_files: Arc<Mutex<Vec<ExcludedFile>>>,
+    /// Symbolic links followed while walking, by relative path, with their targets
+    pub symlinks: Arc<Mutex<BTreeMap<String, String>>>,
 }
 
 impl ProcessingContext {
@@ -52,26 +72,152 @@ impl ProcessingContext {
             repository_info: Arc::new(repository_info),
             stats: Arc::new(Mutex::new(ProcessingStats::new())),
             file_system,
+            excluded_files: Arc::new(Mutex::new(Vec::new())),
+            symlinks: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    /// Create a context for the CLI configuration
+    pub fn from_config(config: &YekConfig) -> Self {
+        let token_mode = config.token_mode || !config.tokens.is_empty();
+        let root_path = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+
+        Self::new(
+            InputConfig {
+                input_paths: config.input_paths.clone(),
+                ignore_patterns: config
+                    .ignore_patterns
+                    .iter()
+                    .filter_map(|s| glob::Pattern::new(s).ok())
+                    .collect(),
+                include_patterns: config
+                    .include_patterns
+                    .iter()
+                    .filter_map(|s| glob::Pattern::new(s).ok())
+                    .collect(),
+                summarize_patterns: config
+                    .summarize_patterns
+                    .iter()
+                    .filter_map(|s| glob::Pattern::new(s).ok())
+                    .collect(),
+                summary_rows: config.summary_rows,
+                max_file_size: config.max_file_size_bytes().ok().flatten(),
+                max_file_lines: config.max_file_lines,
+                include_generated: config.include_generated,
+                follow_symlinks: config.follow_symlinks,
+                legacy_encoding: config.legacy_encoding.as_deref().and_then(legacy_encoding),
+                strip_bom: config.strip_bom,
+                normalize_newlines: config.normalize_newlines,
+                notebook_outputs: config.notebook_outputs,
+                submodules: config.submodules,
+                binary_extensions: config.binary_extensions.iter().cloned().collect(),
+                max_git_depth: config.max_git_depth,
+                git_boost_max: config.git_boost_max,
+            },
+            OutputConfig {
+                max_size: config.max_size.clone(),
+                token_mode,
+                token_limit: token_mode.then(|| config.tokens.clone()),
+                output_template: config
+                    .output_template
+                    .clone()
+                    .unwrap_or_else(|| crate::defaults::DEFAULT_OUTPUT_TEMPLATE.to_string()),
+                line_numbers: config.line_numbers,
+                json_output: config.json,
+                tree_header: config.tree_header,
+                tree_only: config.tree_only,
+                output_dir: config.output_dir.clone(),
+                output_name: config.output_name.clone(),
+                stream: config.stream,
+            },
+            ProcessingConfig {
+                priority_rules: config.priority_rules.clone(),
+                category_weights: config.category_weights.clone().unwrap_or_default(),
+                debug: config.debug,
+                parallel: true,
+                ..Default::default()
+            },
+            RepositoryInfo::new(root_path, false),
+            Arc::new(RealFileSystem),
+        )
+    }
+
+    /// Update the stats of the pipeline stage that is currently running
+    pub fn update_current_stage(&self, update: impl FnOnce(&mut StageStats)) {
+        if let Ok(mut stats) = self.stats.lock() {
+            if let Some(current) = stats.stages.last_mut() {
+                update(current);
+            }
         }
     }
+
+    /// Record a file that was discovered but will not be part of the output
+    pub fn record_exclusion(&self, excluded: ExcludedFile) {
+        if let Ok(mut excluded_files) = self.excluded_files.lock() {
+            excluded_files.push(excluded);
+        }
+    }
+
+    /// Record a symbolic link that was followed
+    pub fn record_symlink(&self, rel_path: String, target: String) {
+        if let Ok(mut symlinks) = self.symlinks.lock() {
+            symlinks.insert(rel_path, target);
+        }
+    }
+
+    /// Get a snapshot of the symbolic links followed so far
+    pub fn get_symlinks(&self) -> BTreeMap<String, String> {
+        self.symlinks
+            .lock()
+            .map(|symlinks| symlinks.clone())
+            .unwrap_or_default()
+    }
+
+    /// Get a snapshot of all exclusions recorded so far, sorted by path
+    pub fn get_excluded_files(&self) -> Vec<ExcludedFile> {
+        let mut excluded = self
+            .excluded_files
+            .lock()
+            .map(|files| files.clone())
+            .unwrap_or_default();
+        excluded.sort_by(|a, b| a.rel_path.cmp(&b.rel_path));
+        // Overlapping inputs can report the same file more than once
+        excluded.dedup_by(|a, b| a.rel_path == b.rel_path);
+        excluded
+    }
 }
 
-/// File discovery stage - finds and filters files to process
+/// File discovery stage - finds, filters and reads the files to process.
+///
+/// Paths are made relative to the deepest directory containing every input,
+/// so files from different inputs never collide. Directories are walked with
+/// `.gitignore` support and files are read in parallel.
+#[derive(Default)]
 pub struct FileDiscoveryStage {
-    repository_factory: RepositoryFactory,
+    base_dir: Option<PathBuf>,
 }
 
-impl Default for FileDiscoveryStage {
-    fn default() -> Self {
-        Self::new()
-    }
+/// A file that passed the ignore checks and still has to be read
+struct Candidate {
+    path: PathBuf,
+    rel_path: String,
+    /// Category set by `.gitattributes`, overriding the path heuristics
+    category: Option<FileCategory>,
+    /// Priority from the per-directory config files above the file
+    priority: i32,
+    /// Where to read the file from, when not from the context's file system
+    file_system: Option<Arc<dyn FileSystem + Send + Sync>>,
 }
 
 impl FileDiscoveryStage {
     pub fn new() -> Self {
-        Self {
-            repository_factory: RepositoryFactory::new(),
-        }
+        Self::default()
+    }
+
+    /// Make paths relative to `base_dir` instead of the common base of the inputs
+    pub fn with_base_dir(mut self, base_dir: impl Into<PathBuf>) -> Self {
+        self.base_dir = Some(base_dir.into());
+        self
     }
 }
 
@@ -81,37 +227,71 @@ impl ProcessingStage for FileDiscoveryStage {
         _files: Vec<ProcessedFile>,
         context: &ProcessingContext,
     ) -> Result<Vec<ProcessedFile>> {
-        // eprintln!("DEBUG: FileDiscoveryStage::process called");
         let start_time = Instant::now();
-        let mut discovered_files = Vec::new();
-
-        // Calculate optimal base directory for mixed inputs
-        let base_dir = self.calculate_base_directory(&context.input_config.input_paths)?;
-        // eprintln!("DEBUG: Base directory calculated: {}", base_dir.display());
-
-        // Create repository info for the base directory
-        let repo_info = self
-            .repository_factory
-            .create_repository_info(&base_dir, &context.input_config)?;
+        let input_paths = &context.input_config.input_paths;
+        let base_dir = match &self.base_dir {
+            Some(base_dir) => absolute_path(base_dir),
+            None => calculate_base_directory(input_paths),
+        };
 
-        // Process all inputs with the same repository context
-        for input_path in &context.input_config.input_paths {
-            let path = Path::new(input_path);
-            // eprintln!("DEBUG: Processing input path: {}", path.display());
+        let ignore_patterns = &context.input_config.ignore_patterns;
+        let patterns = Arc::new(IgnoreRules::build_patterns(&base_dir, ignore_patterns)?);
+        let user_patterns = Arc::new(IgnoreRules::build_patterns(
+            &base_dir,
+            IgnoreRules::without_builtin(ignore_patterns),
+        )?);
+        let mut candidates = Vec::new();
+        for input_path in input_paths {
+            let rules = IgnoreRules::new(&input_root(input_path), &base_dir, patterns.clone())
+                .with_user_patterns(user_patterns.clone());
+            for path in expand_globs(input_path) {
+                if is_archive_file(&path) {
+                    walk_archive(
+                        &path,
+                        &base_dir,
+                        &patterns,
+                        &user_patterns,
+                        context,
+                        &mut candidates,
+                    )?;
+                } else if context.file_system.is_file(&path) {
+                    if !has_binary_extension(&path, &base_dir, context) {
+                        add_candidate(&path, &base_dir, &rules, context, None, &mut candidates);
+                    }
+                } else if context.file_system.is_directory(&path) {
+                    walk_directory(&path, &base_dir, &rules, context, &mut candidates)?;
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.rel_path.cmp(&b.rel_path));
+        candidates.dedup_by(|a, b| a.rel_path == b.rel_path);
+
+        let boosts = commit_boosts(&base_dir, context);
+        let summarize = IncludePatterns::new(&base_dir, &context.input_config.summarize_patterns)?;
+        let mut files: Vec<ProcessedFile> = candidates
+            .par_iter()
+            .filter_map(|candidate| read_candidate(candidate, &boosts, &summarize, context))
+            .collect();
+        for (file_index, file) in files.iter_mut().enumerate() {
+            file.file_index = file_index;
+        }
 
-            // Discover files in this path
-            let files = self.discover_files_in_path(path, &repo_info, context)?;
-            // eprintln!("DEBUG: Found {} files for path: {}", files.len(), path.display());
-            discovered_files.extend(files);
+        if context.processing_config.debug {
+            debug!(
+                "Discovered {} files under {} in {:?}",
+                files.len(),
+                base_dir.display(),
+                start_time.elapsed()
+            );
         }
 
         // Update stats
         if let Ok(mut stats) = context.stats.lock() {
             stats.processing_time_ms += start_time.elapsed().as_millis();
-            stats.files_processed = discovered_files.len();
+            stats.files_processed = files.len();
         }
 
-        Ok(discovered_files)
+        Ok(files)
     }
 
     fn name(&self) -> &'static str {
@@ -119,501 +299,609 @@ impl ProcessingStage for FileDiscoveryStage {
     }
 }
 
-impl FileDiscoveryStage {
-    /// Calculate the optimal base directory for mixed inputs
-    fn calculate_base_directory(&self, input_paths: &[String]) -> Result<std::path::PathBuf> {
-        if input_paths.is_empty() {
-            return Ok(std::env::current_dir().unwrap_or_else(|_| Path::new(".").to_path_buf()));
-        }
-
-        // Convert all paths to absolute paths for comparison, handling glob patterns
-        let mut absolute_paths = Vec::new();
-        for path_str in input_paths {
-            let path = Path::new(path_str);
-
-            // For glob patterns, extract the directory part
-            let actual_path =
-                if path_str.contains('*') || path_str.contains('?') || path_str.contains('[') {
-                    // This is a glob pattern, extract the directory part
-                    if let Some(parent) = path.parent() {
-                        if parent == Path::new("") {
-                            // Pattern like "*.txt" - use current directory
-                            std::env::current_dir().unwrap_or_else(|_| Path::new(".").to_path_buf())
-                        } else {
-                            // Pattern like "src/*.txt" - use the parent directory
-                            if parent.is_absolute() {
-                                parent.to_path_buf()
-                            } else {
-                                std::env::current_dir()
-                                    .unwrap_or_else(|_| Path::new(".").to_path_buf())
-                                    .join(parent)
-                            }
-                        }
-                    } else {
-                        // Pattern with no directory part - use current directory
-                        std::env::current_dir().unwrap_or_else(|_| Path::new(".").to_path_buf())
-                    }
-                } else {
-                    // Regular path
-                    if path.is_absolute() {
-                        path.to_path_buf()
-                    } else {
-                        std::env::current_dir()
-                            .unwrap_or_else(|_| Path::new(".").to_path_buf())
-                            .join(path)
-                    }
-                };
-            absolute_paths.push(actual_path);
-        }
+/// Deepest directory containing every input. Files count as their parent
+/// directory and glob patterns as the part before the first wildcard.
+fn calculate_base_directory(input_paths: &[String]) -> PathBuf {
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
 
-        // Check if all paths are files (not directories)
-        let all_files = absolute_paths.iter().all(|path| {
-            // Check if the path exists and is a file, or if it doesn't exist but has a file extension
-            path.exists() && path.is_file() || (!path.exists() && path.extension().is_some())
-        });
-
-        // If all paths are files, use the parent directory of the first file as the base
-        if all_files && !absolute_paths.is_empty() {
-            let first_file = &absolute_paths[0];
-            if let Some(parent) = first_file.parent() {
-                return Ok(parent.to_path_buf());
+    let dirs: Vec<PathBuf> = input_paths
+        .iter()
+        .filter_map(|input| {
+            if is_glob(input) {
+                return Some(absolute_path(&glob_root(input)));
             }
-        }
-
-        // Find the common base directory
-        let first_path = &absolute_paths[0];
-        let mut common_base = first_path.as_path();
-
-        for path in &absolute_paths[1..] {
-            common_base = self.find_common_base(common_base, path);
-        }
+            let path = absolute_path(Path::new(input));
+            if path.is_dir() || is_archive_file(&path) {
+                Some(path)
+            } else if path.is_file() {
+                path.parent().map(Path::to_path_buf)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let Some((first, rest)) = dirs.split_first() else {
+        return cwd;
+    };
+    rest.iter().fold(first.clone(), |common, dir| {
+        common
+            .components()
+            .zip(dir.components())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a)
+            .collect()
+    })
+}
 
-        Ok(common_base.to_path_buf())
-    }
+fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
 
-    /// Find the common base directory between two paths
-    fn find_common_base<'a>(&self, path1: &'a Path, path2: &'a Path) -> &'a Path {
-        let ancestors1 = path1.ancestors().collect::<Vec<_>>();
-        let ancestors2 = path2.ancestors().collect::<Vec<_>>();
+/// Leading part of a glob pattern that contains no wildcards
+fn glob_root(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|c| !is_glob(&c.as_os_str().to_string_lossy()))
+        .collect()
+}
 
-        // Find the last common ancestor
-        for (a, b) in ancestors1.iter().zip(ancestors2.iter()) {
-            if a != b {
-                // Return the parent of the first mismatch
-                return ancestors1
-                    .iter()
-                    .position(|&x| x == *a)
-                    .and_then(|pos| ancestors1.get(pos + 1))
-                    .copied()
-                    .unwrap_or_else(|| Path::new("."));
+/// Absolute form of `path` with `.` and `..` resolved lexically
+fn absolute_path(path: &Path) -> PathBuf {
+    let joined = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join(path)
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
             }
+            other => normalized.push(other),
         }
+    }
+    normalized
+}
 
-        // If one path is a parent of the other, return the shorter one
-        if ancestors1.len() <= ancestors2.len() {
-            path1
-        } else {
-            path2
-        }
-    }
-
-    /// Expand glob patterns into concrete paths
-    fn expand_globs(&self, path: &Path) -> Result<Vec<std::path::PathBuf>> {
-        let mut expanded_paths = Vec::new();
-        let path_str = path.to_string_lossy();
-
-        // Check if the path contains glob patterns
-        // eprintln!("DEBUG: Checking path for glob patterns: {}", path_str);
-        if path_str.contains('*') || path_str.contains('?') || path_str.contains('[') {
-            // eprintln!("DEBUG: Found glob pattern, expanding: {}", path_str);
-            // Expand glob pattern
-            for entry in glob::glob(&path_str)? {
-                match entry {
-                    Ok(expanded_path) => {
-                        // eprintln!("DEBUG: Found glob match: {}", expanded_path.display());
-                        // Convert to absolute path to ensure consistency
-                        let absolute_path = if expanded_path.is_absolute() {
-                            expanded_path
-                        } else {
-                            std::env::current_dir()
-                                .unwrap_or_else(|_| Path::new(".").to_path_buf())
-                                .join(expanded_path)
-                        };
-                        expanded_paths.push(absolute_path);
-                    }
-                    Err(e) => {
-                        // Log the error but continue with other matches
-                        eprintln!("Warning: Glob pattern error for '{}': {}", path_str, e);
-                    }
-                }
-            }
-        } else {
-            // No glob patterns, use the path as-is, converting to absolute
-            let absolute_path = if path.is_absolute() {
-                path.to_path_buf()
-            } else {
-                std::env::current_dir()
-                    .unwrap_or_else(|_| Path::new(".").to_path_buf())
-                    .join(path)
-            };
-            expanded_paths.push(absolute_path);
-        }
-
-        Ok(expanded_paths)
+/// Expand an input path into the absolute paths it refers to
+fn expand_globs(input: &str) -> Vec<PathBuf> {
+    if !is_glob(input) {
+        return vec![absolute_path(Path::new(input))];
     }
 
-    fn discover_files_in_path(
-        &self,
-        path: &Path,
-        repo_info: &RepositoryInfo,
-        context: &ProcessingContext,
-    ) -> Result<Vec<ProcessedFile>> {
-        // eprintln!("DEBUG: discover_files_in_path called with: {}", path.display());
-        let mut files = Vec::new();
-
-        // First, expand glob patterns
-        let expanded_paths = self.expand_globs(path)?;
-
-        for expanded_path in expanded_paths {
-            // eprintln!("DEBUG: Processing expanded path: {}", expanded_path.display());
-            // eprintln!("DEBUG: is_file: {}, is_directory: {}", context.file_system.is_file(&expanded_path), context.file_system.is_directory(&expanded_path));
-            if context.file_system.is_file(&expanded_path) {
-                // Single file
-                // eprintln!("DEBUG: Processing single file: {}", expanded_path.display());
-                if let Ok(processed_file) =
-                    self.process_single_file(&expanded_path, repo_info, context)
-                {
-                    // eprintln!("DEBUG: Successfully processed file: {} with content length: {}", processed_file.rel_path, processed_file.content.len());
-                    files.push(processed_file);
-                } else {
-                    // eprintln!("DEBUG: Failed to process file: {}", expanded_path.display());
+    match glob::glob(input) {
+        Ok(entries) => entries
+            .filter_map(|entry| match entry {
+                Ok(path) => Some(absolute_path(&path)),
+                Err(e) => {
+                    debug!("Glob entry error for '{}': {}", input, e);
+                    None
                 }
-            } else if context.file_system.is_directory(&expanded_path) {
-                // Directory - walk recursively
-                // eprintln!("DEBUG: Processing directory: {}", expanded_path.display());
-                self.walk_directory(&expanded_path, repo_info, context, &mut files)?;
-            } else {
-                // eprintln!("DEBUG: Path is neither file nor directory: {}", expanded_path.display());
-            }
+            })
+            .collect(),
+        Err(e) => {
+            debug!("Invalid glob pattern '{}': {}", input, e);
+            Vec::new()
         }
+    }
+}
 
-        Ok(files)
+/// Directory an input's ignore rules start from: the input itself for
+/// directories, the parent for files and the part before the wildcards for globs
+pub(crate) fn input_root(input: &str) -> PathBuf {
+    if is_glob(input) {
+        return absolute_path(&glob_root(input));
+    }
+    let path = absolute_path(Path::new(input));
+    match path.parent() {
+        Some(parent) if !path.is_dir() => parent.to_path_buf(),
+        _ => path,
     }
+}
 
-    fn process_single_file(
-        &self,
-        file_path: &Path,
-        repo_info: &RepositoryInfo,
-        context: &ProcessingContext,
-    ) -> Result<ProcessedFile> {
-        // eprintln!("DEBUG: process_single_file called for: {}", file_path.display());
-        // Check if file should be ignored
-        if self.should_ignore_file(file_path, context) {
-            // eprintln!("DEBUG: File ignored: {}", file_path.display());
-            return Err(anyhow!("File ignored: {}", file_path.display()));
-        }
-
-        // Read file content
-        // eprintln!("DEBUG: Reading file content for: {}", file_path.display());
-        let content = match crate::repository::convenience::read_file_content_safe(
-            file_path,
-            &*context.file_system,
-        ) {
-            Ok(content) => {
-                // eprintln!("DEBUG: Successfully read file content, length: {}", content.len());
-                content
+/// Collect the files under `dir`, skipping hidden entries, files that do not
+/// match the include patterns (relative to `dir`) and whatever the ignore rules
+/// exclude. Ignored directories are recorded once and not entered.
+fn walk_directory(
+    dir: &Path,
+    base_dir: &Path,
+    rules: &IgnoreRules,
+    context: &ProcessingContext,
+    candidates: &mut Vec<Candidate>,
+) -> Result<()> {
+    let include_patterns = &context.input_config.include_patterns;
+    let include = if include_patterns.is_empty() {
+        None
+    } else {
+        Some(IncludePatterns::new(dir, include_patterns)?)
+    };
+    let follow = context.input_config.follow_symlinks;
+    let root = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    let walker = WalkDir::new(dir)
+        .follow_links(follow != FollowSymlinks::Never)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            if entry.depth() == 0 {
+                return true;
             }
-            Err(e) => {
-                // eprintln!("DEBUG: Failed to read file content: {}", e);
-                return Err(anyhow!(
-                    "Failed to read file '{}': {}",
-                    file_path.display(),
-                    e
-                ));
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                return false;
             }
-        };
-
-        // Create processed file
-        // eprintln!("DEBUG: Creating relative path for: {} with base: {}", file_path.display(), repo_info.root_path.display());
-        let rel_path = match crate::repository::convenience::get_relative_path(
-            file_path,
-            &repo_info.root_path,
-        ) {
-            Ok(path) => {
-                let rel_path_str = path.to_string_lossy().to_string();
-                // eprintln!("DEBUG: Successfully created relative path: {}", rel_path_str);
-                rel_path_str
+            if entry.path_is_symlink() && !check_symlink(entry.path(), &root, base_dir, context) {
+                return false;
             }
-            Err(e) => {
-                // eprintln!("DEBUG: Failed to create relative path: {}", e);
-                return Err(anyhow!(
-                    "Failed to create relative path for '{}': {}",
-                    file_path.display(),
-                    e
-                ));
+            if !entry.file_type().is_dir() {
+                return true;
             }
-        };
-
-        // Calculate priority
-        let priority = self.calculate_priority(&rel_path, repo_info, context);
+            if let Some(detail) = skipped_checkout(entry.path(), context) {
+                let rel_path = format!("{}/", normalize_path(entry.path(), base_dir));
+                debug!("Skipping {rel_path} ({detail})");
+                context.record_exclusion(
+                    ExcludedFile::new(rel_path, ExclusionReason::Ignored).with_detail(detail),
+                );
+                return false;
+            }
+            match rules.matched(entry.path(), true) {
+                Some(rule) => {
+                    let rel_path = format!("{}/", normalize_path(entry.path(), base_dir));
+                    record_ignored(rel_path, &rule, base_dir, context);
+                    false
+                }
+                None => true,
+            }
+        });
 
-        Ok(ProcessedFile::new(rel_path, content, priority, 0))
+    for entry in walker {
+        match entry {
+            Ok(entry)
+                if entry.file_type().is_file()
+                    && include
+                        .as_ref()
+                        .is_none_or(|include| include.matches(entry.path())) =>
+            {
+                add_candidate(entry.path(), base_dir, rules, context, None, candidates);
+            }
+            Ok(_) => {}
+            // Followed links that loop or dangle only show up as errors
+            Err(e) => match (e.path(), e.loop_ancestor()) {
+                (Some(path), Some(ancestor)) => {
+                    let ancestor = match normalize_path(ancestor, base_dir) {
+                        rel if rel.is_empty() => ".".to_string(),
+                        rel => rel,
+                    };
+                    let detail = format!("cycle back to {ancestor}/");
+                    record_symlink_skipped(path, detail, base_dir, context);
+                }
+                (Some(path), None)
+                    if path.is_symlink()
+                        && !path.exists()
+                        && !path
+                            .file_name()
+                            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+                        && rules.matched(path, false).is_none() =>
+                {
+                    check_symlink(path, &root, base_dir, context);
+                }
+                _ => debug!("Walk error under {}: {}", dir.display(), e),
+            },
+        }
     }
 
-    fn should_ignore_file(&self, path: &Path, context: &ProcessingContext) -> bool {
-        // eprintln!("DEBUG: should_ignore_file called for: {}", path.display());
-        let _path_str = path.to_string_lossy();
-        let _file_name = path.file_name().unwrap_or_default().to_string_lossy();
-        // eprintln!("DEBUG: path_str: {}, file_name: {}", path_str, file_name);
-
-        // First check binary extensions - these always take precedence
-        let is_binary = path
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .map(|ext| context.input_config.binary_extensions.contains(ext))
-            .unwrap_or(false);
+    Ok(())
+}
 
-        if is_binary {
-            // eprintln!("DEBUG: File ignored due to binary extension");
+/// Whether to follow the symbolic link at `path` found while walking `root`.
+/// Followed links are recorded for the tree header, others as exclusions.
+fn check_symlink(path: &Path, root: &Path, base_dir: &Path, context: &ProcessingContext) -> bool {
+    let target = std::fs::read_link(path)
+        .map(|target| target.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let detail = match (context.input_config.follow_symlinks, path.canonicalize()) {
+        (FollowSymlinks::Never, _) => format!("-> {target} (follow_symlinks is never)"),
+        (_, Err(_)) => format!("-> {target} (broken link)"),
+        (FollowSymlinks::WithinRoot, Ok(resolved)) if !resolved.starts_with(root) => {
+            format!("-> {target} (outside {})", root.display())
+        }
+        _ => {
+            context.record_symlink(normalize_path(path, base_dir), target);
             return true;
         }
+    };
+    record_symlink_skipped(path, detail, base_dir, context);
+    false
+}
 
-        // Check if file should be ignored by .gitignore files
-        let ignored_by_gitignore = self.is_ignored_by_gitignore(path, context);
-        // eprintln!("DEBUG: ignored_by_gitignore: {}", ignored_by_gitignore);
-
-        // Check if file should be ignored by default patterns (like LICENSE)
-        let ignored_by_default = self.is_ignored_by_default_patterns(path, context);
-        // eprintln!("DEBUG: ignored_by_default: {}", ignored_by_default);
-
-        // Check if file is allowlisted by any source (.gitignore or config)
-        let allowlisted = self.is_allowlisted(path, context);
-        // eprintln!("DEBUG: allowlisted: {}", allowlisted);
-
-        // A file is ignored if it's ignored by any source AND not allowlisted
-
-        // eprintln!("DEBUG: final should_ignore: {}", should_ignore);
+fn record_symlink_skipped(
+    path: &Path,
+    detail: String,
+    base_dir: &Path,
+    context: &ProcessingContext,
+) {
+    let rel_path = normalize_path(path, base_dir);
+    debug!("Skipping symlink: {rel_path} ({detail})");
+    context.record_exclusion(
+        ExcludedFile::new(rel_path, ExclusionReason::Symlink).with_detail(detail),
+    );
+}
 
-        (ignored_by_gitignore || ignored_by_default) && !allowlisted
+/// Why the directory `path` is left out as a nested git checkout, if it is.
+/// Linked worktrees are always left out since they repeat their repository.
+fn skipped_checkout(path: &Path, context: &ProcessingContext) -> Option<&'static str> {
+    match GitCheckout::detect(path)? {
+        GitCheckout::Submodule if context.input_config.submodules == Submodules::Skip => {
+            Some("git submodule")
+        }
+        GitCheckout::Worktree => Some("git worktree"),
+        _ => None,
     }
+}
 
-    /// Check if a file should be ignored by default patterns (like LICENSE)
-    fn is_ignored_by_default_patterns(&self, path: &Path, context: &ProcessingContext) -> bool {
-        let path_str = path.to_string_lossy();
-        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
-
-        // Get the relative path from the repository root
-        let rel_path = match path.strip_prefix(&context.repository_info.root_path) {
-            Ok(rel_path) => rel_path.to_string_lossy().to_string(),
-            Err(_) => path_str.to_string(), // Fallback to full path if we can't make it relative
-        };
+/// Whether `path` is an archive file that is read like a directory
+fn is_archive_file(path: &Path) -> bool {
+    ArchiveFileSystem::is_archive(path) && path.is_file()
+}
 
-        // Check default ignore patterns (these are built into the config)
-        for pattern in &context.input_config.ignore_patterns {
-            let pattern_str = pattern.as_str();
-            // Skip allowlist patterns (starting with !) for default pattern matching
-            if !pattern_str.starts_with('!') {
-                let matches_path = pattern.matches(&path_str)
-                    || pattern.matches(&file_name)
-                    || pattern.matches(&rel_path);
-                if matches_path {
-                    // eprintln!("DEBUG: File ignored by default pattern: {} (matched path: {})", pattern_str, rel_path);
-                    return true;
-                }
-            }
+/// Same as [`walk_directory`] for the entries of an archive, with paths
+/// below the archive's path. Only ignore files inside the archive apply.
+fn walk_archive(
+    path: &Path,
+    base_dir: &Path,
+    patterns: &Arc<Gitignore>,
+    user_patterns: &Arc<Gitignore>,
+    context: &ProcessingContext,
+    candidates: &mut Vec<Candidate>,
+) -> Result<()> {
+    let archive = match ArchiveFileSystem::open(path, context.input_config.max_file_size) {
+        Ok(archive) => Arc::new(archive),
+        Err(e) => {
+            debug!("{e}");
+            context.record_exclusion(
+                ExcludedFile::new(normalize_path(path, base_dir), ExclusionReason::Unreadable)
+                    .with_detail(e.to_string()),
+            );
+            return Ok(());
+        }
+    };
+    let rules = IgnoreRules::for_archive(archive.clone(), patterns.clone())
+        .with_user_patterns(user_patterns.clone());
+    let include_patterns = &context.input_config.include_patterns;
+    let include = if include_patterns.is_empty() {
+        None
+    } else {
+        Some(IncludePatterns::new(path, include_patterns)?)
+    };
+    let is_hidden = |entry: &Path| {
+        entry
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+    };
+
+    // Parents sort before their children, so a pruned directory is seen first
+    let mut pruned: Vec<&Path> = Vec::new();
+    for dir in archive.directories() {
+        if pruned.iter().any(|parent| dir.starts_with(parent)) {
+            continue;
+        }
+        if is_hidden(dir) {
+            pruned.push(dir);
+        } else if let Some(rule) = rules.matched(dir, true) {
+            let rel_path = format!("{}/", normalize_path(dir, base_dir));
+            record_ignored(rel_path, &rule, base_dir, context);
+            pruned.push(dir);
         }
-
-        false
     }
 
-    /// Check if a file is allowlisted by any source (.gitignore or config patterns)
-    fn is_allowlisted(&self, path: &Path, context: &ProcessingContext) -> bool {
-        let path_str = path.to_string_lossy();
-        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
-        // eprintln!("DEBUG: Checking if file is allowlisted: {}", path_str);
-
-        // Check allowlist patterns from config
-        for pattern in &context.input_config.ignore_patterns {
-            let pattern_str = pattern.as_str();
-            if pattern_str.starts_with('!') {
-                let matches_path = pattern.matches(&path_str) || pattern.matches(&file_name);
-                // eprintln!("DEBUG: Checking config allowlist pattern: {} against {} -> {}", pattern_str, path_str, matches_path);
-                if matches_path {
-                    // eprintln!("DEBUG: File allowlisted by config pattern: {}", pattern_str);
-                    return true;
-                }
-            }
+    let file_system: Arc<dyn FileSystem + Send + Sync> = archive.clone();
+    for file in archive.files() {
+        if is_hidden(file) || pruned.iter().any(|dir| file.starts_with(dir)) {
+            continue;
         }
-
-        // Check allowlist patterns from .gitignore
-        let root_path = &context.repository_info.root_path;
-        // eprintln!("DEBUG: Checking .gitignore in: {}", root_path.display());
-
-        // Manual .gitignore parsing for allowlist patterns
-        let gitignore_path = root_path.join(".gitignore");
-        if gitignore_path.exists() {
-            // eprintln!("DEBUG: .gitignore file exists at: {}", gitignore_path.display());
-            if let Ok(contents) = std::fs::read_to_string(&gitignore_path) {
-                // eprintln!("DEBUG: .gitignore contents: {:?}", contents);
-
-                // Parse .gitignore manually for allowlist patterns
-                for line in contents.lines() {
-                    let line = line.trim();
-                    if line.starts_with('!') && !line.starts_with("!#") {
-                        let pattern = &line[1..]; // Remove the '!' prefix
-                                                  // eprintln!("DEBUG: Found allowlist pattern in .gitignore: {}", pattern);
-
-                        // Check if this pattern matches our file
-                        let path_str = path.to_string_lossy();
-                        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
-
-                        // Simple pattern matching (basic glob support)
-                        if Self::matches_pattern(pattern, &path_str)
-                            || Self::matches_pattern(pattern, &file_name)
-                        {
-                            // eprintln!("DEBUG: File allowlisted by .gitignore pattern: {}", pattern);
-                            return true;
-                        }
-                    }
-                }
-            } else {
-                // eprintln!("DEBUG: Failed to read .gitignore file");
-            }
-        } else {
-            // eprintln!("DEBUG: .gitignore file does not exist");
+        if include.as_ref().is_none_or(|include| include.matches(file)) {
+            add_candidate(
+                file,
+                base_dir,
+                &rules,
+                context,
+                Some(&file_system),
+                candidates,
+            );
         }
+    }
+    Ok(())
+}
 
-        false
+/// Record a path left out by an ignore rule, naming the rule and where it comes from
+fn record_ignored(
+    rel_path: String,
+    rule: &IgnoreRule,
+    base_dir: &Path,
+    context: &ProcessingContext,
+) {
+    let detail = match &rule.source {
+        Some(source) => format!("{}: {}", normalize_path(source, base_dir), rule.pattern),
+        None => rule.pattern.clone(),
+    };
+    debug!("Skipping ignored path: {rel_path} ({detail})");
+    context.record_exclusion(
+        ExcludedFile::new(rel_path, ExclusionReason::Ignored).with_detail(detail),
+    );
+}
+
+/// Check an explicit file argument against `binary_extensions`, recording it when
+/// it matches. Files found by walking are only checked by content, so source
+/// extensions that double as binary ones (`.ts`) are still read
+fn has_binary_extension(path: &Path, base_dir: &Path, context: &ProcessingContext) -> bool {
+    let is_binary = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| context.input_config.binary_extensions.contains(ext));
+    if is_binary {
+        let rel_path = normalize_path(path, base_dir);
+        debug!("Skipping binary file: {rel_path}");
+        context.record_exclusion(ExcludedFile::new(
+            rel_path,
+            ExclusionReason::BinaryExtension,
+        ));
     }
+    is_binary
+}
 
-    /// Check if a file should be ignored based on .gitignore files
-    fn is_ignored_by_gitignore(&self, path: &Path, context: &ProcessingContext) -> bool {
-        let root_path = &context.repository_info.root_path;
-        // eprintln!("DEBUG: Checking .gitignore for: {} (root: {})", path.display(), root_path.display());
+/// Queue `path` for reading, or record why it was left out
+fn add_candidate(
+    path: &Path,
+    base_dir: &Path,
+    rules: &IgnoreRules,
+    context: &ProcessingContext,
+    file_system: Option<&Arc<dyn FileSystem + Send + Sync>>,
+    candidates: &mut Vec<Candidate>,
+) {
+    let rel_path = normalize_path(path, base_dir);
+
+    if let Some(rule) = rules.matched(path, false) {
+        record_ignored(rel_path, &rule, base_dir, context);
+        return;
+    }
 
-        // Create a gitignore matcher for the root directory
-        let (gi, error) = ignore::gitignore::Gitignore::new(root_path);
+    let attributes = rules.attributes();
+    let binary_attr = attributes.set_by(path, false, "binary").or_else(|| {
+        attributes
+            .lookup(path, false, "diff")
+            .filter(|found| found.value == AttrValue::Unset)
+    });
+    if let Some(found) = binary_attr {
+        let detail = format!(
+            "{}: {}",
+            normalize_path(&found.source, base_dir),
+            found.line
+        );
+        debug!("Skipping binary file: {rel_path} ({detail})");
+        context.record_exclusion(
+            ExcludedFile::new(rel_path, ExclusionReason::BinaryContent).with_detail(detail),
+        );
+        return;
+    }
 
-        if let Some(_e) = error {
-            // eprintln!("DEBUG: Failed to create gitignore matcher: {}", e);
-            return false;
-        }
+    if !context.input_config.include_generated && attributes.is_set(path, "linguist-generated") {
+        record_generated(
+            &rel_path,
+            ExclusionReason::Generated,
+            "linguist-generated in .gitattributes".to_string(),
+            context,
+        );
+        return;
+    }
 
-        // Get the relative path from root to the file
-        match path.strip_prefix(root_path) {
-            Ok(rel_path) => {
-                // eprintln!("DEBUG: Relative path: {}", rel_path.display());
-                let matched = gi.matched(rel_path, false);
+    candidates.push(Candidate {
+        path: path.to_path_buf(),
+        rel_path,
+        category: attributes
+            .is_set(path, "linguist-documentation")
+            .then_some(FileCategory::Documentation),
+        priority: rules.scoped_priority(path),
+        file_system: file_system.cloned(),
+    });
+}
 
-                // eprintln!("DEBUG: .gitignore matched: {:?}, is_ignore: {}", matched, should_ignore);
-                matched.is_ignore()
-            }
-            Err(_) => {
-                // eprintln!("DEBUG: Could not make path relative: {} relative to {}", path.display(), root_path.display());
-                false
+/// Read a candidate, skipping files over the size limits, unreadable and binary files
+fn read_candidate(
+    candidate: &Candidate,
+    boosts: &HashMap<String, i32>,
+    summarize: &IncludePatterns,
+    context: &ProcessingContext,
+) -> Option<ProcessedFile> {
+    let rel_path = &candidate.rel_path;
+    let input_config = &context.input_config;
+    let file_system = candidate
+        .file_system
+        .as_ref()
+        .unwrap_or(&context.file_system);
+
+    // Checked from the metadata so huge files are never read into memory
+    if let Some(max_file_size) = input_config.max_file_size {
+        if let Ok(metadata) = file_system.get_file_metadata(&candidate.path) {
+            if metadata.size > max_file_size {
+                record_too_large(
+                    rel_path,
+                    format!("{} bytes > max_file_size {}", metadata.size, max_file_size),
+                    context,
+                );
+                return None;
             }
         }
     }
 
-    fn calculate_priority(
-        &self,
-        rel_path: &str,
-        repo_info: &RepositoryInfo,
-        context: &ProcessingContext,
-    ) -> i32 {
-        let mut priority = 0;
-
-        // Apply priority rules
-        for rule in &context.processing_config.priority_rules {
-            if let Ok(regex) = regex::Regex::new(&rule.pattern) {
-                if regex.is_match(rel_path) {
-                    priority += rule.score;
-                }
-            }
+    let content = match file_system.read_file(&candidate.path) {
+        Ok(content) => content,
+        Err(e) => {
+            debug!("Failed to read {rel_path}: {e}");
+            context.record_exclusion(
+                ExcludedFile::new(rel_path, ExclusionReason::Unreadable).with_detail(e.to_string()),
+            );
+            return None;
         }
-
-        // Apply git boost if available
-        if let Some(commit_time) = repo_info.commit_times.get(rel_path) {
-            let max_boost = context.input_config.git_boost_max.unwrap_or(100);
-            let boost = self.calculate_git_boost(*commit_time, &repo_info.commit_times, max_boost);
-            priority += boost;
+    };
+
+    let Some(decoded) = decode(&content, input_config.legacy_encoding) else {
+        debug!("Skipping binary file: {rel_path}");
+        context.record_exclusion(ExcludedFile::new(rel_path, ExclusionReason::BinaryContent));
+        return None;
+    };
+    let mut content = decoded.text;
+    if input_config.strip_bom {
+        content = strip_bom(content);
+    }
+    if input_config.normalize_newlines {
+        content = normalize_newlines(content);
+    }
+    if is_notebook(rel_path) {
+        match render_notebook(&content, input_config.notebook_outputs) {
+            Ok(rendered) => content = rendered,
+            Err(e) => debug!("Keeping {rel_path} as is: {e}"),
+        }
+    }
+    if summarize.matches(&candidate.path) {
+        match summarize_file(rel_path, &content, input_config.summary_rows) {
+            Some(summary) => content = summary,
+            None => debug!("Keeping {rel_path} as is: no summary for this file"),
         }
+    }
 
-        priority
+    if let Some(max_file_lines) = input_config.max_file_lines {
+        let lines = count_lines(content.as_bytes());
+        if lines > max_file_lines {
+            record_too_large(
+                rel_path,
+                format!("{} lines > max_file_lines {}", lines, max_file_lines),
+                context,
+            );
+            return None;
+        }
     }
 
-    fn calculate_git_boost(
-        &self,
-        file_time: u64,
-        all_times: &HashMap<String, u64>,
-        max_boost: i32,
-    ) -> i32 {
-        if all_times.is_empty() {
-            return 0;
+    if !input_config.include_generated {
+        if let Some(marker) = generated_marker(&content) {
+            record_generated(
+                rel_path,
+                ExclusionReason::Generated,
+                format!("'{}' marker", marker),
+                context,
+            );
+            return None;
         }
+        if let Some(reason) = minified_reason(&content) {
+            record_generated(rel_path, ExclusionReason::Minified, reason, context);
+            return None;
+        }
+    }
 
-        let times: Vec<&u64> = all_times.values().collect();
-        let min_time = times.iter().min().map_or(file_time, |&&t| t);
-        let max_time = times.iter().max().map_or(file_time, |&&t| t);
+    let priority = get_file_priority(rel_path, &context.processing_config.priority_rules)
+        + candidate.priority
+        + boosts.get(rel_path).copied().unwrap_or(0);
 
-        if max_time == min_time {
-            return 0; // All files have same timestamp
+    let mut file = match candidate.category {
+        Some(category) => {
+            ProcessedFile::new_with_category(rel_path.clone(), content, priority, 0, category)
         }
+        None => ProcessedFile::new(rel_path.clone(), content, priority, 0),
+    };
+    file.encoding = decoded.encoding;
+    Some(file)
+}
 
-        let normalized = (file_time - min_time) as f64 / (max_time - min_time) as f64;
-        (normalized * max_boost as f64).round() as i32
-    }
+fn record_generated(
+    rel_path: &str,
+    reason: ExclusionReason,
+    detail: String,
+    context: &ProcessingContext,
+) {
+    debug!("Skipping {} file: {rel_path} ({detail})", reason.name());
+    context.record_exclusion(ExcludedFile::new(rel_path, reason).with_detail(detail));
+}
 
-    fn walk_directory(
-        &self,
-        dir_path: &Path,
-        repo_info: &RepositoryInfo,
-        context: &ProcessingContext,
-        files: &mut Vec<ProcessedFile>,
-    ) -> Result<()> {
-        let entries = context.file_system.read_directory(dir_path)?;
-
-        for entry in entries {
-            let entry_path = &entry;
-
-            if context.file_system.is_directory(entry_path) {
-                // Recurse into subdirectory
-                self.walk_directory(entry_path, repo_info, context, files)?;
-            } else if context.file_system.is_file(entry_path) {
-                // Process file
-                if let Ok(processed_file) = self.process_single_file(entry_path, repo_info, context)
-                {
-                    files.push(processed_file);
-                }
-            }
-        }
+fn record_too_large(rel_path: &str, detail: String, context: &ProcessingContext) {
+    debug!("Skipping too large file: {rel_path} ({detail})");
+    context.record_exclusion(
+        ExcludedFile::new(rel_path, ExclusionReason::TooLarge).with_detail(detail),
+    );
+}
 
-        Ok(())
+/// Number of lines, counting a last line without a trailing newline
+fn count_lines(content: &[u8]) -> usize {
+    let newlines = content.iter().filter(|&&b| b == b'\n').count();
+    newlines + usize::from(!content.is_empty() && !content.ends_with(b"\n"))
+}
+
+/// Priority boost for recently committed files, keyed by path relative to `base_dir`
+fn commit_boosts(base_dir: &Path, context: &ProcessingContext) -> HashMap<String, i32> {
+    let Some(repo_root) = RealGitOperations::discover(base_dir)
+        .and_then(|git| git.get_repository_root())
+        .ok()
+    else {
+        return HashMap::new();
+    };
+    let Ok(prefix) = base_dir.strip_prefix(&repo_root) else {
+        return HashMap::new();
+    };
+    let max_commits = context.input_config.max_git_depth.try_into().unwrap_or(0);
+    let mut by_submodule = get_commit_times_by_submodule(&repo_root, max_commits);
+    if context.input_config.submodules == Submodules::Skip {
+        by_submodule.retain(|submodule, _| submodule.is_empty());
     }
 
-    /// Simple pattern matching for .gitignore patterns
-    fn matches_pattern(pattern: &str, text: &str) -> bool {
-        // Handle exact matches
-        if pattern == text {
-            return true;
-        }
+    // Commit times are relative to the repository or submodule they come from
+    let commit_times: HashMap<String, u64> = by_submodule
+        .into_iter()
+        .flat_map(|(submodule, times)| {
+            times
+                .into_iter()
+                .map(move |(path, time)| (format!("{submodule}{path}"), time))
+        })
+        .filter_map(|(path, time)| {
+            let rel_path = Path::new(&path).strip_prefix(prefix).ok()?;
+            Some((rel_path.to_path_buf().to_slash()?.to_string(), time))
+        })
+        .collect();
+    compute_recentness_boost(
+        &commit_times,
+        context.input_config.git_boost_max.unwrap_or(100),
+    )
+}
 
-        // Handle simple glob patterns
-        if pattern.contains('*') {
-            // Simple wildcard matching
-            let pattern_parts: Vec<&str> = pattern.split('*').collect();
-            if pattern_parts.len() == 2 {
-                let (prefix, suffix) = (pattern_parts[0], pattern_parts[1]);
-                return text.starts_with(prefix) && text.ends_with(suffix);
-            }
-        }
+/// Create a relative, slash-normalized path
+pub fn normalize_path(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .to_path_buf()
+        .to_slash()
+        .unwrap_or_default()
+        .to_string()
+}
 
-        // Handle patterns ending with /
-        if pattern.ends_with('/') && text.starts_with(pattern) {
-            return true;
+/// Category weighting stage - adds the category priority offset to each file
+pub struct CategoryWeightingStage;
+
+impl ProcessingStage for CategoryWeightingStage {
+    fn process(
+        &self,
+        mut files: Vec<ProcessedFile>,
+        context: &ProcessingContext,
+    ) -> Result<Vec<ProcessedFile>> {
+        let weights = &context.processing_config.category_weights;
+        for file in &mut files {
+            file.priority += weights.get_offset(file.category);
         }
+        Ok(files)
+    }
 
-        false
+    fn name(&self) -> &'static str {
+        "CategoryWeighting"
     }
 }
 
@@ -746,6 +1034,7 @@ impl ProcessingPipeline {
     pub fn new(context: ProcessingContext) -> Self {
         let stages: Vec<Box<dyn ProcessingStage>> = vec![
             Box::new(FileDiscoveryStage::new()),
+            Box::new(CategoryWeightingStage),
             Box::new(ContentFilteringStage),
             Box::new(OutputFormattingStage),
         ];
@@ -753,6 +1042,11 @@ impl ProcessingPipeline {
         Self { stages, context }
     }
 
+    /// Create a pipeline that runs exactly `stages`, in order
+    pub fn with_stages(context: ProcessingContext, stages: Vec<Box<dyn ProcessingStage>>) -> Self {
+        Self { stages, context }
+    }
+
     pub fn add_stage(&mut self, stage: Box<dyn ProcessingStage>) {
         self.stages.push(stage);
     }
@@ -761,7 +1055,30 @@ impl ProcessingPipeline {
         let mut files: Vec<ProcessedFile> = Vec::new();
 
         for stage in &self.stages {
+            let start_time = Instant::now();
+            if let Ok(mut stats) = self.context.stats.lock() {
+                stats.stages.push(StageStats {
+                    name: stage.name().to_string(),
+                    files_in: files.len(),
+                    ..Default::default()
+                });
+            }
+
             files = stage.process(files, &self.context)?;
+
+            let files_out = files.len();
+            self.context.update_current_stage(|stats| {
+                stats.files_out = files_out;
+                stats.duration_ms = start_time.elapsed().as_millis();
+                debug!(
+                    "Stage {}: {} -> {} files, {} changed in {}ms",
+                    stats.name,
+                    stats.files_in,
+                    stats.files_out,
+                    stats.files_changed,
+                    stats.duration_ms
+                );
+            });
         }
 
         // Final sorting by priority and file_index
@@ -777,6 +1094,16 @@ impl ProcessingPipeline {
     pub fn get_stats(&self) -> ProcessingStats {
         self.context.stats.lock().unwrap().clone()
     }
+
+    /// Files the stages left out so far, sorted by path
+    pub fn get_excluded_files(&self) -> Vec<ExcludedFile> {
+        self.context.get_excluded_files()
+    }
+
+    /// Symbolic links followed during discovery, by relative path
+    pub fn get_symlinks(&self) -> BTreeMap<String, String> {
+        self.context.get_symlinks()
+    }
 }
 
 /// Pipeline builder for fluent configuration
@@ -1,3 +1,4 @@
+use crate::repository::GitCheckout;
 use git2;
 use regex;
 use serde::{Deserialize, Serialize};
@@ -101,10 +102,10 @@ pub fn get_recent_commit_times_git2(
     repo_path: &Path,
     max_commits: usize,
 ) -> Option<HashMap<String, u64>> {
-    // Walk up until you find a .git folder but not higher than the base of the given repo_path
+    // Walk up to the top of the checkout (repository, submodule or worktree) containing repo_path
     let mut current_path = repo_path.to_path_buf();
     while current_path.components().count() > 1 {
-        if current_path.join(".git").exists() {
+        if GitCheckout::detect(&current_path).is_some() {
             break;
         }
         current_path = current_path.parent()?.to_path_buf();
@@ -175,3 +176,42 @@ pub fn get_recent_commit_times_git2(
 
     Some(commit_times)
 }
+
+/// Commit times of the repository at `repo_root` and, recursively, of its
+/// checked out submodules. Each map is keyed by its path prefix: "" for the
+/// repository itself and e.g. "libs/foo/" for a submodule, whose file paths
+/// are relative to the submodule.
+pub fn get_commit_times_by_submodule(
+    repo_root: &Path,
+    max_commits: usize,
+) -> HashMap<String, HashMap<String, u64>> {
+    let mut maps = HashMap::new();
+    collect_submodule_commit_times(repo_root, String::new(), max_commits, &mut maps);
+    maps
+}
+
+fn collect_submodule_commit_times(
+    repo_root: &Path,
+    prefix: String,
+    max_commits: usize,
+    maps: &mut HashMap<String, HashMap<String, u64>>,
+) {
+    if let Some(commit_times) = get_recent_commit_times_git2(repo_root, max_commits) {
+        maps.insert(prefix.clone(), commit_times);
+    }
+    let Ok(repo) = git2::Repository::open(repo_root) else {
+        return;
+    };
+    for submodule in repo.submodules().unwrap_or_default() {
+        let path = repo_root.join(submodule.path());
+        // Submodules that are not checked out have nothing to prioritize
+        if GitCheckout::detect(&path) != Some(GitCheckout::Submodule) {
+            continue;
+        }
+        let Some(sub_path) = submodule.path().to_str() else {
+            continue;
+        };
+        let sub_prefix = format!("{prefix}{}/", sub_path.replace('\\', "/"));
+        collect_submodule_commit_times(&path, sub_prefix, max_commits, maps);
+    }
+}
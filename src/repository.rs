@@ -2,8 +2,9 @@ use crate::models::{InputConfig, RepositoryInfo};
 use anyhow::{anyhow, Result};
 use git2;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     fs,
+    io::Read,
     path::{Path, PathBuf},
     sync::{Arc, OnceLock},
     time::SystemTime,
@@ -49,6 +50,12 @@ pub trait GitOperations {
 
     /// Get repository root path
     fn get_repository_root(&self) -> Result<PathBuf>;
+
+    /// Get the id of the commit HEAD points to (None for a repository without commits)
+    fn get_head_commit(&self) -> Result<Option<String>>;
+
+    /// Check whether tracked files have uncommitted changes
+    fn is_dirty(&self) -> Result<bool>;
 }
 
 /// Real file system implementation
@@ -122,6 +129,252 @@ impl FileSystem for RealFileSystem {
     }
 }
 
+/// How a directory is checked out, judging from its `.git` entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitCheckout {
+    /// `.git` is the repository itself
+    Repository,
+    /// `.git` is a file pointing into the superproject's `.git/modules/`
+    Submodule,
+    /// `.git` is a file pointing into another repository's `.git/worktrees/`
+    Worktree,
+}
+
+impl GitCheckout {
+    /// The kind of checkout whose top is `dir`, if any
+    pub fn detect(dir: &Path) -> Option<Self> {
+        let dot_git = dir.join(".git");
+        if dot_git.is_dir() {
+            return Some(Self::Repository);
+        }
+        let content = fs::read_to_string(&dot_git).ok()?;
+        let git_dir = dir.join(content.trim().strip_prefix("gitdir:")?.trim());
+        // Only linked worktrees share the object store of a main repository
+        if git_dir.join("commondir").is_file() {
+            Some(Self::Worktree)
+        } else {
+            Some(Self::Submodule)
+        }
+    }
+}
+
+/// Read-only file system over the files of a `.zip`, `.tar`, `.tar.gz` or
+/// `.tgz` archive. Entries are read into memory when the archive is opened,
+/// except those over the size limit, and are addressed as the archive's path
+/// joined with the entry path, so the archive behaves like a directory.
+pub struct ArchiveFileSystem {
+    root: PathBuf,
+    files: BTreeMap<PathBuf, ArchiveEntry>,
+    directories: BTreeSet<PathBuf>,
+    modified: SystemTime,
+}
+
+/// A file of an archive. Only its size is kept when it is over the size limit.
+struct ArchiveEntry {
+    size: u64,
+    content: Option<Vec<u8>>,
+}
+
+impl ArchiveFileSystem {
+    /// Whether `path` has the extension of a supported archive
+    pub fn is_archive(path: &Path) -> bool {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        [".zip", ".tar", ".tar.gz", ".tgz"]
+            .iter()
+            .any(|ext| name.ends_with(ext))
+    }
+
+    /// Read every regular file of the archive at `path`. Entries with absolute
+    /// paths or `..` components are skipped, and entries larger than
+    /// `max_entry_size` are listed without being decompressed.
+    pub fn open(path: &Path, max_entry_size: Option<u64>) -> Result<Self> {
+        let file = fs::File::open(path)
+            .map_err(|e| anyhow!("Failed to open archive '{}': {}", path.display(), e))?;
+        let modified = file
+            .metadata()
+            .and_then(|m| m.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let name = path.to_string_lossy().to_lowercase();
+        let limit = max_entry_size.unwrap_or(u64::MAX);
+        let entries = if name.ends_with(".zip") {
+            read_zip_entries(file, limit)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            read_tar_entries(flate2::read::GzDecoder::new(file), limit)
+        } else {
+            read_tar_entries(file, limit)
+        }
+        .map_err(|e| anyhow!("Failed to read archive '{}': {}", path.display(), e))?;
+
+        let mut archive = Self {
+            root: path.to_path_buf(),
+            files: BTreeMap::new(),
+            directories: BTreeSet::new(),
+            modified,
+        };
+        for (entry, file) in entries {
+            let Some(entry) = enclosed_entry_path(&entry) else {
+                continue;
+            };
+            let full = archive.root.join(entry);
+            archive.directories.extend(
+                full.ancestors()
+                    .skip(1)
+                    .take_while(|dir| *dir != archive.root)
+                    .map(Path::to_path_buf),
+            );
+            archive.files.insert(full, file);
+        }
+        Ok(archive)
+    }
+
+    /// Path of the archive, which is also the root of its entries
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Paths of all files, sorted
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    /// Paths of all directories below the root, sorted so parents come first
+    pub fn directories(&self) -> impl Iterator<Item = &Path> {
+        self.directories.iter().map(PathBuf::as_path)
+    }
+}
+
+impl FileSystem for ArchiveFileSystem {
+    fn path_exists(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_directory(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn is_directory(&self, path: &Path) -> bool {
+        path == self.root || self.directories.contains(path)
+    }
+
+    fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
+        let entry = self
+            .files
+            .get(path)
+            .ok_or_else(|| anyhow!("No file '{}' in archive", path.display()))?;
+        entry.content.clone().ok_or_else(|| {
+            anyhow!(
+                "'{}' ({} bytes) is over the size limit and was not read",
+                path.display(),
+                entry.size
+            )
+        })
+    }
+
+    fn read_directory(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        if !self.is_directory(path) {
+            return Err(anyhow!("No directory '{}' in archive", path.display()));
+        }
+        Ok(self
+            .directories
+            .iter()
+            .chain(self.files.keys())
+            .filter(|entry| entry.parent() == Some(path))
+            .cloned()
+            .collect())
+    }
+
+    fn get_file_metadata(&self, path: &Path) -> Result<FileMetadata> {
+        let size = match self.files.get(path) {
+            Some(entry) => entry.size,
+            None if self.is_directory(path) => 0,
+            None => return Err(anyhow!("No entry '{}' in archive", path.display())),
+        };
+        Ok(FileMetadata {
+            size,
+            modified: self.modified,
+            is_file: self.is_file(path),
+            is_directory: self.is_directory(path),
+            is_symlink: false,
+        })
+    }
+
+    fn is_symlink(&self, _path: &Path) -> bool {
+        false
+    }
+
+    fn resolve_symlink(&self, path: &Path) -> Result<PathBuf> {
+        Ok(path.to_path_buf())
+    }
+}
+
+/// Paths and contents of the regular files in a zip archive
+fn read_zip_entries(file: fs::File, limit: u64) -> Result<Vec<(PathBuf, ArchiveEntry)>> {
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        if !entry.is_file() {
+            continue;
+        }
+        let path = PathBuf::from(entry.name());
+        let size = entry.size();
+        entries.push((path, read_entry(&mut entry, size, limit)?));
+    }
+    Ok(entries)
+}
+
+/// Paths and contents of the regular files in a tar archive
+fn read_tar_entries(reader: impl Read, limit: u64) -> Result<Vec<(PathBuf, ArchiveEntry)>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        let size = entry.size();
+        entries.push((path, read_entry(&mut entry, size, limit)?));
+    }
+    Ok(entries)
+}
+
+/// Read an entry whose header gives `size`, unless it is over `limit`. The
+/// read stops past `limit` in case the header understates the size.
+fn read_entry(reader: &mut impl Read, size: u64, limit: u64) -> Result<ArchiveEntry> {
+    if size > limit {
+        return Ok(ArchiveEntry {
+            size,
+            content: None,
+        });
+    }
+    let mut content = Vec::with_capacity(size as usize);
+    reader
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut content)?;
+    let read = content.len() as u64;
+    Ok(ArchiveEntry {
+        size: read,
+        content: (read <= limit).then_some(content),
+    })
+}
+
+/// `entry` without `.` components, or None if it could escape the archive root
+fn enclosed_entry_path(entry: &Path) -> Option<PathBuf> {
+    let mut enclosed = PathBuf::new();
+    for component in entry.components() {
+        match component {
+            std::path::Component::Normal(part) => enclosed.push(part),
+            std::path::Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!enclosed.as_os_str().is_empty()).then_some(enclosed)
+}
+
 /// Real Git operations implementation
 pub struct RealGitOperations {
     repository: git2::Repository,
@@ -143,6 +396,29 @@ impl RealGitOperations {
             repo_path: repo_path.to_path_buf(),
         })
     }
+
+    /// Open the repository containing `path`, searching parent directories
+    pub fn discover(path: &Path) -> Result<Self> {
+        let repository = git2::Repository::discover(path).map_err(|e| {
+            anyhow!(
+                "Failed to find git repository for '{}': {}",
+                path.display(),
+                e
+            )
+        })?;
+
+        // Rebuild from components to drop the trailing separator git2 leaves on workdir()
+        let repo_path = repository
+            .workdir()
+            .unwrap_or_else(|| repository.path())
+            .components()
+            .collect::<PathBuf>();
+
+        Ok(Self {
+            repository,
+            repo_path,
+        })
+    }
 }
 
 impl GitOperations for RealGitOperations {
@@ -203,6 +479,27 @@ impl GitOperations for RealGitOperations {
     fn get_repository_root(&self) -> Result<PathBuf> {
         Ok(self.repo_path.clone())
     }
+
+    fn get_head_commit(&self) -> Result<Option<String>> {
+        match self.repository.head() {
+            Ok(head) => Ok(head.target().map(|oid| oid.to_string())),
+            // A freshly initialized repository has an unborn HEAD
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => Ok(None),
+            Err(e) => Err(anyhow!("Failed to resolve HEAD: {}", e)),
+        }
+    }
+
+    fn is_dirty(&self) -> Result<bool> {
+        let mut options = git2::StatusOptions::new();
+        options.include_untracked(false).include_ignored(false);
+
+        let statuses = self
+            .repository
+            .statuses(Some(&mut options))
+            .map_err(|e| anyhow!("Failed to read repository status: {}", e))?;
+
+        Ok(!statuses.is_empty())
+    }
 }
 
 /// File metadata structure
@@ -267,21 +564,10 @@ impl RepositoryFactory {
         Ok(repo_info)
     }
 
-    /// Check if a path is a git repository
+    /// Check if a path is inside a git repository, submodule or worktree
     fn is_git_repository(&self, path: &Path) -> bool {
-        // Walk up the directory tree to find a .git folder
-        let mut current = path.to_path_buf();
-        while current.components().count() > 0 {
-            if current.join(".git").exists() {
-                return true;
-            }
-            if let Some(parent) = current.parent() {
-                current = parent.to_path_buf();
-            } else {
-                break;
-            }
-        }
-        false
+        path.ancestors()
+            .any(|dir| GitCheckout::detect(dir).is_some())
     }
 
     /// Get cached git operations for a repository
@@ -318,6 +604,174 @@ pub fn get_repository_factory() -> &'static RepositoryFactory {
     REPOSITORY_FACTORY.get_or_init(RepositoryFactory::new)
 }
 
+/// State of a git attribute for a path, as set in `.gitattributes`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttrValue {
+    /// `attr`
+    Set,
+    /// `-attr`
+    Unset,
+    /// `attr=value`
+    Value(String),
+}
+
+/// The `.gitattributes` line that gives a path an attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttrMatch {
+    /// The state the line gives the attribute
+    pub value: AttrValue,
+    /// The line as written
+    pub line: String,
+    /// The attributes file the line comes from
+    pub source: PathBuf,
+}
+
+/// One `.gitattributes` line
+struct AttrRule {
+    matcher: ignore::gitignore::Gitignore,
+    /// Attributes in line order; None for `!attr`, which resets an attribute
+    attrs: Vec<(String, Option<AttrValue>)>,
+    line: String,
+    source: PathBuf,
+}
+
+/// `.gitattributes` rules for the files below a directory.
+///
+/// `.git/info/attributes` takes precedence over `.gitattributes` files, which
+/// take precedence over the ones in parent directories. Inside a file the last
+/// matching line wins.
+pub struct GitAttributes {
+    /// Highest directory whose `.gitattributes` applies
+    top: PathBuf,
+    /// Rules from `.git/info/attributes`
+    info: Vec<AttrRule>,
+    /// `.gitattributes` rules of each directory seen so far
+    dirs: std::sync::Mutex<HashMap<PathBuf, Arc<Vec<AttrRule>>>>,
+}
+
+impl GitAttributes {
+    /// Attributes for the files below `top`, plus `info/attributes` of the
+    /// git directory when there is a repository
+    pub fn new(top: &Path, git_dir: Option<&Path>) -> Self {
+        let info = git_dir
+            .map(|dir| load_attr_rules(top, &dir.join("info").join("attributes")))
+            .unwrap_or_default();
+        Self {
+            top: top.to_path_buf(),
+            info,
+            dirs: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The value of `attr` for the file `path`, or None if it is unspecified
+    pub fn get(&self, path: &Path, attr: &str) -> Option<AttrValue> {
+        self.lookup(path, false, attr).map(|found| found.value)
+    }
+
+    /// Whether `attr` is set (or set to "true") for the file `path`
+    pub fn is_set(&self, path: &Path, attr: &str) -> bool {
+        self.set_by(path, false, attr).is_some()
+    }
+
+    /// The line setting `attr` (or setting it to "true") for `path`
+    pub fn set_by(&self, path: &Path, is_dir: bool, attr: &str) -> Option<AttrMatch> {
+        self.lookup(path, is_dir, attr)
+            .filter(|found| match &found.value {
+                AttrValue::Set => true,
+                AttrValue::Value(value) => value == "true",
+                AttrValue::Unset => false,
+            })
+    }
+
+    /// The line deciding `attr` for `path`, or None if it is unspecified
+    pub fn lookup(&self, path: &Path, is_dir: bool, attr: &str) -> Option<AttrMatch> {
+        let dirs: Vec<_> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.top))
+            .map(|dir| self.dir_rules(dir))
+            .collect();
+        let rule_sets = std::iter::once(&self.info).chain(dirs.iter().map(|rules| rules.as_ref()));
+        for rules in rule_sets {
+            for rule in rules.iter().rev() {
+                let Some((_, value)) = rule.attrs.iter().rev().find(|(name, _)| name == attr)
+                else {
+                    continue;
+                };
+                if rule.matcher.matched(path, is_dir).is_ignore() {
+                    return value.clone().map(|value| AttrMatch {
+                        value,
+                        line: rule.line.clone(),
+                        source: rule.source.clone(),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    fn dir_rules(&self, dir: &Path) -> Arc<Vec<AttrRule>> {
+        let mut cache = self.dirs.lock().unwrap();
+        cache
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| Arc::new(load_attr_rules(dir, &dir.join(".gitattributes"))))
+            .clone()
+    }
+}
+
+/// Parse an attributes file whose patterns are relative to `root`
+fn load_attr_rules(root: &Path, file: &Path) -> Vec<AttrRule> {
+    let Ok(content) = fs::read_to_string(file) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let mut fields = line.split_whitespace();
+            let pattern = fields.next()?;
+            // Negative patterns are not allowed in .gitattributes
+            if pattern.starts_with('!') {
+                return None;
+            }
+            let mut builder = ignore::gitignore::GitignoreBuilder::new(root);
+            builder.add_line(None, pattern).ok()?;
+            let matcher = builder.build().ok()?;
+            let attrs = fields.flat_map(parse_attr).collect();
+            Some(AttrRule {
+                matcher,
+                attrs,
+                line: line.to_string(),
+                source: file.to_path_buf(),
+            })
+        })
+        .collect()
+}
+
+/// Parse one attribute of a `.gitattributes` line, expanding the `binary` macro
+fn parse_attr(field: &str) -> Vec<(String, Option<AttrValue>)> {
+    if field == "binary" {
+        return ["diff", "merge", "text"]
+            .into_iter()
+            .map(|name| (name.to_string(), Some(AttrValue::Unset)))
+            .chain([("binary".to_string(), Some(AttrValue::Set))])
+            .collect();
+    }
+    let attr = if let Some(name) = field.strip_prefix('-') {
+        (name.to_string(), Some(AttrValue::Unset))
+    } else if let Some(name) = field.strip_prefix('!') {
+        (name.to_string(), None)
+    } else if let Some((name, value)) = field.split_once('=') {
+        (name.to_string(), Some(AttrValue::Value(value.to_string())))
+    } else {
+        (field.to_string(), Some(AttrValue::Set))
+    };
+    vec![attr]
+}
+
 /// Convenience functions for common operations
 pub mod convenience {
     use super::*;
@@ -0,0 +1,61 @@
+use anyhow::{anyhow, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// The paths a server (`yek mcp`, `yek serve`) is allowed to read from.
+///
+/// Requests may narrow the input paths, but never point outside of these roots.
+#[derive(Debug, Clone)]
+pub struct ServedRoots {
+    roots: Vec<PathBuf>,
+}
+
+impl ServedRoots {
+    /// Roots are canonicalized up front; paths that do not exist are dropped
+    pub fn new(input_paths: &[String]) -> Self {
+        let roots = input_paths
+            .iter()
+            .filter_map(|p| Path::new(p).canonicalize().ok())
+            .collect();
+        Self { roots }
+    }
+
+    /// Canonicalize a path and make sure it is inside one of the roots
+    pub fn resolve(&self, path: &str) -> Result<PathBuf> {
+        let resolved = Path::new(path)
+            .canonicalize()
+            .map_err(|e| anyhow!("'{}': {}", path, e))?;
+        if self.roots.iter().any(|root| resolved.starts_with(root)) {
+            Ok(resolved)
+        } else {
+            Err(anyhow!("'{}' is outside of the served paths", path))
+        }
+    }
+
+    /// Ensure a path or glob pattern, and every file it expands to, stays inside the roots
+    pub fn check_pattern(&self, pattern: &str) -> Result<()> {
+        // A `..` after a wildcard would climb out from whatever the wildcard matched
+        if Path::new(pattern)
+            .components()
+            .any(|c| c == Component::ParentDir)
+        {
+            return Err(anyhow!(
+                "'{}': '..' is not allowed in served paths",
+                pattern
+            ));
+        }
+        let Some(idx) = pattern.find(['*', '?', '[']) else {
+            return self.resolve(pattern).map(|_| ());
+        };
+        let fixed = match pattern[..idx].rfind('/') {
+            Some(slash) => &pattern[..slash.max(1)],
+            None => ".",
+        };
+        self.resolve(fixed)?;
+        // Matches are canonicalized, so a symlink out of the roots is caught too
+        let matches = glob::glob(pattern).map_err(|e| anyhow!("'{}': {}", pattern, e))?;
+        for path in matches.flatten() {
+            self.resolve(&path.to_string_lossy())?;
+        }
+        Ok(())
+    }
+}
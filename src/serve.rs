@@ -0,0 +1,369 @@
+use crate::{
+    config::{SubcommandConfigArgs, YekConfig},
+    roots::ServedRoots,
+    serialize_repo_with_report,
+    tree::generate_tree,
+    SerializationReport,
+};
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+/// Maximum number of bundles kept in memory before the cache is cleared
+const MAX_CACHED_BUNDLES: usize = 32;
+
+/// Number of connections handled at once; further ones wait to be accepted
+const MAX_CONNECTIONS: usize = 16;
+
+/// Maximum size of a request line plus its headers
+const MAX_REQUEST_HEAD_BYTES: u64 = 8 * 1024;
+
+/// How long a client may take to send its request, or to read a response chunk
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Arguments for `yek serve`
+#[derive(clap::Args, Debug, Clone)]
+pub struct ServeArgs {
+    /// Port to listen on (0 picks a free port)
+    #[arg(long, default_value_t = 7878)]
+    pub port: u16,
+
+    /// Address to bind to
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: String,
+
+    #[command(flatten)]
+    pub config: SubcommandConfigArgs,
+}
+
+/// A response produced by [`BundleServer::handle`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl HttpResponse {
+    fn new(status: u16, content_type: &'static str, body: String) -> Self {
+        Self {
+            status,
+            content_type,
+            headers: Vec::new(),
+            body,
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::new(
+            status,
+            "text/plain; charset=utf-8",
+            format!("{}\n", message),
+        )
+    }
+
+    fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Get a header value by (case-insensitive) name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn write_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            reason_phrase(self.status),
+            self.content_type,
+            self.body.len()
+        )?;
+        for (name, value) in &self.headers {
+            write!(writer, "{}: {}\r\n", name, value)?;
+        }
+        write!(writer, "\r\n{}", self.body)?;
+        writer.flush()
+    }
+}
+
+/// HTTP server exposing bundles of the configured input paths.
+///
+/// Bundles are cached in memory, keyed by the content checksum of the requested
+/// paths plus the settings used to render them, so unchanged repositories are
+/// served without serializing them again.
+pub struct BundleServer {
+    config: YekConfig,
+    roots: ServedRoots,
+    cache: Mutex<HashMap<String, Arc<SerializationReport>>>,
+}
+
+impl BundleServer {
+    pub fn new(config: YekConfig) -> Self {
+        let roots = ServedRoots::new(&config.input_paths);
+        Self {
+            config,
+            roots,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Accept connections until the listener fails, handling them on a pool of
+    /// [`MAX_CONNECTIONS`] threads
+    pub fn serve(&self, listener: TcpListener) -> Result<()> {
+        let (sender, receiver) = mpsc::sync_channel::<TcpStream>(MAX_CONNECTIONS);
+        let receiver = Mutex::new(receiver);
+        std::thread::scope(|scope| {
+            for _ in 0..MAX_CONNECTIONS {
+                scope.spawn(|| loop {
+                    // Release the lock before handling, so other workers can take the next one
+                    let stream = match receiver.lock().unwrap().recv() {
+                        Ok(stream) => stream,
+                        Err(_) => break,
+                    };
+                    if let Err(e) = self.handle_connection(stream) {
+                        eprintln!("Warning: Failed to handle request: {}", e);
+                    }
+                });
+            }
+            let accepted = listener
+                .incoming()
+                .try_for_each(|stream| -> Result<()> { Ok(sender.send(stream?)?) });
+            // Let the workers finish the queued connections and stop
+            drop(sender);
+            accepted
+        })
+    }
+
+    fn handle_connection(&self, stream: TcpStream) -> Result<()> {
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        let mut reader = BufReader::new((&stream).take(MAX_REQUEST_HEAD_BYTES));
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+
+        // Skip headers; requests have no body we care about
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && header.trim_end() != "" {
+            header.clear();
+        }
+        // Without the blank line ending the headers, the limit may have cut them off
+        let truncated = !header.ends_with('\n') && reader.get_ref().limit() == 0;
+
+        let mut parts = request_line.split_whitespace();
+        let response = match (parts.next(), parts.next()) {
+            _ if truncated => HttpResponse::error(431, "Request header fields too large"),
+            (Some(method), Some(target)) => self.handle(method, target),
+            _ => HttpResponse::error(400, "Bad request"),
+        };
+        response.write_to(&stream)?;
+        Ok(())
+    }
+
+    /// Route a request to the matching endpoint
+    pub fn handle(&self, method: &str, target: &str) -> HttpResponse {
+        if method != "GET" {
+            return HttpResponse::error(405, "Only GET is supported");
+        }
+
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let params = parse_query(query);
+        let result = match path {
+            "/bundle" => self.bundle(&params),
+            "/tree" => self.tree(&params),
+            "/stats" => self.stats(&params),
+            _ => return HttpResponse::error(404, "Not found"),
+        };
+        result.unwrap_or_else(|e| HttpResponse::error(400, &e.to_string()))
+    }
+
+    fn bundle(&self, params: &QueryParams) -> Result<HttpResponse> {
+        let config = self.config_for(params)?;
+        let (report, cache_status) = self.report_for(&config)?;
+        let content_type = if config.json {
+            "application/json"
+        } else {
+            "text/plain; charset=utf-8"
+        };
+        Ok(HttpResponse::new(200, content_type, report.output.clone())
+            .with_header("X-Yek-Checksum", &report.checksum)
+            .with_header("X-Yek-Cache", cache_status))
+    }
+
+    fn tree(&self, params: &QueryParams) -> Result<HttpResponse> {
+        let config = self.config_for(params)?;
+        let (report, cache_status) = self.report_for(&config)?;
+        let paths: Vec<PathBuf> = report
+            .files
+            .iter()
+            .map(|f| PathBuf::from(&f.rel_path))
+            .collect();
+        Ok(
+            HttpResponse::new(200, "text/plain; charset=utf-8", generate_tree(&paths))
+                .with_header("X-Yek-Cache", cache_status),
+        )
+    }
+
+    fn stats(&self, params: &QueryParams) -> Result<HttpResponse> {
+        let config = self.config_for(params)?;
+        let (report, cache_status) = self.report_for(&config)?;
+        let body = json!({
+            "checksum": report.checksum,
+            "files": report.files.len(),
+            "included": report.entries.len(),
+            "excluded": report.excluded.len(),
+            "bytes": report.output.len(),
+            "lines": report.output.lines().count(),
+            "tokens": crate::count_tokens(&report.output),
+        });
+        Ok(HttpResponse::new(200, "application/json", body.to_string())
+            .with_header("X-Yek-Cache", cache_status))
+    }
+
+    /// Server config adjusted by the `paths`, `tokens` and `format` query parameters
+    fn config_for(&self, params: &QueryParams) -> Result<YekConfig> {
+        let mut config = self.config.clone();
+
+        let paths: Vec<String> = params
+            .get("paths")
+            .into_iter()
+            .flatten()
+            .flat_map(|v| v.split(','))
+            .filter(|p| !p.is_empty())
+            .map(|p| p.to_string())
+            .collect();
+        if !paths.is_empty() {
+            for path in &paths {
+                self.roots.check_pattern(path)?;
+            }
+            config.input_paths = paths;
+        }
+
+        if let Some(tokens) = params.get("tokens").and_then(|v| v.last()) {
+            config.tokens = tokens.clone();
+            config.token_mode = !tokens.is_empty();
+        }
+
+        match params
+            .get("format")
+            .and_then(|v| v.last())
+            .map(|s| s.as_str())
+        {
+            None => {}
+            Some("json") => config.json = true,
+            Some("text") => config.json = false,
+            Some(other) => {
+                return Err(anyhow!(
+                    "format: expected 'text' or 'json', got '{}'",
+                    other
+                ))
+            }
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Serialize with `config`, reusing a cached report when the inputs have not changed
+    fn report_for(&self, config: &YekConfig) -> Result<(Arc<SerializationReport>, &'static str)> {
+        // The input checksum cannot see which files a glob pattern will match
+        if config
+            .input_paths
+            .iter()
+            .any(|p| p.contains(['*', '?', '[']))
+        {
+            return Ok((Arc::new(serialize_repo_with_report(config)?), "bypass"));
+        }
+
+        let key = format!(
+            "{}:{}",
+            YekConfig::get_content_checksum(&config.input_paths),
+            config.checksum_settings()?
+        );
+        if let Some(report) = self.cache.lock().unwrap().get(&key) {
+            return Ok((Arc::clone(report), "hit"));
+        }
+
+        let report = Arc::new(serialize_repo_with_report(config)?);
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHED_BUNDLES {
+            cache.clear();
+        }
+        cache.insert(key, Arc::clone(&report));
+        Ok((report, "miss"))
+    }
+}
+
+/// Load the config, bind the listener and serve until interrupted
+pub fn run(args: &ServeArgs) -> Result<()> {
+    let config = args.config.load()?;
+    let listener = TcpListener::bind((args.host.as_str(), args.port))
+        .map_err(|e| anyhow!("port: cannot listen on {}:{}: {}", args.host, args.port, e))?;
+    eprintln!("Serving bundles on http://{}", listener.local_addr()?);
+    BundleServer::new(config).serve(listener)
+}
+
+type QueryParams = HashMap<String, Vec<String>>;
+
+/// Parse `a=1&b=2&a=3` into a map of (percent-decoded) values
+fn parse_query(query: &str) -> QueryParams {
+    let mut params = QueryParams::new();
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        params
+            .entry(percent_decode(key))
+            .or_default()
+            .push(percent_decode(value));
+    }
+    params
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| {
+            std::str::from_utf8(h)
+                .ok()
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+        });
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
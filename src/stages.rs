@@ -0,0 +1,307 @@
+//! Processing stages declared in the config file.
+//!
+//! ```yaml
+//! stages:
+//!   - type: exec
+//!     command: "prettier --stdin-filepath {path}"
+//!     match: "*.ts"
+//!     timeout_secs: 10
+//!   - type: regex_replace
+//!     pattern: "(?m)^\\s*// TODO.*\n"
+//!     replacement: ""
+//! ```
+//!
+//! Stages run in order after discovery, each one on the files matching its
+//! `match` glob (all files if omitted). A file a stage fails on keeps its
+//! content and the failure is reported as a warning.
+
+use crate::{
+    defaults::DEFAULT_STAGE_TIMEOUT_SECS,
+    models::ProcessedFile,
+    pipeline::{ProcessingContext, ProcessingStage},
+};
+use anyhow::{anyhow, Result};
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{Read, Write},
+    process::{Child, Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A stage as declared in the `stages` config option
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StageConfig {
+    /// Pipe the content through a shell command. `{path}` in the command is
+    /// replaced with the (quoted) relative path of the file.
+    Exec {
+        command: String,
+        #[serde(rename = "match", default, skip_serializing_if = "Option::is_none")]
+        match_pattern: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout_secs: Option<u64>,
+    },
+    /// Replace every match of a regex. The replacement can use `$1` or `${name}`.
+    RegexReplace {
+        pattern: String,
+        replacement: String,
+        #[serde(rename = "match", default, skip_serializing_if = "Option::is_none")]
+        match_pattern: Option<String>,
+    },
+}
+
+impl StageConfig {
+    /// Create the stage, validating its options
+    pub fn build(&self) -> Result<Box<dyn ProcessingStage>> {
+        Ok(match self {
+            StageConfig::Exec {
+                command,
+                match_pattern,
+                timeout_secs,
+            } => {
+                if command.trim().is_empty() {
+                    return Err(anyhow!("exec stage needs a command"));
+                }
+                let timeout_secs = timeout_secs.unwrap_or(DEFAULT_STAGE_TIMEOUT_SECS);
+                if timeout_secs == 0 {
+                    return Err(anyhow!("timeout_secs: cannot be 0"));
+                }
+                Box::new(ExecStage {
+                    command: command.clone(),
+                    file_match: FileMatch::new(match_pattern.as_deref())?,
+                    timeout: Duration::from_secs(timeout_secs),
+                })
+            }
+            StageConfig::RegexReplace {
+                pattern,
+                replacement,
+                match_pattern,
+            } => Box::new(RegexReplaceStage {
+                regex: Regex::new(pattern)
+                    .map_err(|e| anyhow!("invalid regex '{}': {}", pattern, e))?,
+                replacement: replacement.clone(),
+                file_match: FileMatch::new(match_pattern.as_deref())?,
+            }),
+        })
+    }
+}
+
+/// Create the stages declared in the config, in order
+pub fn build_stages(configs: &[StageConfig]) -> Result<Vec<Box<dyn ProcessingStage>>> {
+    configs
+        .iter()
+        .enumerate()
+        .map(|(i, config)| config.build().map_err(|e| anyhow!("stages[{}]: {}", i, e)))
+        .collect()
+}
+
+/// Which files a stage applies to
+struct FileMatch(Option<glob::Pattern>);
+
+impl FileMatch {
+    fn new(pattern: Option<&str>) -> Result<Self> {
+        pattern
+            .map(|p| glob::Pattern::new(p).map_err(|e| anyhow!("invalid match '{}': {}", p, e)))
+            .transpose()
+            .map(Self)
+    }
+
+    fn matches(&self, file: &ProcessedFile) -> bool {
+        self.0
+            .as_ref()
+            .is_none_or(|pattern| pattern.matches(&file.rel_path))
+    }
+}
+
+/// Apply `transform` to every matching file in parallel and record the outcome
+/// in the stats of the current stage
+fn transform_files<F>(
+    mut files: Vec<ProcessedFile>,
+    file_match: &FileMatch,
+    context: &ProcessingContext,
+    transform: F,
+) -> Vec<ProcessedFile>
+where
+    F: Fn(&ProcessedFile) -> Result<String> + Sync,
+{
+    let outcomes: Vec<Result<bool, String>> = files
+        .par_iter_mut()
+        .filter(|file| file_match.matches(file))
+        .map(|file| match transform(file) {
+            Ok(content) if content == file.content => Ok(false),
+            Ok(content) => {
+                file.set_content(content);
+                Ok(true)
+            }
+            Err(e) => Err(format!("{}: {}", file.rel_path, e)),
+        })
+        .collect();
+
+    context.update_current_stage(|stats| {
+        for outcome in outcomes {
+            match outcome {
+                Ok(changed) => stats.files_changed += usize::from(changed),
+                Err(e) => stats.errors.push(e),
+            }
+        }
+    });
+    files
+}
+
+/// Pipes file content through an external command
+pub struct ExecStage {
+    command: String,
+    file_match: FileMatch,
+    timeout: Duration,
+}
+
+impl ProcessingStage for ExecStage {
+    fn process(
+        &self,
+        files: Vec<ProcessedFile>,
+        context: &ProcessingContext,
+    ) -> Result<Vec<ProcessedFile>> {
+        Ok(transform_files(files, &self.file_match, context, |file| {
+            let command = self.command.replace("{path}", &shell_quote(&file.rel_path));
+            run_command(&command, &file.content, self.timeout)
+        }))
+    }
+
+    fn name(&self) -> &'static str {
+        "exec"
+    }
+}
+
+/// Rewrites file content with a regex
+pub struct RegexReplaceStage {
+    regex: Regex,
+    replacement: String,
+    file_match: FileMatch,
+}
+
+impl ProcessingStage for RegexReplaceStage {
+    fn process(
+        &self,
+        files: Vec<ProcessedFile>,
+        context: &ProcessingContext,
+    ) -> Result<Vec<ProcessedFile>> {
+        Ok(transform_files(files, &self.file_match, context, |file| {
+            Ok(self
+                .regex
+                .replace_all(&file.content, self.replacement.as_str())
+                .into_owned())
+        }))
+    }
+
+    fn name(&self) -> &'static str {
+        "regex_replace"
+    }
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+
+    let mut cmd = Command::new("sh");
+    // A group of its own, so the processes it starts can be killed with it
+    cmd.arg("-c").arg(command).process_group(0);
+    cmd
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command);
+    cmd
+}
+
+#[cfg(unix)]
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(windows)]
+fn shell_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+/// Kill the command along with the processes it started
+fn kill_command(child: &mut Child) {
+    #[cfg(unix)]
+    if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: kill(2) only sends a signal; the group is the one created for this child
+        unsafe {
+            libc::kill(-pid, libc::SIGKILL);
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Run `command` with `input` on stdin and return its stdout.
+/// The command is killed if it runs longer than `timeout`, including the
+/// time until the processes it started in the background close its output.
+fn run_command(command: &str, input: &str, timeout: Duration) -> Result<String> {
+    let mut child = shell_command(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("cannot run '{}': {}", command, e))?;
+
+    // Feed stdin and drain the outputs on their own threads so a full pipe cannot block
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_owned();
+    thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let (stdout_tx, stdout_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stdout_tx.send(stdout.read_to_end(&mut buf).map(|_| buf));
+    });
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let (stderr_tx, stderr_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stderr.read_to_string(&mut buf);
+        let _ = stderr_tx.send(buf);
+    });
+
+    let deadline = Instant::now() + timeout;
+    let timed_out = |child: &mut Child| {
+        kill_command(child);
+        anyhow!("timed out after {}s", timeout.as_secs())
+    };
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            return Err(timed_out(&mut child));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    // A background process can keep the output open after the command exits
+    let stdout = match stdout_rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(read) => read?,
+        Err(_) => return Err(timed_out(&mut child)),
+    };
+    if !status.success() {
+        let stderr = stderr_rx
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            .unwrap_or_default();
+        return Err(match stderr.lines().next().map(str::trim) {
+            Some(reason) if !reason.is_empty() => {
+                anyhow!("'{}' failed ({}): {}", command, status, reason)
+            }
+            _ => anyhow!("'{}' failed ({})", command, status),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&stdout).into_owned())
+}
@@ -0,0 +1,376 @@
+//! Condensed versions of lockfiles and data files.
+//!
+//! Files matching `summarize_patterns` are included as a summary instead of
+//! verbatim: lockfiles as a sorted `name@version` list, CSV and TSV files as
+//! their columns with inferred types plus the first rows, and JSON files as
+//! their schema plus the value with every array cut to its first items.
+//! Files no summarizer understands are kept as they are.
+
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// Rows looked at to infer the type of CSV columns
+const CSV_TYPE_SAMPLE_ROWS: usize = 1000;
+
+/// Array items looked at to infer a JSON schema
+const JSON_SCHEMA_SAMPLE_ITEMS: usize = 100;
+
+/// A summary of `content` for the file at `rel_path`, keeping `rows` rows or
+/// array items of data files. None if no summarizer applies or the file
+/// cannot be parsed.
+pub fn summarize(rel_path: &str, content: &str, rows: usize) -> Option<String> {
+    let file_name = Path::new(rel_path)
+        .file_name()?
+        .to_string_lossy()
+        .to_lowercase();
+    if let Some(packages) = lockfile_packages(&file_name, content) {
+        let packages: BTreeSet<String> = packages.into_iter().collect();
+        let mut summary = format!("# {}: {} packages (summarized)\n", rel_path, packages.len());
+        for package in packages {
+            summary.push_str(&package);
+            summary.push('\n');
+        }
+        return Some(summary);
+    }
+    match Path::new(&file_name).extension()?.to_str()? {
+        "csv" => summarize_csv(rel_path, content, ',', rows),
+        "tsv" => summarize_csv(rel_path, content, '\t', rows),
+        "json" => summarize_json(rel_path, content, rows),
+        _ => None,
+    }
+}
+
+/// `name@version` of every package in a lockfile, or None if `file_name` is
+/// not a known lockfile
+fn lockfile_packages(file_name: &str, content: &str) -> Option<Vec<String>> {
+    match file_name {
+        "cargo.lock" | "poetry.lock" => toml_packages(content),
+        "package-lock.json" | "npm-shrinkwrap.json" => npm_packages(content),
+        "composer.lock" => composer_packages(content),
+        "pipfile.lock" => pipfile_packages(content),
+        "yarn.lock" => Some(yarn_packages(content)),
+        "pnpm-lock.yaml" => pnpm_packages(content),
+        "gemfile.lock" => Some(gemfile_packages(content)),
+        "go.sum" => Some(go_sum_packages(content)),
+        _ => None,
+    }
+}
+
+/// `[[package]]` tables with `name` and `version`, as in `Cargo.lock` and `poetry.lock`
+fn toml_packages(content: &str) -> Option<Vec<String>> {
+    let lock: toml::Table = toml::from_str(content).ok()?;
+    let packages = lock.get("package")?.as_array()?;
+    Some(
+        packages
+            .iter()
+            .filter_map(|package| {
+                let name = package.get("name")?.as_str()?;
+                let version = package.get("version")?.as_str()?;
+                Some(format!("{name}@{version}"))
+            })
+            .collect(),
+    )
+}
+
+fn npm_packages(content: &str) -> Option<Vec<String>> {
+    let lock: Value = serde_json::from_str(content).ok()?;
+    // Lockfile v2 and v3 list every installed path under "packages"
+    if let Some(packages) = lock.get("packages").and_then(Value::as_object) {
+        return Some(
+            packages
+                .iter()
+                .filter_map(|(path, package)| {
+                    let (_, name) = path.rsplit_once("node_modules/")?;
+                    let version = package.get("version")?.as_str()?;
+                    Some(format!("{name}@{version}"))
+                })
+                .collect(),
+        );
+    }
+    // Lockfile v1 nests "dependencies"
+    fn collect(dependencies: &Value, packages: &mut Vec<String>) {
+        for (name, dependency) in dependencies.as_object().into_iter().flatten() {
+            if let Some(version) = dependency.get("version").and_then(Value::as_str) {
+                packages.push(format!("{name}@{version}"));
+            }
+            if let Some(nested) = dependency.get("dependencies") {
+                collect(nested, packages);
+            }
+        }
+    }
+    let mut packages = Vec::new();
+    collect(lock.get("dependencies")?, &mut packages);
+    Some(packages)
+}
+
+fn composer_packages(content: &str) -> Option<Vec<String>> {
+    let lock: Value = serde_json::from_str(content).ok()?;
+    Some(
+        ["packages", "packages-dev"]
+            .iter()
+            .filter_map(|section| lock.get(section)?.as_array())
+            .flatten()
+            .filter_map(|package| {
+                let name = package.get("name")?.as_str()?;
+                let version = package.get("version")?.as_str()?;
+                Some(format!("{name}@{version}"))
+            })
+            .collect(),
+    )
+}
+
+fn pipfile_packages(content: &str) -> Option<Vec<String>> {
+    let lock: Value = serde_json::from_str(content).ok()?;
+    Some(
+        ["default", "develop"]
+            .iter()
+            .filter_map(|section| lock.get(section)?.as_object())
+            .flatten()
+            .filter_map(|(name, package)| {
+                let version = package.get("version")?.as_str()?;
+                Some(format!("{name}@{}", version.trim_start_matches("==")))
+            })
+            .collect(),
+    )
+}
+
+/// Entries of a classic or Berry `yarn.lock`: an unindented line of specs
+/// such as `"@babel/core@^7.0.0", "@babel/core@^7.1.0":` followed by an
+/// indented `version "7.1.2"` (or `version: 7.1.2`) line
+fn yarn_packages(content: &str) -> Vec<String> {
+    let mut packages = Vec::new();
+    let mut name: Option<&str> = None;
+    for line in content.lines() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        if !line.starts_with(' ') {
+            let spec = line
+                .trim_end_matches(':')
+                .split(',')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .trim_matches('"');
+            // The version range follows the last '@' that is not the scope's
+            name = spec
+                .char_indices()
+                .skip(1)
+                .filter(|&(_, c)| c == '@')
+                .last()
+                .map(|(i, _)| &spec[..i]);
+            continue;
+        }
+        let field = line.trim();
+        let version = field
+            .strip_prefix("version ")
+            .or_else(|| field.strip_prefix("version: "));
+        if let Some(version) = version {
+            if let Some(current) = name.take() {
+                packages.push(format!("{current}@{}", version.trim().trim_matches('"')));
+            }
+        }
+    }
+    packages
+}
+
+/// Keys of `packages` in `pnpm-lock.yaml`: `/name/1.0.0` (v5),
+/// `/name@1.0.0` (v6) or `name@1.0.0(peer@2.0.0)` (v9)
+fn pnpm_packages(content: &str) -> Option<Vec<String>> {
+    let lock: serde_yaml::Value = serde_yaml::from_str(content).ok()?;
+    let packages = lock.get("packages")?.as_mapping()?;
+    Some(
+        packages
+            .keys()
+            .filter_map(|key| {
+                let key = key.as_str()?.trim_start_matches('/');
+                let key = key.split('(').next()?;
+                let (name, version) = match key.get(1..)?.rfind('@') {
+                    Some(i) => (&key[..=i], &key[i + 2..]),
+                    None => key.rsplit_once('/')?,
+                };
+                Some(format!("{name}@{version}"))
+            })
+            .collect(),
+    )
+}
+
+/// Gems under `specs:`, indented by four spaces as `name (version)`
+fn gemfile_packages(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter(|line| line.starts_with("    ") && !line.starts_with("     "))
+        .filter_map(|line| {
+            let (name, version) = line.trim().split_once(" (")?;
+            Some(format!("{name}@{}", version.trim_end_matches(')')))
+        })
+        .collect()
+}
+
+/// Modules of `go.sum`, whose lines are `module version[/go.mod] hash`
+fn go_sum_packages(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let module = fields.next()?;
+            let version = fields.next()?.trim_end_matches("/go.mod");
+            Some(format!("{module}@{version}"))
+        })
+        .collect()
+}
+
+/// Fields of one CSV line, honoring double quotes
+fn csv_fields(line: &str, separator: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// The narrowest type holding both `a` and `b`
+fn merge_types(a: &'static str, b: &'static str) -> &'static str {
+    match (a, b) {
+        ("empty", other) | (other, "empty") => other,
+        (a, b) if a == b => a,
+        ("integer", "number") | ("number", "integer") => "number",
+        _ => "text",
+    }
+}
+
+fn value_type(value: &str) -> &'static str {
+    let value = value.trim();
+    if value.is_empty() {
+        "empty"
+    } else if value.parse::<i64>().is_ok() {
+        "integer"
+    } else if value.parse::<f64>().is_ok() {
+        "number"
+    } else if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+        "boolean"
+    } else {
+        "text"
+    }
+}
+
+fn summarize_csv(rel_path: &str, content: &str, separator: char, rows: usize) -> Option<String> {
+    let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+    let header = lines.next()?;
+    let columns = csv_fields(header, separator);
+    let records: Vec<&str> = lines.collect();
+
+    let mut types = vec!["empty"; columns.len()];
+    for record in records.iter().take(CSV_TYPE_SAMPLE_ROWS) {
+        for (i, value) in csv_fields(record, separator).iter().enumerate() {
+            if let Some(column_type) = types.get_mut(i) {
+                *column_type = merge_types(column_type, value_type(value));
+            }
+        }
+    }
+    let schema: Vec<String> = columns
+        .iter()
+        .zip(&types)
+        .map(|(column, column_type)| format!("{} ({})", column.trim(), column_type))
+        .collect();
+
+    let mut summary = format!(
+        "# {}: {} columns, {} rows (summarized)\n# columns: {}\n{}\n",
+        rel_path,
+        columns.len(),
+        records.len(),
+        schema.join(", "),
+        header
+    );
+    for record in records.iter().take(rows) {
+        summary.push_str(record);
+        summary.push('\n');
+    }
+    if records.len() > rows {
+        summary.push_str(&format!("# ... {} more rows\n", records.len() - rows));
+    }
+    Some(summary)
+}
+
+/// Type of a JSON value, with objects as `{"key": type}` and arrays as `[type]`
+fn json_schema(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(_) => "boolean".to_string(),
+        Value::Number(n) if n.is_f64() => "number".to_string(),
+        Value::Number(_) => "integer".to_string(),
+        Value::String(_) => "string".to_string(),
+        Value::Array(items) => {
+            let kinds: BTreeSet<String> = items
+                .iter()
+                .take(JSON_SCHEMA_SAMPLE_ITEMS)
+                .map(json_schema)
+                .collect();
+            format!("[{}]", kinds.into_iter().collect::<Vec<_>>().join(" | "))
+        }
+        Value::Object(fields) => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(key, value)| format!("{:?}: {}", key, json_schema(value)))
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+    }
+}
+
+/// `value` with every array cut to `rows` items plus a note of what was left out
+fn truncate_arrays(value: &Value, rows: usize) -> Value {
+    match value {
+        Value::Array(items) => {
+            let mut kept: Vec<Value> = items
+                .iter()
+                .take(rows)
+                .map(|item| truncate_arrays(item, rows))
+                .collect();
+            if items.len() > rows {
+                kept.push(Value::String(format!(
+                    "... {} more items",
+                    items.len() - rows
+                )));
+            }
+            Value::Array(kept)
+        }
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), truncate_arrays(value, rows)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn summarize_json(rel_path: &str, content: &str, rows: usize) -> Option<String> {
+    let value: Value = serde_json::from_str(content).ok()?;
+    let shape = match &value {
+        Value::Array(items) => format!("array of {} items", items.len()),
+        Value::Object(fields) => format!("object with {} keys", fields.len()),
+        _ => "scalar".to_string(),
+    };
+    let sample = serde_json::to_string_pretty(&truncate_arrays(&value, rows)).ok()?;
+    Some(format!(
+        "# {}: {} (summarized)\n# schema: {}\n{}\n",
+        rel_path,
+        shape,
+        json_schema(&value),
+        sample
+    ))
+}
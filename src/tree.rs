@@ -1,8 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Component, Path, PathBuf};
 
 /// Generate a directory tree from a list of file paths
 pub fn generate_tree(paths: &[PathBuf]) -> String {
+    generate_tree_with_symlinks(paths, &BTreeMap::new())
+}
+
+/// Same as [`generate_tree`], marking the files and directories in `symlinks`
+/// (relative path to link target) as `name -> target`
+pub fn generate_tree_with_symlinks(
+    paths: &[PathBuf],
+    symlinks: &BTreeMap<String, String>,
+) -> String {
     if paths.is_empty() {
         return String::new();
     }
@@ -18,6 +27,11 @@ pub fn generate_tree(paths: &[PathBuf]) -> String {
     for path in paths {
         add_path_to_tree(&mut tree, path);
     }
+    for (path, target) in symlinks {
+        if let Some(node) = tree.find_mut(Path::new(path)) {
+            node.link = Some(target.clone());
+        }
+    }
 
     // Generate the tree output
     output.push_str("Directory structure:\n");
@@ -32,6 +46,8 @@ struct TreeNode {
     name: String,
     children: HashMap<String, TreeNode>,
     is_file: bool,
+    /// Target of a symbolic link
+    link: Option<String>,
 }
 
 impl TreeNode {
@@ -40,6 +56,7 @@ impl TreeNode {
             name: String::new(),
             children: HashMap::new(),
             is_file: false,
+            link: None,
         }
     }
 
@@ -48,8 +65,15 @@ impl TreeNode {
             name,
             children: HashMap::new(),
             is_file,
+            link: None,
         }
     }
+
+    fn find_mut(&mut self, path: &Path) -> Option<&mut TreeNode> {
+        clean_path_components(path)
+            .iter()
+            .try_fold(self, |node, name| node.children.get_mut(name))
+    }
 }
 
 /// Filter out Windows drive prefixes and root directory components to get logical path components.
@@ -172,6 +196,10 @@ fn render_child(
     if !child.is_file {
         output.push('/');
     }
+    if let Some(target) = &child.link {
+        output.push_str(" -> ");
+        output.push_str(target);
+    }
     output.push('\n');
 
     // Calculate next prefix for children
@@ -0,0 +1,166 @@
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use yek::{models::ExclusionReason, Budget, OutputFormat, Yek};
+
+#[cfg(test)]
+mod api_tests {
+    use super::*;
+
+    fn setup_repo(dir: &Path) {
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.join("src/lib.rs"), "pub fn lib() {}\n").unwrap();
+        fs::write(dir.join("notes.log"), "noise\n").unwrap();
+    }
+
+    #[test]
+    fn test_builder_serializes_with_structured_result() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+
+        let result = Yek::builder()
+            .path(temp_dir.path())
+            .ignore("*.log")
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+
+        let selected: Vec<&str> = result
+            .selected
+            .iter()
+            .map(|e| e.rel_path.as_str())
+            .collect();
+        assert_eq!(selected.len(), 2);
+        assert!(selected.contains(&"src/main.rs"));
+        assert!(selected.contains(&"src/lib.rs"));
+
+        let reason_for = |path: &str| {
+            result
+                .excluded
+                .iter()
+                .find(|e| e.rel_path == path)
+                .map(|e| e.reason)
+        };
+        assert_eq!(reason_for("notes.log"), Some(ExclusionReason::Ignored));
+
+        assert_eq!(result.stats.files_processed, 2);
+        assert_eq!(result.stats.files_included, 2);
+        assert_eq!(result.stats.files_excluded, 1);
+        assert_eq!(result.stats.total_bytes, result.output.len());
+        assert_eq!(result.stats.total_tokens, None);
+        assert_eq!(result.checksum.len(), 8);
+        assert!(result.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_builder_budgets() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+
+        let result = Yek::builder()
+            .path(temp_dir.path())
+            .max_bytes(20)
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        assert_eq!(result.stats.files_included, 1);
+        assert!(result
+            .excluded
+            .iter()
+            .any(|e| e.reason == ExclusionReason::OverBudget));
+
+        let yek = Yek::builder()
+            .path(temp_dir.path())
+            .budget(Budget::Tokens(1000))
+            .build()
+            .unwrap();
+        assert!(yek.config().token_mode);
+        let result = yek.serialize().unwrap();
+        assert!(result.stats.total_tokens.unwrap() > 0);
+        assert!(result.selected.iter().all(|e| e.tokens.is_some()));
+    }
+
+    #[test]
+    fn test_builder_binary_extension() {
+        let temp_dir = tempdir().unwrap();
+        let model = temp_dir.path().join("model.blend");
+        fs::write(&model, "not really binary").unwrap();
+
+        let result = Yek::builder()
+            .path(&model)
+            .binary_extension(".blend")
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        assert!(result.selected.is_empty());
+        assert_eq!(result.excluded[0].reason, ExclusionReason::BinaryExtension);
+    }
+
+    #[test]
+    fn test_builder_output_options() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+        let main_rs = temp_dir.path().join("src/main.rs");
+
+        let result = Yek::builder()
+            .path(&main_rs)
+            .format(OutputFormat::Json)
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&result.output).unwrap();
+        assert_eq!(parsed[0]["filename"], "main.rs");
+
+        let result = Yek::builder()
+            .path(&main_rs)
+            .output_template("== FILE_PATH ==\nFILE_CONTENT")
+            .line_numbers(true)
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        assert_eq!(result.output, "== main.rs ==\n  1 | fn main() {}");
+    }
+
+    #[test]
+    fn test_builder_priority_rules_order_output() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+
+        let result = Yek::builder()
+            .path(temp_dir.path())
+            .priority_rule("main", 1000)
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        assert_eq!(result.selected.last().unwrap().rel_path, "src/main.rs");
+    }
+
+    #[test]
+    fn test_builder_validation_and_warnings() {
+        let invalid = Yek::builder()
+            .format(OutputFormat::Json)
+            .tree_header(true)
+            .build();
+        assert!(invalid.is_err());
+
+        let invalid = Yek::builder().output_template("no placeholders").build();
+        assert!(invalid.is_err());
+
+        let result = Yek::builder()
+            .path("definitely_missing_path_for_api_test")
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        assert!(result.output.is_empty());
+        assert_eq!(result.warnings.len(), 2);
+        assert!(result.warnings[0].contains("does not exist"));
+    }
+}
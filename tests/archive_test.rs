@@ -0,0 +1,276 @@
+use assert_cmd::Command;
+use flate2::{write::GzEncoder, Compression};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tempfile::tempdir;
+use yek::{
+    category::FileCategory,
+    config::YekConfig,
+    models::ExclusionReason,
+    repository::{ArchiveFileSystem, FileSystem},
+    serialize_repo_with_report, SerializationReport,
+};
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod archive_tests {
+    use super::*;
+
+    fn write_tar(writer: impl Write, files: &[(&str, &str)]) {
+        let mut builder = tar::Builder::new(writer);
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap();
+    }
+
+    fn write_tar_gz(path: &Path, files: &[(&str, &str)]) {
+        let encoder = GzEncoder::new(fs::File::create(path).unwrap(), Compression::default());
+        write_tar(encoder, files);
+    }
+
+    fn write_zip(path: &Path, files: &[(&str, &str)]) {
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        for (name, content) in files {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn serialize(inputs: &[&Path]) -> SerializationReport {
+        let mut config = YekConfig::default();
+        config.input_paths = inputs
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        config.stream = true;
+        serialize_repo_with_report(&config).unwrap()
+    }
+
+    fn included(report: &SerializationReport) -> Vec<&str> {
+        let mut paths: Vec<_> = report.files.iter().map(|f| f.rel_path.as_str()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_archive_file_system() {
+        let temp_dir = tempdir().unwrap();
+        let archive_path = temp_dir.path().join("src.zip");
+        write_zip(
+            &archive_path,
+            &[
+                ("a/b.rs", "fn b() {}\n"),
+                ("a/c/d.rs", "fn d() {}\n"),
+                ("e.txt", "e\n"),
+            ],
+        );
+
+        let archive = ArchiveFileSystem::open(&archive_path, None).unwrap();
+        let root = archive.root().to_path_buf();
+        assert!(archive.is_directory(&root));
+        assert!(archive.is_directory(&root.join("a/c")));
+        assert!(archive.is_file(&root.join("a/c/d.rs")));
+        assert!(!archive.path_exists(&root.join("missing.rs")));
+        assert_eq!(archive.read_file(&root.join("e.txt")).unwrap(), b"e\n");
+        assert_eq!(
+            archive
+                .get_file_metadata(&root.join("a/b.rs"))
+                .unwrap()
+                .size,
+            10
+        );
+        let mut listing = archive.read_directory(&root.join("a")).unwrap();
+        listing.sort();
+        assert_eq!(listing, vec![root.join("a/b.rs"), root.join("a/c")]);
+        assert!(archive.read_file(&root.join("a")).is_err());
+
+        assert!(ArchiveFileSystem::is_archive(Path::new("x.TAR.GZ")));
+        assert!(ArchiveFileSystem::is_archive(Path::new("x.tgz")));
+        assert!(ArchiveFileSystem::is_archive(Path::new("x.tar")));
+        assert!(!ArchiveFileSystem::is_archive(Path::new("x.gz")));
+    }
+
+    #[test]
+    fn test_oversized_entries_are_not_read() {
+        let temp_dir = tempdir().unwrap();
+        let big = "x".repeat(4096);
+        let zip_path = temp_dir.path().join("src.zip");
+        let tar_path = temp_dir.path().join("src.tar.gz");
+        let files = [("big.txt", big.as_str()), ("small.rs", "fn s() {}\n")];
+        write_zip(&zip_path, &files);
+        write_tar_gz(&tar_path, &files);
+
+        for archive_path in [&zip_path, &tar_path] {
+            let archive = ArchiveFileSystem::open(archive_path, Some(1024)).unwrap();
+            let big_path = archive.root().join("big.txt");
+            assert!(archive.is_file(&big_path));
+            assert_eq!(archive.get_file_metadata(&big_path).unwrap().size, 4096);
+            let err = archive.read_file(&big_path).unwrap_err();
+            assert!(err.to_string().contains("over the size limit"), "{}", err);
+            assert_eq!(
+                archive.read_file(&archive.root().join("small.rs")).unwrap(),
+                b"fn s() {}\n"
+            );
+
+            let mut config = YekConfig::default();
+            config.input_paths = vec![archive_path.to_string_lossy().to_string()];
+            config.max_file_size = Some("1K".to_string());
+            config.stream = true;
+            let report = serialize_repo_with_report(&config).unwrap();
+            assert_eq!(included(&report), vec!["small.rs"]);
+            assert_eq!(report.excluded[0].rel_path, "big.txt");
+            assert_eq!(report.excluded[0].reason, ExclusionReason::TooLarge);
+        }
+    }
+
+    #[test]
+    fn test_tar_gz_entries_are_relative_to_archive_root() {
+        let temp_dir = tempdir().unwrap();
+        let archive = temp_dir.path().join("release-1.0.tar.gz");
+        write_tar_gz(
+            &archive,
+            &[
+                ("release-1.0/.gitignore", "*.log\n"),
+                ("release-1.0/src/main.rs", "fn main() {}\n"),
+                ("release-1.0/README.md", "# Release\n"),
+                ("release-1.0/debug.log", "noise\n"),
+                ("release-1.0/.github/ci.yml", "on: push\n"),
+            ],
+        );
+
+        let report = serialize(&[&archive]);
+        assert_eq!(
+            included(&report),
+            vec!["release-1.0/README.md", "release-1.0/src/main.rs"]
+        );
+        let main = report
+            .files
+            .iter()
+            .find(|f| f.rel_path.ends_with("main.rs"))
+            .unwrap();
+        assert_eq!(main.content, "fn main() {}\n");
+        assert_eq!(main.category, FileCategory::Source);
+        assert!(report
+            .output
+            .contains(">>>> release-1.0/src/main.rs\nfn main() {}"));
+
+        let ignored: Vec<_> = report
+            .excluded
+            .iter()
+            .filter(|e| e.reason == ExclusionReason::Ignored)
+            .map(|e| (e.rel_path.as_str(), e.detail.as_deref().unwrap_or("")))
+            .collect();
+        assert_eq!(
+            ignored,
+            vec![("release-1.0/debug.log", "release-1.0/.gitignore: *.log")]
+        );
+    }
+
+    #[test]
+    fn test_zip_applies_ignore_and_include_patterns() {
+        let temp_dir = tempdir().unwrap();
+        let archive = temp_dir.path().join("export.zip");
+        write_zip(
+            &archive,
+            &[
+                ("lib.rs", "pub fn f() {}\n"),
+                ("notes.md", "notes\n"),
+                ("target/out.rs", "// built\n"),
+                ("image.png", "\u{1}PNG\0\0\0\0"),
+                ("../escape.rs", "// outside the archive\n"),
+            ],
+        );
+
+        let mut config = YekConfig::default();
+        config.input_paths = vec![archive.to_string_lossy().to_string()];
+        config.ignore_patterns = vec!["target/".to_string()];
+        config.stream = true;
+        let report = serialize_repo_with_report(&config).unwrap();
+        assert_eq!(included(&report), vec!["lib.rs", "notes.md"]);
+        let excluded: Vec<_> = report
+            .excluded
+            .iter()
+            .map(|e| (e.rel_path.as_str(), e.reason))
+            .collect();
+        assert_eq!(
+            excluded,
+            vec![
+                ("image.png", ExclusionReason::BinaryContent),
+                ("target/", ExclusionReason::Ignored),
+            ]
+        );
+
+        config.include_patterns = vec!["*.rs".to_string()];
+        let report = serialize_repo_with_report(&config).unwrap();
+        assert_eq!(included(&report), vec!["lib.rs"]);
+    }
+
+    #[test]
+    fn test_archive_next_to_directory_input() {
+        let temp_dir = tempdir().unwrap();
+        let archive = temp_dir.path().join("vendor.tar");
+        write_tar(
+            fs::File::create(&archive).unwrap(),
+            &[("dep/lib.rs", "pub fn dep() {}\n")],
+        );
+        let app = temp_dir.path().join("app");
+        fs::create_dir(&app).unwrap();
+        fs::write(app.join("main.rs"), "fn main() {}\n").unwrap();
+
+        let report = serialize(&[&app, &archive]);
+        assert_eq!(
+            included(&report),
+            vec!["app/main.rs", "vendor.tar/dep/lib.rs"]
+        );
+    }
+
+    #[test]
+    fn test_unreadable_archive_is_reported() {
+        let temp_dir = tempdir().unwrap();
+        let archive = temp_dir.path().join("broken.zip");
+        fs::write(&archive, "not a zip archive").unwrap();
+
+        let report = serialize(&[&archive]);
+        assert!(report.files.is_empty());
+        assert_eq!(report.excluded.len(), 1);
+        assert_eq!(report.excluded[0].reason, ExclusionReason::Unreadable);
+        assert!(report.excluded[0]
+            .detail
+            .as_deref()
+            .unwrap()
+            .starts_with("Failed to read archive"));
+    }
+
+    #[test]
+    fn test_cli_archive_input() {
+        let temp_dir = tempdir().unwrap();
+        write_tar_gz(
+            &temp_dir.path().join("src.tgz"),
+            &[("src/lib.rs", "pub fn lib() {}\n")],
+        );
+
+        let output = Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .arg("src.tgz")
+            .env("TERM", "dumb")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            stdout.contains(">>>> src/lib.rs\npub fn lib() {}"),
+            "{}",
+            stdout
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tempfile::tempdir;
+use yek::{config::YekConfig, output_checksum, serialize_repo_with_report};
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod checksum_tests {
+    use super::*;
+
+    fn setup_repo(dir: &Path) {
+        fs::create_dir_all(dir.join("src/deep/nested")).unwrap();
+        fs::write(dir.join("README.md"), "# readme").unwrap();
+        fs::write(dir.join("src/lib.rs"), "pub fn lib() {}").unwrap();
+        fs::write(dir.join("src/deep/nested/mod.rs"), "pub fn nested() {}").unwrap();
+    }
+
+    fn config_for(dir: &Path) -> YekConfig {
+        let mut config = YekConfig::default();
+        config.input_paths = vec![dir.to_string_lossy().to_string()];
+        config
+    }
+
+    fn run_yek(repo: &Path, output_dir: &Path) -> String {
+        let assert = Command::cargo_bin("yek")
+            .unwrap()
+            .env("FORCE_TTY", "1")
+            .arg("--output-dir")
+            .arg(output_dir)
+            .arg(repo)
+            .assert()
+            .success();
+        String::from_utf8(assert.get_output().stdout.clone())
+            .unwrap()
+            .trim()
+            .to_string()
+    }
+
+    #[test]
+    fn test_get_checksum_sees_deep_changes() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+        let input_paths = vec![temp_dir.path().to_string_lossy().to_string()];
+
+        let before = YekConfig::get_checksum(&input_paths);
+        fs::write(
+            temp_dir.path().join("src/deep/nested/mod.rs"),
+            "pub fn changed() {}",
+        )
+        .unwrap();
+        assert_ne!(before, YekConfig::get_checksum(&input_paths));
+    }
+
+    #[test]
+    fn test_get_checksum_sees_modification_time() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+        let input_paths = vec![temp_dir.path().to_string_lossy().to_string()];
+
+        // Files are not read, so a touched file may have changed
+        let before = YekConfig::get_checksum(&input_paths);
+        assert_eq!(before, YekConfig::get_checksum(&input_paths));
+        let file = fs::File::options()
+            .write(true)
+            .open(temp_dir.path().join("README.md"))
+            .unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(3600))
+            .unwrap();
+        assert_ne!(before, YekConfig::get_checksum(&input_paths));
+    }
+
+    #[test]
+    fn test_identical_inputs_produce_identical_output() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+
+        let mut config = config_for(temp_dir.path());
+        config.tree_header = true;
+        let first = serialize_repo_with_report(&config).unwrap();
+        let second = serialize_repo_with_report(&config).unwrap();
+
+        assert_eq!(first.output, second.output);
+        assert_eq!(first.checksum, second.checksum);
+        assert_eq!(first.checksum.len(), 8);
+    }
+
+    #[test]
+    fn test_output_checksum_tracks_content_and_config() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+
+        let config = config_for(temp_dir.path());
+        let original = serialize_repo_with_report(&config).unwrap().checksum;
+
+        // Output location does not change the checksum
+        let mut relocated = config.clone();
+        relocated.output_dir = Some("/somewhere/else".to_string());
+        relocated.debug = true;
+        assert_eq!(
+            original,
+            serialize_repo_with_report(&relocated).unwrap().checksum
+        );
+
+        // Settings that change the output do
+        let mut numbered = config.clone();
+        numbered.line_numbers = true;
+        assert_ne!(
+            original,
+            serialize_repo_with_report(&numbered).unwrap().checksum
+        );
+
+        // And so do contents deep in the tree
+        fs::write(
+            temp_dir.path().join("src/deep/nested/mod.rs"),
+            "pub fn changed() {}",
+        )
+        .unwrap();
+        assert_ne!(
+            original,
+            serialize_repo_with_report(&config).unwrap().checksum
+        );
+    }
+
+    #[test]
+    fn test_output_checksum_covers_rendered_entries() {
+        let temp_dir = tempdir().unwrap();
+        for name in ["d.rs", "c.rs", "b.rs", "a.rs"] {
+            fs::write(temp_dir.path().join(name), format!("// {}\n", name)).unwrap();
+        }
+
+        // Only some files fit in the budget
+        let mut config = config_for(temp_dir.path());
+        config.max_size = "20".to_string();
+        let report = serialize_repo_with_report(&config).unwrap();
+        assert!(!report.entries.is_empty());
+        assert!(report.entries.len() < report.files.len());
+
+        let included: Vec<_> = report
+            .entries
+            .iter()
+            .map(|entry| {
+                report
+                    .files
+                    .iter()
+                    .find(|f| f.rel_path == entry.rel_path)
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(
+            report.checksum,
+            output_checksum(&included, &config).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_output_checksum_ignores_excluded_files() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+
+        let mut config = config_for(temp_dir.path());
+        config.ignore_patterns = vec!["*.log".to_string()];
+        let original = serialize_repo_with_report(&config).unwrap().checksum;
+
+        fs::write(temp_dir.path().join("debug.log"), "noise").unwrap();
+        assert_eq!(
+            original,
+            serialize_repo_with_report(&config).unwrap().checksum
+        );
+    }
+
+    #[test]
+    fn test_cli_output_name_is_reproducible() {
+        let repo = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        setup_repo(repo.path());
+
+        let first = run_yek(repo.path(), output_dir.path());
+        let first_content = fs::read(&first).unwrap();
+
+        // Touching a file keeps the same output file and bytes
+        let file = fs::File::options()
+            .write(true)
+            .open(repo.path().join("src/lib.rs"))
+            .unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(3600))
+            .unwrap();
+        let second = run_yek(repo.path(), output_dir.path());
+        assert_eq!(first, second);
+        assert_eq!(first_content, fs::read(&second).unwrap());
+
+        // Changing a nested file produces a new output file
+        fs::write(
+            repo.path().join("src/deep/nested/mod.rs"),
+            "pub fn changed() {}",
+        )
+        .unwrap();
+        let third = run_yek(repo.path(), output_dir.path());
+        assert_ne!(first, third);
+    }
+}
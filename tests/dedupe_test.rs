@@ -0,0 +1,175 @@
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use yek::{
+    api::{OutputFormat, Yek},
+    dedupe::{find_duplicates, Duplicate},
+    models::Dedupe,
+};
+
+#[cfg(test)]
+mod dedupe_tests {
+    use super::*;
+
+    const CONFIG: &str = "# Shared settings for every service\nport = 8080\nlog_level = \"info\"\ntimeout_seconds = 30\nretries = 3\n";
+
+    fn write(root: &Path, rel_path: &str, content: &str) {
+        let path = root.join(rel_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_find_exact_duplicates() {
+        let files = [
+            ("a/config.toml", CONFIG),
+            ("b/settings.toml", CONFIG),
+            ("c/config.toml", CONFIG),
+        ];
+        let duplicates = find_duplicates(&files, Dedupe::Exact);
+        assert_eq!(
+            duplicates,
+            vec![
+                None,
+                Some(Duplicate::Exact { of: 0 }),
+                Some(Duplicate::Exact { of: 0 })
+            ]
+        );
+        assert_eq!(find_duplicates(&files, Dedupe::Off), vec![None, None, None]);
+    }
+
+    #[test]
+    fn test_small_files_are_not_deduplicated() {
+        let files = [("a.txt", "same\n"), ("b.txt", "same\n")];
+        assert_eq!(find_duplicates(&files, Dedupe::Near), vec![None, None]);
+    }
+
+    #[test]
+    fn test_find_near_duplicates() {
+        let original: String = (0..30).map(|i| format!("line {}\n", i)).collect();
+        let changed = original.replace("line 15\n", "line fifteen\n");
+        let files = [
+            ("a/main.py", original.as_str()),
+            ("b/main.py", changed.as_str()),
+            ("c/other.py", changed.as_str()),
+        ];
+
+        // Near-duplicates need the same file name
+        let duplicates = find_duplicates(&files, Dedupe::Near);
+        assert_eq!(duplicates[0], None);
+        let Some(Duplicate::Near { of: 0, diff }) = &duplicates[1] else {
+            panic!("expected a near-duplicate, got {:?}", duplicates[1]);
+        };
+        assert!(diff.contains("-line 15\n+line fifteen\n"), "{}", diff);
+        assert!(!diff.contains("line 10\n"), "{}", diff);
+        assert_eq!(duplicates[2], None);
+
+        assert_eq!(
+            find_duplicates(&files, Dedupe::Exact),
+            vec![None, None, Some(Duplicate::Exact { of: 1 })]
+        );
+    }
+
+    #[test]
+    fn test_dissimilar_files_are_not_near_duplicates() {
+        let a: String = (0..30).map(|i| format!("alpha {}\n", i)).collect();
+        let b: String = (0..30).map(|i| format!("beta {}\n", i)).collect();
+        let files = [("a/lib.rs", a.as_str()), ("b/lib.rs", b.as_str())];
+        assert_eq!(find_duplicates(&files, Dedupe::Near), vec![None, None]);
+    }
+
+    #[test]
+    fn test_serialize_renders_duplicates_as_note() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write(root, "service-a/config.toml", CONFIG);
+        write(root, "service-b/config.toml", CONFIG);
+
+        let result = Yek::builder()
+            .path(root)
+            .dedupe(Dedupe::Exact)
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        assert_eq!(result.output.matches("Shared settings").count(), 1);
+        assert!(
+            result
+                .output
+                .contains(">>>> service-b/config.toml\n[same content as service-a/config.toml]\n"),
+            "{}",
+            result.output
+        );
+        assert_eq!(result.selected.len(), 2);
+        assert_eq!(result.selected[0].duplicate_of, None);
+        assert_eq!(
+            result.selected[1].duplicate_of.as_deref(),
+            Some("service-a/config.toml")
+        );
+        assert!(result.stats.bytes_saved > 0);
+        // Tokens are only counted with a token budget
+        assert_eq!(result.stats.tokens_saved, None);
+        let tokens = Yek::builder()
+            .path(root)
+            .dedupe(Dedupe::Exact)
+            .max_tokens(100_000)
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        assert!(tokens.stats.tokens_saved.unwrap() > 0);
+
+        let without = Yek::builder()
+            .path(root)
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        assert_eq!(without.output.matches("Shared settings").count(), 2);
+        assert_eq!(without.stats.bytes_saved, 0);
+    }
+
+    #[test]
+    fn test_json_output_names_original() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write(root, "a/config.toml", CONFIG);
+        write(root, "b/config.toml", CONFIG);
+
+        let result = Yek::builder()
+            .path(root)
+            .format(OutputFormat::Json)
+            .dedupe(Dedupe::Exact)
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        let objects: Vec<serde_json::Value> = serde_json::from_str(&result.output).unwrap();
+        assert_eq!(objects.len(), 2);
+        assert!(objects[0].get("duplicate_of").is_none());
+        assert_eq!(objects[1]["duplicate_of"], "a/config.toml");
+        assert_eq!(objects[1]["content"], "[same content as a/config.toml]\n");
+    }
+
+    #[test]
+    fn test_cli_dedupe() {
+        let temp_dir = tempdir().unwrap();
+        write(temp_dir.path(), "one/config.toml", CONFIG);
+        write(temp_dir.path(), "two/config.toml", CONFIG);
+
+        let output = Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .args([".", "--dedupe", "exact"])
+            .env("TERM", "dumb")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            stdout.contains("[same content as one/config.toml]"),
+            "{}",
+            stdout
+        );
+    }
+}
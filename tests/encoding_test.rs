@@ -0,0 +1,165 @@
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use yek::{
+    config::YekConfig,
+    encoding::{decode, is_binary, legacy_encoding, strip_bom},
+    models::ExclusionReason,
+    serialize_repo_with_report, SerializationReport,
+};
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod encoding_tests {
+    use super::*;
+
+    fn utf16(text: &str, little_endian: bool, bom: bool) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if bom {
+            bytes.extend(if little_endian {
+                [0xFF, 0xFE]
+            } else {
+                [0xFE, 0xFF]
+            });
+        }
+        for unit in text.encode_utf16() {
+            bytes.extend(if little_endian {
+                unit.to_le_bytes()
+            } else {
+                unit.to_be_bytes()
+            });
+        }
+        bytes
+    }
+
+    fn serialize(root: &Path, configure: impl FnOnce(&mut YekConfig)) -> SerializationReport {
+        let mut config = YekConfig::default();
+        config.input_paths = vec![root.to_string_lossy().to_string()];
+        config.stream = true;
+        configure(&mut config);
+        serialize_repo_with_report(&config).unwrap()
+    }
+
+    #[test]
+    fn test_decode_unicode_encodings() {
+        let text = "fn main() { println!(\"héllo\"); }\n";
+
+        let plain = decode(text.as_bytes(), None).unwrap();
+        assert_eq!(plain.text, text);
+        assert_eq!(plain.encoding, None);
+
+        let with_bom = [b"\xEF\xBB\xBF".as_slice(), text.as_bytes()].concat();
+        let decoded = decode(&with_bom, None).unwrap();
+        assert_eq!(decoded.text, format!("\u{FEFF}{text}"));
+        assert_eq!(strip_bom(decoded.text), text);
+        assert_eq!(decoded.encoding.as_deref(), Some("UTF-8 with BOM"));
+
+        for (little_endian, bom, name) in [
+            (true, true, "UTF-16LE"),
+            (false, true, "UTF-16BE"),
+            (true, false, "UTF-16LE"),
+            (false, false, "UTF-16BE"),
+        ] {
+            let decoded = decode(&utf16(text, little_endian, bom), None).unwrap();
+            assert_eq!(strip_bom(decoded.text), text, "{name}, bom: {bom}");
+            assert_eq!(decoded.encoding.as_deref(), Some(name));
+        }
+
+        let mut utf32 = vec![0xFF, 0xFE, 0x00, 0x00];
+        for c in text.chars() {
+            utf32.extend((c as u32).to_le_bytes());
+        }
+        let decoded = decode(&utf32, None).unwrap();
+        assert_eq!(decoded.text, format!("\u{FEFF}{text}"));
+        assert_eq!(decoded.encoding.as_deref(), Some("UTF-32LE"));
+    }
+
+    #[test]
+    fn test_decode_legacy_and_binary() {
+        let latin1 = b"caf\xE9\n";
+        let lossy = decode(latin1, None).unwrap();
+        assert_eq!(lossy.text, "caf\u{FFFD}\n");
+        assert_eq!(lossy.encoding.as_deref(), Some("invalid UTF-8"));
+
+        let legacy = decode(latin1, legacy_encoding("latin1")).unwrap();
+        assert_eq!(legacy.text, "café\n");
+        assert_eq!(legacy.encoding.as_deref(), Some("windows-1252"));
+        assert!(legacy_encoding("no-such-encoding").is_none());
+
+        let binary = [0x00, 0x01, 0x02, 0x03, 0x00, 0x01, 0x02, 0x03];
+        assert!(decode(&binary, None).is_none());
+        assert!(is_binary(&binary));
+        assert!(!is_binary(&utf16("text\n", true, false)));
+    }
+
+    #[test]
+    fn test_utf16_files_are_serialized() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("win.cs"), utf16("class A {}\r\n", true, true)).unwrap();
+        fs::write(root.join("plain.rs"), "fn a() {}\n").unwrap();
+
+        let report = serialize(root, |_| {});
+        let win = report
+            .files
+            .iter()
+            .find(|f| f.rel_path == "win.cs")
+            .expect("UTF-16 file is not binary");
+        assert_eq!(win.content, "\u{FEFF}class A {}\r\n");
+        assert_eq!(win.encoding.as_deref(), Some("UTF-16LE"));
+
+        let entries: Vec<_> = report
+            .entries
+            .iter()
+            .map(|e| (e.rel_path.as_str(), e.encoding.as_deref()))
+            .collect();
+        assert!(entries.contains(&("win.cs", Some("UTF-16LE"))));
+        assert!(entries.contains(&("plain.rs", None)));
+        assert!(report.excluded.is_empty());
+
+        let report = serialize(root, |config| {
+            config.strip_bom = true;
+            config.normalize_newlines = true;
+        });
+        assert!(report.output.contains(">>>> win.cs\nclass A {}\n"));
+    }
+
+    #[test]
+    fn test_legacy_encoding_and_newline_options() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("notes.md"), b"caf\xE9\r\nna\xEFve\r\n").unwrap();
+
+        let report = serialize(root, |_| {});
+        assert_eq!(report.files[0].content, "caf\u{FFFD}\r\nna\u{FFFD}ve\r\n");
+
+        let report = serialize(root, |config| {
+            config.legacy_encoding = Some("windows-1252".to_string());
+            config.normalize_newlines = true;
+        });
+        assert_eq!(report.files[0].content, "café\nnaïve\n");
+        assert_eq!(report.files[0].encoding.as_deref(), Some("windows-1252"));
+    }
+
+    #[test]
+    fn test_binary_with_bom_is_excluded() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("blob.bin2"), [0xFF, 0xFE, 0x00, 0x00, 0x00, 0x00]).unwrap();
+
+        let report = serialize(root, |_| {});
+        assert!(report.files.is_empty());
+        assert_eq!(report.excluded[0].reason, ExclusionReason::BinaryContent);
+    }
+
+    #[test]
+    fn test_unknown_legacy_encoding_is_rejected() {
+        let mut config = YekConfig::default();
+        config.legacy_encoding = Some("klingon".to_string());
+        let err = config.validate().unwrap_err().to_string();
+        assert!(
+            err.contains("legacy_encoding: Unknown encoding 'klingon'"),
+            "{err}"
+        );
+    }
+}
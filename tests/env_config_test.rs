@@ -0,0 +1,247 @@
+use assert_cmd::Command;
+use serde_json::json;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use yek::config::{ConfigOrigin, YekConfig};
+
+#[cfg(test)]
+mod env_config_tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(var, value)| (var.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn yek(dir: &Path, args: &[&str], env: &[(&str, &str)]) -> std::process::Output {
+        Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(dir)
+            .args(args)
+            .env("TERM", "dumb")
+            .env("XDG_CONFIG_HOME", dir.join("no-user-config"))
+            .envs(env.iter().copied())
+            .output()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let (options, names) = YekConfig::env_overrides(vars(&[
+            ("YEK_TOKENS", "20k"),
+            ("YEK_LINE_NUMBERS", "true"),
+            ("YEK_IGNORE_PATTERNS", "docs/**, *.snap,"),
+            ("YEK_PRIORITY_RULES", r#"[{pattern: "^src/", score: 100}]"#),
+            ("YEK_OUTPUT_DIR", ""),
+            ("YEK_TOKENZ", "1"),
+            ("YEK_STREAM", "true"),
+            ("PATH", "/usr/bin"),
+        ]))
+        .unwrap();
+
+        assert_eq!(options["tokens"], "20k");
+        assert_eq!(options["line_numbers"], "true");
+        assert_eq!(options["ignore_patterns"], json!(["docs/**", "*.snap"]));
+        assert_eq!(
+            options["priority_rules"],
+            json!([{"pattern": "^src/", "score": 100}])
+        );
+        // Empty, unknown and computed options are skipped
+        assert_eq!(options.len(), 4);
+        assert_eq!(names["tokens"], "YEK_TOKENS");
+    }
+
+    #[test]
+    fn test_invalid_env_list() {
+        let err =
+            YekConfig::env_overrides(vars(&[("YEK_PRIORITY_RULES", "[unclosed")])).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("YEK_PRIORITY_RULES: Invalid list"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_apply_layers_precedence() {
+        let temp_dir = tempdir().unwrap();
+        let config_file = temp_dir.path().join("custom.yaml");
+        fs::write(
+            &config_file,
+            "tokens: 10k\nmax_size: 5MB\nline_numbers: true\nprofiles:\n  review:\n    tokens: 50k\n    output_name: review.txt\n",
+        )
+        .unwrap();
+
+        let mut config = YekConfig {
+            profile: Some("review".to_string()),
+            max_size: "1MB".to_string(),
+            ..YekConfig::default()
+        };
+        let cli_keys = HashSet::from(["max_size".to_string(), "profile".to_string()]);
+        let origins = config
+            .apply_layers(
+                false,
+                Some(&config_file),
+                &cli_keys,
+                vars(&[("YEK_TOKENS", "80k"), ("YEK_MAX_SIZE", "2MB")]),
+            )
+            .unwrap();
+
+        assert_eq!(config.tokens, "80k");
+        assert_eq!(
+            origins["tokens"],
+            ConfigOrigin::Env("YEK_TOKENS".to_string())
+        );
+        assert_eq!(config.max_size, "1MB");
+        assert_eq!(origins["max_size"], ConfigOrigin::CommandLine);
+        assert_eq!(config.output_name.as_deref(), Some("review.txt"));
+        assert_eq!(
+            origins["output_name"],
+            ConfigOrigin::Profile("review".to_string())
+        );
+        assert!(config.line_numbers);
+        assert_eq!(
+            origins["line_numbers"],
+            ConfigOrigin::ConfigFile(config_file.clone())
+        );
+        assert!(!origins.contains_key("debug"));
+    }
+
+    #[test]
+    fn test_env_selects_profile() {
+        let temp_dir = tempdir().unwrap();
+        let config_file = temp_dir.path().join("custom.yaml");
+        fs::write(&config_file, "profiles:\n  review:\n    tokens: 50k\n").unwrap();
+
+        let mut config = YekConfig::default();
+        let origins = config
+            .apply_layers(
+                false,
+                Some(&config_file),
+                &HashSet::new(),
+                vars(&[("YEK_PROFILE", "review")]),
+            )
+            .unwrap();
+        assert_eq!(config.tokens, "50k");
+        assert_eq!(config.profile.as_deref(), Some("review"));
+        assert_eq!(
+            origins["profile"],
+            ConfigOrigin::Env("YEK_PROFILE".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_env_value() {
+        let mut config = YekConfig::default();
+        let err = config
+            .apply_layers(
+                true,
+                None,
+                &HashSet::new(),
+                vars(&[("YEK_SUMMARY_ROWS", "many")]),
+            )
+            .unwrap_err();
+        assert!(err.to_string().starts_with("env:"), "{}", err);
+    }
+
+    #[test]
+    fn test_cli_env_overrides() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+        let template = [("YEK_OUTPUT_TEMPLATE", "ENV FILE_PATH\nFILE_CONTENT")];
+
+        let output = yek(temp_dir.path(), &["."], &template);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("ENV main.rs\nfn main() {}"), "{}", stdout);
+
+        let output = yek(
+            temp_dir.path(),
+            &[".", "--output-template", "CLI FILE_PATH\nFILE_CONTENT"],
+            &template,
+        );
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("CLI main.rs"), "{}", stdout);
+    }
+
+    #[test]
+    fn test_cli_config_show() {
+        let temp_dir = tempdir().unwrap();
+        git2::Repository::init(temp_dir.path()).unwrap();
+        fs::write(temp_dir.path().join("yek.yaml"), "tokens: 1000\n").unwrap();
+
+        let output = yek(
+            temp_dir.path(),
+            &["config", "show", "--max-size", "2MB"],
+            &[("YEK_OUTPUT_DIR", "/tmp/yek-env-out")],
+        );
+        assert!(output.status.success(), "{:?}", output);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let config_file = temp_dir.path().join("yek.yaml");
+        assert!(
+            stdout.contains(&format!(
+                "tokens: \"1000\"  (project config {})\n",
+                config_file.display()
+            )),
+            "{}",
+            stdout
+        );
+        assert!(
+            stdout.contains("max_size: \"2MB\"  (command line)\n"),
+            "{}",
+            stdout
+        );
+        assert!(
+            stdout.contains("output_dir: \"/tmp/yek-env-out\"  (env YEK_OUTPUT_DIR)\n"),
+            "{}",
+            stdout
+        );
+        assert!(stdout.contains("debug: false  (default)\n"), "{}", stdout);
+        // "config" and "show" are not input paths, and nothing is serialized
+        assert!(
+            stdout.contains("input_paths: [\".\"]  (default)\n"),
+            "{}",
+            stdout
+        );
+        assert!(!Path::new("/tmp/yek-env-out").exists());
+    }
+
+    #[test]
+    fn test_cli_keys_follow_clap_parsing() {
+        let temp_dir = tempdir().unwrap();
+        git2::Repository::init(temp_dir.path()).unwrap();
+        fs::write(
+            temp_dir.path().join("yek.yaml"),
+            "tokens: 1000\ntree_header: false\nmax_size: 1MB\n",
+        )
+        .unwrap();
+
+        // `--flag=value` and short flags count as set on the command line
+        let output = yek(
+            temp_dir.path(),
+            &["config", "show", "--tokens=2000", "-t"],
+            &[],
+        );
+        assert!(output.status.success(), "{:?}", output);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            stdout.contains("tokens: \"2000\"  (command line)\n"),
+            "{}",
+            stdout
+        );
+        assert!(
+            stdout.contains("tree_header: true  (command line)\n"),
+            "{}",
+            stdout
+        );
+        assert!(
+            stdout.contains("max_size: \"1MB\"  (project config"),
+            "{}",
+            stdout
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use assert_cmd::Command;
+use std::fs;
+use tempfile::tempdir;
+use yek::{config::YekConfig, models::ExclusionReason, serialize_repo_with_report, Yek};
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod file_limits_tests {
+    use super::*;
+
+    fn too_large(report: &yek::SerializationReport) -> Vec<(String, String)> {
+        report
+            .excluded
+            .iter()
+            .filter(|e| e.reason == ExclusionReason::TooLarge)
+            .map(|e| (e.rel_path.clone(), e.detail.clone().unwrap_or_default()))
+            .collect()
+    }
+
+    #[test]
+    fn test_max_file_size_skips_large_files() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("small.txt"), "small\n").unwrap();
+        fs::write(temp_dir.path().join("big.txt"), "x".repeat(2000)).unwrap();
+
+        let mut config = YekConfig::default();
+        config.input_paths = vec![temp_dir.path().to_string_lossy().to_string()];
+        config.max_file_size = Some("1KB".to_string());
+        config.stream = true;
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        let included: Vec<_> = report.files.iter().map(|f| f.rel_path.as_str()).collect();
+        assert_eq!(included, vec!["small.txt"]);
+        assert_eq!(
+            too_large(&report),
+            vec![(
+                "big.txt".to_string(),
+                "2000 bytes > max_file_size 1000".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_max_file_lines_skips_long_files() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("short.rs"), "a\nb\nc\n").unwrap();
+        fs::write(temp_dir.path().join("long.rs"), "a\nb\nc\nd").unwrap();
+
+        let mut config = YekConfig::default();
+        config.input_paths = vec![temp_dir.path().to_string_lossy().to_string()];
+        config.max_file_lines = Some(3);
+        config.stream = true;
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        let included: Vec<_> = report.files.iter().map(|f| f.rel_path.as_str()).collect();
+        assert_eq!(included, vec!["short.rs"]);
+        assert_eq!(
+            too_large(&report),
+            vec![(
+                "long.rs".to_string(),
+                "4 lines > max_file_lines 3".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_too_large_files_listed_in_tree_header_when_enabled() {
+        let temp_dir = tempdir().unwrap();
+        fs::create_dir(temp_dir.path().join("gen")).unwrap();
+        fs::write(temp_dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(temp_dir.path().join("gen/huge.rs"), "//\n".repeat(50)).unwrap();
+
+        let mut config = YekConfig::default();
+        config.input_paths = vec![temp_dir.path().to_string_lossy().to_string()];
+        config.max_file_lines = Some(10);
+        config.tree_header = true;
+        config.stream = true;
+
+        let report = serialize_repo_with_report(&config).unwrap();
+        assert!(!report.output.contains("huge.rs"));
+
+        config.tree_include_too_large = true;
+        let report = serialize_repo_with_report(&config).unwrap();
+        let (tree, body) = report.output.split_once(">>>> ").unwrap();
+        assert!(tree.contains("huge.rs"), "{}", tree);
+        assert!(!body.contains("huge.rs"), "{}", body);
+        assert_eq!(report.files.len(), 1);
+    }
+
+    #[test]
+    fn test_file_limits_from_builder() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "a\n").unwrap();
+        fs::write(temp_dir.path().join("b.txt"), "b".repeat(100)).unwrap();
+        fs::write(temp_dir.path().join("c.txt"), "1\n2\n3\n").unwrap();
+
+        let result = Yek::builder()
+            .path(temp_dir.path())
+            .max_file_size(50)
+            .max_file_lines(2)
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        let paths: Vec<_> = result
+            .selected
+            .iter()
+            .map(|f| f.rel_path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["a.txt"]);
+        assert_eq!(
+            result
+                .excluded
+                .iter()
+                .filter(|e| e.reason == ExclusionReason::TooLarge)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_invalid_file_limits_fail_validation() {
+        let mut config = YekConfig::default();
+        config.stream = true;
+        config.max_file_size = Some("lots".to_string());
+        let err = config.validate().unwrap_err().to_string();
+        assert!(
+            err.starts_with("max_file_size: Invalid size format"),
+            "{}",
+            err
+        );
+
+        config.max_file_size = Some("0".to_string());
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "max_file_size: cannot be 0"
+        );
+
+        config.max_file_size = None;
+        config.max_file_lines = Some(0);
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "max_file_lines: cannot be 0"
+        );
+    }
+
+    #[test]
+    fn test_cli_file_limit_flags() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("keep.txt"), "keep\n").unwrap();
+        fs::write(temp_dir.path().join("drop.txt"), "x".repeat(5000)).unwrap();
+
+        let output = Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .args(["--max-file-size", "1K", "--max-file-lines", "100"])
+            .env("TERM", "dumb")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(">>>> keep.txt"), "{}", stdout);
+        assert!(!stdout.contains("drop.txt"), "{}", stdout);
+    }
+}
@@ -0,0 +1,237 @@
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use yek::{
+    config::YekConfig,
+    generated::{generated_marker, minified_reason},
+    models::ExclusionReason,
+    serialize_repo_with_report, Yek,
+};
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod generated_tests {
+    use super::*;
+
+    fn write_files(root: &Path, files: &[(&str, &str)]) {
+        for (path, content) in files {
+            let full = root.join(path);
+            fs::create_dir_all(full.parent().unwrap()).unwrap();
+            fs::write(full, content).unwrap();
+        }
+    }
+
+    fn minified_js() -> String {
+        "var a=function(b){return b*2};".repeat(100)
+    }
+
+    fn skipped(report: &yek::SerializationReport) -> Vec<(String, ExclusionReason, String)> {
+        report
+            .excluded
+            .iter()
+            .filter(|e| {
+                matches!(
+                    e.reason,
+                    ExclusionReason::Generated | ExclusionReason::Minified
+                )
+            })
+            .map(|e| {
+                (
+                    e.rel_path.clone(),
+                    e.reason,
+                    e.detail.clone().unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_generated_markers() {
+        assert_eq!(
+            generated_marker("// Code generated by protoc-gen-go. DO NOT EDIT.\npackage pb\n"),
+            Some("Code generated by protoc-gen-go. DO NOT EDIT")
+        );
+        assert_eq!(
+            generated_marker("/**\n * @generated\n */\n"),
+            Some("@generated")
+        );
+        assert_eq!(
+            generated_marker("// <auto-generated>\n// </auto-generated>\n"),
+            Some("<auto-generated")
+        );
+        assert_eq!(
+            generated_marker("# This file is automatically generated by Cargo.\n"),
+            Some("This file is automatically generated")
+        );
+        assert_eq!(generated_marker("fn main() {}\n"), None);
+        assert_eq!(
+            generated_marker("// mentions @generatedCode in passing\n"),
+            None
+        );
+
+        // Mentions outside a comment, or in doc comments, are not markers
+        assert_eq!(
+            generated_marker("const TAG: &str = \"@generated\";\n"),
+            None
+        );
+        assert_eq!(
+            generated_marker("/// Skips files marked @generated\nfn skip() {}\n"),
+            None
+        );
+        assert_eq!(
+            generated_marker("Note that this file was generated by hand.\n"),
+            None
+        );
+        assert_eq!(
+            generated_marker("<!-- @generated -->\n"),
+            Some("@generated")
+        );
+        assert_eq!(generated_marker(include_str!("../src/generated.rs")), None);
+        assert_eq!(generated_marker(include_str!("generated_test.rs")), None);
+
+        let late = format!("{}// @generated\n", "line\n".repeat(30));
+        assert_eq!(generated_marker(&late), None);
+    }
+
+    #[test]
+    fn test_minified_heuristic() {
+        assert!(minified_reason(&minified_js()).is_some());
+        assert!(minified_reason("var a=1;").is_none());
+
+        let source = "fn main() {\n    println!(\"hello\");\n}\n".repeat(100);
+        assert!(minified_reason(&source).is_none());
+
+        let prose = "word ".repeat(1000);
+        assert!(minified_reason(&prose).is_none());
+    }
+
+    #[test]
+    fn test_generated_and_minified_files_skipped_by_default() {
+        let temp_dir = tempdir().unwrap();
+        write_files(
+            temp_dir.path(),
+            &[
+                ("main.go", "package main\n"),
+                ("api.pb.go", "// Code generated by protoc. DO NOT EDIT.\n"),
+                ("dist/app.min.js", &minified_js()),
+            ],
+        );
+
+        let mut config = YekConfig::default();
+        config.input_paths = vec![temp_dir.path().to_string_lossy().to_string()];
+        config.stream = true;
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        let included: Vec<_> = report.files.iter().map(|f| f.rel_path.as_str()).collect();
+        assert_eq!(included, vec!["main.go"]);
+        let generated = skipped(&report);
+        assert_eq!(generated.len(), 2, "{:?}", generated);
+        assert_eq!(
+            generated[0],
+            (
+                "api.pb.go".to_string(),
+                ExclusionReason::Generated,
+                "'Code generated by protoc. DO NOT EDIT' marker".to_string()
+            )
+        );
+        assert_eq!(generated[1].0, "dist/app.min.js");
+        assert_eq!(generated[1].1, ExclusionReason::Minified);
+
+        config.include_generated = true;
+        let report = serialize_repo_with_report(&config).unwrap();
+        assert_eq!(report.files.len(), 3);
+        assert!(skipped(&report).is_empty());
+    }
+
+    #[test]
+    fn test_linguist_generated_attribute() {
+        let temp_dir = tempdir().unwrap();
+        write_files(
+            temp_dir.path(),
+            &[
+                (".gitattributes", "*.gen.go linguist-generated\n"),
+                ("proto/.gitattributes", "*.rs linguist-generated=true\n"),
+                ("src/.gitattributes", "keep.gen.go -linguist-generated\n"),
+                ("src/app.gen.go", "package src\n"),
+                ("src/keep.gen.go", "package src\n"),
+                ("proto/msg.rs", "pub struct Msg;\n"),
+                ("src/main.rs", "fn main() {}\n"),
+            ],
+        );
+
+        let mut config = YekConfig::default();
+        config.input_paths = vec![temp_dir.path().to_string_lossy().to_string()];
+        config.stream = true;
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        let included: Vec<_> = report.files.iter().map(|f| f.rel_path.as_str()).collect();
+        assert_eq!(included, vec!["src/keep.gen.go", "src/main.rs"]);
+        let detail = "linguist-generated in .gitattributes".to_string();
+        assert_eq!(
+            skipped(&report),
+            vec![
+                (
+                    "proto/msg.rs".to_string(),
+                    ExclusionReason::Generated,
+                    detail.clone()
+                ),
+                (
+                    "src/app.gen.go".to_string(),
+                    ExclusionReason::Generated,
+                    detail
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_include_generated_from_builder() {
+        let temp_dir = tempdir().unwrap();
+        write_files(temp_dir.path(), &[("schema.rs", "// @generated\n")]);
+
+        let serialize = |include| {
+            Yek::builder()
+                .path(temp_dir.path())
+                .include_generated(include)
+                .build()
+                .unwrap()
+                .serialize()
+                .unwrap()
+        };
+        assert!(serialize(false).selected.is_empty());
+        assert_eq!(serialize(true).selected.len(), 1);
+    }
+
+    #[test]
+    fn test_cli_include_generated_flag() {
+        let temp_dir = tempdir().unwrap();
+        write_files(
+            temp_dir.path(),
+            &[
+                ("main.rs", "fn main() {}\n"),
+                (
+                    "bindings.rs",
+                    "/* automatically generated by rust-bindgen */\n// @generated\n",
+                ),
+            ],
+        );
+
+        let run = |args: &[&str]| {
+            let output = Command::cargo_bin("yek")
+                .unwrap()
+                .current_dir(temp_dir.path())
+                .args(args)
+                .env("TERM", "dumb")
+                .output()
+                .unwrap();
+            String::from_utf8(output.stdout).unwrap()
+        };
+        let stdout = run(&[]);
+        assert!(stdout.contains(">>>> main.rs"), "{}", stdout);
+        assert!(!stdout.contains("bindings.rs"), "{}", stdout);
+
+        let stdout = run(&["--include-generated"]);
+        assert!(stdout.contains(">>>> bindings.rs"), "{}", stdout);
+    }
+}
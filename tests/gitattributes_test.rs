@@ -0,0 +1,198 @@
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use yek::{
+    category::FileCategory,
+    config::YekConfig,
+    models::ExclusionReason,
+    repository::{AttrValue, GitAttributes},
+    serialize_repo_with_report, SerializationReport,
+};
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod gitattributes_tests {
+    use super::*;
+
+    fn write_files(root: &Path, files: &[(&str, &str)]) {
+        for (path, content) in files {
+            let full = root.join(path);
+            fs::create_dir_all(full.parent().unwrap()).unwrap();
+            fs::write(full, content).unwrap();
+        }
+    }
+
+    fn serialize(root: &Path) -> SerializationReport {
+        let mut config = YekConfig::default();
+        config.input_paths = vec![root.to_string_lossy().to_string()];
+        config.stream = true;
+        serialize_repo_with_report(&config).unwrap()
+    }
+
+    fn excluded(report: &SerializationReport, reason: ExclusionReason) -> Vec<(String, String)> {
+        report
+            .excluded
+            .iter()
+            .filter(|e| e.reason == reason)
+            .map(|e| (e.rel_path.clone(), e.detail.clone().unwrap_or_default()))
+            .collect()
+    }
+
+    #[test]
+    fn test_attribute_precedence() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write_files(
+            root,
+            &[
+                (".gitattributes", "*.txt text eol=lf\n*.txt -text\n"),
+                ("sub/.gitattributes", "*.txt text\nkeep.txt !text\n"),
+                ("git/info/attributes", "notes.txt binary\n"),
+            ],
+        );
+        let attributes = GitAttributes::new(root, Some(&root.join("git")));
+
+        assert_eq!(
+            attributes.get(&root.join("a.txt"), "text"),
+            Some(AttrValue::Unset)
+        );
+        assert_eq!(
+            attributes.get(&root.join("a.txt"), "eol"),
+            Some(AttrValue::Value("lf".to_string()))
+        );
+        assert_eq!(
+            attributes.get(&root.join("sub/a.txt"), "text"),
+            Some(AttrValue::Set)
+        );
+        assert_eq!(attributes.get(&root.join("sub/keep.txt"), "text"), None);
+        assert_eq!(attributes.get(&root.join("a.rs"), "text"), None);
+
+        let notes = root.join("sub/notes.txt");
+        assert!(attributes.is_set(&notes, "binary"));
+        assert_eq!(attributes.get(&notes, "diff"), Some(AttrValue::Unset));
+        let found = attributes.lookup(&notes, false, "text").unwrap();
+        assert_eq!(found.line, "notes.txt binary");
+        assert_eq!(found.source, root.join("git/info/attributes"));
+    }
+
+    #[test]
+    fn test_export_ignore_and_vendored_files_are_ignored() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write_files(
+            root,
+            &[
+                (
+                    ".gitattributes",
+                    "docs export-ignore\nthird_party/** linguist-vendored\n",
+                ),
+                ("src/.gitattributes", "fixtures.rs export-ignore\n"),
+                ("src/lib.rs", "pub fn f() {}\n"),
+                ("src/fixtures.rs", "// fixtures\n"),
+                ("docs/guide.md", "# Guide\n"),
+                ("third_party/zlib/zlib.c", "int z;\n"),
+            ],
+        );
+
+        let report = serialize(root);
+        let included: Vec<_> = report.files.iter().map(|f| f.rel_path.as_str()).collect();
+        assert_eq!(included, vec!["src/lib.rs"]);
+        assert_eq!(
+            excluded(&report, ExclusionReason::Ignored),
+            vec![
+                (
+                    "docs/".to_string(),
+                    ".gitattributes: docs export-ignore".to_string()
+                ),
+                (
+                    "src/fixtures.rs".to_string(),
+                    "src/.gitattributes: fixtures.rs export-ignore".to_string()
+                ),
+                (
+                    "third_party/zlib/".to_string(),
+                    ".gitattributes: third_party/** linguist-vendored".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_yekignore_reincludes_export_ignored_files() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write_files(
+            root,
+            &[
+                (".gitattributes", "*.md export-ignore\n"),
+                (".yekignore", "!README.md\n"),
+                ("README.md", "# Readme\n"),
+                ("CHANGELOG.md", "# Changes\n"),
+            ],
+        );
+
+        let report = serialize(root);
+        let included: Vec<_> = report.files.iter().map(|f| f.rel_path.as_str()).collect();
+        assert_eq!(included, vec!["README.md"]);
+    }
+
+    #[test]
+    fn test_binary_and_no_diff_files_are_skipped() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write_files(
+            root,
+            &[
+                (".gitattributes", "*.snap -diff\n*.pem binary\n"),
+                ("tests/.gitattributes", "keep.snap diff\n"),
+                ("cert.pem", "-----BEGIN CERTIFICATE-----\n"),
+                ("tests/out.snap", "snapshot\n"),
+                ("tests/keep.snap", "kept snapshot\n"),
+            ],
+        );
+
+        let report = serialize(root);
+        let included: Vec<_> = report.files.iter().map(|f| f.rel_path.as_str()).collect();
+        assert_eq!(included, vec!["tests/keep.snap"]);
+        assert_eq!(
+            excluded(&report, ExclusionReason::BinaryContent),
+            vec![
+                (
+                    "cert.pem".to_string(),
+                    ".gitattributes: *.pem binary".to_string()
+                ),
+                (
+                    "tests/out.snap".to_string(),
+                    ".gitattributes: *.snap -diff".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_linguist_documentation_sets_category() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write_files(
+            root,
+            &[
+                (".gitattributes", "examples/** linguist-documentation\n"),
+                ("examples/demo.rs", "fn main() {}\n"),
+                ("src/main.rs", "fn main() {}\n"),
+            ],
+        );
+
+        let report = serialize(root);
+        let categories: Vec<_> = report
+            .files
+            .iter()
+            .map(|f| (f.rel_path.as_str(), f.category))
+            .collect();
+        assert_eq!(
+            categories,
+            vec![
+                ("examples/demo.rs", FileCategory::Documentation),
+                ("src/main.rs", FileCategory::Source),
+            ]
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+use tempfile::tempdir;
+use yek::{config::YekConfig, ignore_rules::IgnoreRules, models::ExclusionReason};
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod gitignore_tests {
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) -> Option<std::process::Output> {
+        Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .output()
+            .ok()
+    }
+
+    /// Create the files of a fixture; paths ending in `/` are directories
+    fn write_fixture(root: &Path, files: &[(&str, &str)]) {
+        for (path, content) in files {
+            let full = root.join(path);
+            if path.ends_with('/') {
+                fs::create_dir_all(&full).unwrap();
+            } else {
+                fs::create_dir_all(full.parent().unwrap()).unwrap();
+                fs::write(&full, content).unwrap();
+            }
+        }
+    }
+
+    /// Check every path with both `git check-ignore` and yek's ignore rules.
+    /// `global` becomes the repository's `core.excludesFile`.
+    fn assert_conforms(files: &[(&str, &str)], global: &str, checks: &[&str]) {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap().join("repo");
+        fs::create_dir(&root).unwrap();
+        if git(&root, &["init", "-q"]).is_none_or(|out| !out.status.success()) {
+            eprintln!("git is not available, skipping");
+            return;
+        }
+        let global_file = root.parent().unwrap().join("global-ignore");
+        fs::write(&global_file, global).unwrap();
+        git(
+            &root,
+            &["config", "core.excludesFile", global_file.to_str().unwrap()],
+        )
+        .unwrap();
+        write_fixture(&root, files);
+
+        let rules = IgnoreRules::new(
+            &root,
+            &root,
+            Arc::new(IgnoreRules::build_patterns(&root, &[]).unwrap()),
+        );
+        let mut mismatches = Vec::new();
+        for check in checks {
+            let path = root.join(check.trim_end_matches('/'));
+            let git_ignored = git(&root, &["check-ignore", "-q", "--no-index", check])
+                .unwrap()
+                .status
+                .success();
+            let yek_ignored = rules.matched(&path, path.is_dir()).is_some();
+            if git_ignored != yek_ignored {
+                mismatches.push(format!("{check}: git={git_ignored} yek={yek_ignored}"));
+            }
+        }
+        assert!(mismatches.is_empty(), "{:#?}", mismatches);
+    }
+
+    #[test]
+    fn test_nested_gitignore_files() {
+        assert_conforms(
+            &[
+                (".gitignore", "*.log\n!keep.log\n"),
+                ("sub/.gitignore", "!debug.log\nkeep.log\n"),
+                ("a.log", ""),
+                ("keep.log", ""),
+                ("sub/debug.log", ""),
+                ("sub/keep.log", ""),
+                ("sub/deeper/trace.log", ""),
+                ("sub/deeper/keep.log", ""),
+            ],
+            "",
+            &[
+                "a.log",
+                "keep.log",
+                "sub/debug.log",
+                "sub/keep.log",
+                "sub/deeper/trace.log",
+                "sub/deeper/keep.log",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_directory_only_patterns() {
+        assert_conforms(
+            &[
+                (".gitignore", "build/\ncache\n/out/\n"),
+                ("build/app.txt", ""),
+                ("src/build", "a file named build"),
+                ("src/build-notes.txt", ""),
+                ("cache/data.txt", ""),
+                ("src/cache", "a file named cache"),
+                ("out/x.txt", ""),
+                ("src/out/y.txt", ""),
+            ],
+            "",
+            &[
+                "build/",
+                "build/app.txt",
+                "src/build",
+                "src/build-notes.txt",
+                "cache/",
+                "cache/data.txt",
+                "src/cache",
+                "out/x.txt",
+                "src/out/y.txt",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_negation_cannot_reinclude_from_ignored_directory() {
+        assert_conforms(
+            &[
+                (
+                    ".gitignore",
+                    "logs/\n!logs/important.log\ntmp/*\n!tmp/keep.txt\n",
+                ),
+                ("logs/important.log", ""),
+                ("logs/other.log", ""),
+                ("tmp/keep.txt", ""),
+                ("tmp/drop.txt", ""),
+                ("tmp/nested/keep.txt", ""),
+            ],
+            "",
+            &[
+                "logs/important.log",
+                "logs/other.log",
+                "tmp/keep.txt",
+                "tmp/drop.txt",
+                "tmp/nested/keep.txt",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_anchored_and_wildcard_patterns() {
+        assert_conforms(
+            &[
+                (
+                    ".gitignore",
+                    "/root.txt\ndoc/*.md\n**/tmp\na/**/z.txt\n\\#hash\n\\!bang\n# comment\n",
+                ),
+                ("root.txt", ""),
+                ("sub/root.txt", ""),
+                ("doc/a.md", ""),
+                ("doc/sub/b.md", ""),
+                ("x/tmp/y.txt", ""),
+                ("a/z.txt", ""),
+                ("a/b/c/z.txt", ""),
+                ("#hash", ""),
+                ("!bang", ""),
+                ("comment", ""),
+            ],
+            "",
+            &[
+                "root.txt",
+                "sub/root.txt",
+                "doc/a.md",
+                "doc/sub/b.md",
+                "x/tmp/y.txt",
+                "a/z.txt",
+                "a/b/c/z.txt",
+                "#hash",
+                "!bang",
+                "comment",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_info_exclude_and_global_excludes_precedence() {
+        assert_conforms(
+            &[
+                (".git/info/exclude", "*.bak\n!notes.txt\n"),
+                (".gitignore", "!keep.bak\n"),
+                ("a.bak", ""),
+                ("keep.bak", ""),
+                ("notes.txt", ""),
+                ("other.txt", ""),
+                ("patch.orig", ""),
+            ],
+            "*.txt\n*.orig\n",
+            &["a.bak", "keep.bak", "notes.txt", "other.txt", "patch.orig"],
+        );
+    }
+
+    #[test]
+    fn test_serialize_reports_ignore_rule_sources() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        if git(&root, &["init", "-q"]).is_none_or(|out| !out.status.success()) {
+            eprintln!("git is not available, skipping");
+            return;
+        }
+        write_fixture(
+            &root,
+            &[
+                (".git/info/exclude", "secret.txt\n"),
+                (".gitignore", "generated/\n"),
+                ("src/.gitignore", "*.tmp\n"),
+                ("src/main.rs", "fn main() {}\n"),
+                ("src/scratch.tmp", "scratch\n"),
+                ("generated/api.rs", "// generated\n"),
+                ("secret.txt", "hunter2\n"),
+                ("notes.txt", "notes\n"),
+            ],
+        );
+
+        let mut config = YekConfig::default();
+        config.input_paths = vec![root.join("src").to_string_lossy().to_string()];
+        config.input_paths.push(root.to_string_lossy().to_string());
+        config.stream = true;
+        let report = yek::serialize_repo_with_report(&config).unwrap();
+
+        let included: Vec<_> = report.files.iter().map(|f| f.rel_path.as_str()).collect();
+        assert_eq!(included, vec!["notes.txt", "src/main.rs"]);
+
+        let ignored: Vec<_> = report
+            .excluded
+            .iter()
+            .filter(|e| e.reason == ExclusionReason::Ignored)
+            .map(|e| (e.rel_path.as_str(), e.detail.as_deref().unwrap_or("")))
+            .collect();
+        assert_eq!(
+            ignored,
+            vec![
+                ("generated/", ".gitignore: generated/"),
+                ("secret.txt", ".git/info/exclude: secret.txt"),
+                ("src/scratch.tmp", "src/.gitignore: *.tmp"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use assert_cmd::Command;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use yek::{
+    api::{OutputFormat, Yek},
+    headers::{leading_comment, strip_headers},
+    models::ProcessedFile,
+};
+
+#[cfg(test)]
+mod headers_tests {
+    use super::*;
+
+    const LICENSE: &str = "// Copyright 2024 Acme Corp\n//\n// Licensed under the Apache License, Version 2.0.\n// You may not use this file except in compliance with the License.\n";
+
+    fn write(root: &Path, rel_path: &str, content: &str) {
+        let path = root.join(rel_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn file(rel_path: &str, content: &str) -> ProcessedFile {
+        ProcessedFile::new(rel_path.to_string(), content.to_string(), 0, 0)
+    }
+
+    #[test]
+    fn test_leading_comment_styles() {
+        let content = format!("{}\nfn main() {{}}\n", LICENSE);
+        assert_eq!(leading_comment(&content), Some((0, LICENSE.len() + 1)));
+
+        let content = "#!/usr/bin/env python\n# Copyright Acme\n# All rights reserved\nimport os\n";
+        let (start, end) = leading_comment(content).unwrap();
+        assert_eq!(
+            &content[start..end],
+            "# Copyright Acme\n# All rights reserved\n"
+        );
+
+        let content = "/*\n * Copyright Acme\n */\n\npackage main\n";
+        let (start, end) = leading_comment(content).unwrap();
+        assert_eq!(&content[start..end], "/*\n * Copyright Acme\n */\n\n");
+
+        // Code after the end of a block comment keeps the comment in place
+        assert_eq!(leading_comment("/* note */ int x;\n"), None);
+        // Preprocessor lines and attributes are not comments
+        assert_eq!(leading_comment("#include <stdio.h>\n"), None);
+        assert_eq!(leading_comment("#[derive(Debug)]\nstruct A;\n"), None);
+        assert_eq!(leading_comment("fn main() {}\n"), None);
+    }
+
+    #[test]
+    fn test_strip_repeated_headers() {
+        let python_license = LICENSE.replace("//", "#").replace("2024", "2019");
+        let mut files = vec![
+            file("b.rs", &format!("{}\nfn b() {{}}\n", LICENSE)),
+            file("a.rs", &format!("{}\nfn a() {{}}\n", LICENSE)),
+            file("c.py", &format!("{}\ndef c(): pass\n", python_license)),
+            file("d.rs", "// Just a comment\n// on two lines\nfn d() {}\n"),
+        ];
+        let headers = strip_headers(&mut files, true, &[]);
+
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0].text, LICENSE);
+        assert_eq!(headers[0].files, vec!["a.rs", "b.rs", "c.py"]);
+        assert_eq!(files[0].content, "fn b() {}\n");
+        assert_eq!(files[0].size_bytes, "fn b() {}\n".len());
+        assert_eq!(files[2].content, "def c(): pass\n");
+        assert!(files[3].content.starts_with("// Just a comment"));
+    }
+
+    #[test]
+    fn test_headers_in_too_few_files_are_kept() {
+        let mut files = vec![
+            file("a.rs", &format!("{}fn a() {{}}\n", LICENSE)),
+            file("b.rs", &format!("{}fn b() {{}}\n", LICENSE)),
+        ];
+        assert!(strip_headers(&mut files, true, &[]).is_empty());
+        assert!(files[0].content.starts_with(LICENSE));
+    }
+
+    #[test]
+    fn test_strip_boilerplate_patterns() {
+        let mut files = vec![
+            file("a.rs", &format!("{}fn a() {{}}\n", LICENSE)),
+            file("b.rs", "// Helper functions\nfn b() {}\n"),
+        ];
+        let boilerplate = [Regex::new(r"Copyright \d+ Acme").unwrap()];
+        let headers = strip_headers(&mut files, false, &boilerplate);
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0].files, vec!["a.rs"]);
+        assert_eq!(files[0].content, "fn a() {}\n");
+        assert!(files[1].content.starts_with("// Helper"));
+    }
+
+    #[test]
+    fn test_serialize_shows_header_once() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        for name in ["a.rs", "b.rs", "c.rs"] {
+            write(root, name, &format!("{}\nfn main() {{}}\n", LICENSE));
+        }
+
+        let result = Yek::builder()
+            .path(root)
+            .strip_headers(true)
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        assert_eq!(result.output.matches("Licensed under").count(), 1);
+        assert!(
+            result.output.starts_with(&format!(
+                ">>>> [header removed from 3 files]\n{}\n",
+                LICENSE
+            )),
+            "{}",
+            result.output
+        );
+        assert!(result.output.contains(">>>> a.rs\nfn main() {}\n"));
+        let entry = &result.selected[0];
+        assert_eq!(
+            &result.output[entry.byte_start..entry.byte_end],
+            ">>>> a.rs\nfn main() {}\n"
+        );
+
+        let kept = Yek::builder()
+            .path(root)
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        assert_eq!(kept.output.matches("Licensed under").count(), 3);
+    }
+
+    #[test]
+    fn test_json_output_lists_header_first() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write(root, "a.rs", &format!("{}fn a() {{}}\n", LICENSE));
+
+        let result = Yek::builder()
+            .path(root)
+            .format(OutputFormat::Json)
+            .boilerplate("Acme Corp")
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        let objects: Vec<serde_json::Value> = serde_json::from_str(&result.output).unwrap();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0]["header"], LICENSE);
+        assert_eq!(objects[0]["removed_from"], serde_json::json!(["a.rs"]));
+        assert_eq!(objects[1]["filename"], "a.rs");
+        assert_eq!(objects[1]["content"], "fn a() {}\n");
+    }
+
+    #[test]
+    fn test_invalid_boilerplate_pattern() {
+        let err = Yek::builder().boilerplate("(unclosed").build().unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("boilerplate_patterns: Invalid regex"));
+    }
+
+    #[test]
+    fn test_cli_strip_headers() {
+        let temp_dir = tempdir().unwrap();
+        for name in ["a.go", "b.go", "c.go"] {
+            write(temp_dir.path(), name, &format!("{}package main\n", LICENSE));
+        }
+
+        let output = Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .args([".", "--strip-headers"])
+            .env("TERM", "dumb")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(stdout.matches("Licensed under").count(), 1, "{}", stdout);
+        assert!(stdout.contains(">>>> a.go\npackage main\n"), "{}", stdout);
+    }
+}
@@ -0,0 +1,289 @@
+use assert_cmd::Command;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use yek::{api::Yek, config::YekConfig, stages::StageConfig};
+
+#[cfg(test)]
+mod hierarchy_tests {
+    use super::*;
+
+    fn write(root: &Path, rel_path: &str, content: &str) {
+        let path = root.join(rel_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn yek(dir: &Path, config_home: &Path, args: &[&str]) -> std::process::Output {
+        Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(dir)
+            .args(args)
+            .env("TERM", "dumb")
+            .env("XDG_CONFIG_HOME", config_home)
+            .output()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_project_config_files_stop_at_repository_top() {
+        let temp_dir = tempdir().unwrap();
+        let repo = temp_dir.path().join("repo");
+        git2::Repository::init(&repo).unwrap();
+        write(temp_dir.path(), "yek.yaml", "tokens: 10k\n");
+        write(&repo, "yek.toml", "tokens = \"20k\"\n");
+        write(&repo, "services/api/yek.yaml", "tokens: 30k\n");
+
+        let files = YekConfig::project_config_files(&repo.join("services/api"));
+        assert_eq!(
+            files,
+            vec![repo.join("yek.toml"), repo.join("services/api/yek.yaml")]
+        );
+    }
+
+    #[test]
+    fn test_project_config_files_outside_repository() {
+        let temp_dir = tempdir().unwrap();
+        write(temp_dir.path(), "yek.yaml", "tokens: 10k\n");
+        write(temp_dir.path(), "app/yek.toml", "tokens = \"20k\"\n");
+        let app = temp_dir.path().join("app");
+
+        // Parent directories are not searched without a repository to bound them
+        assert_eq!(
+            YekConfig::project_config_files(&app),
+            vec![app.join("yek.toml")]
+        );
+        assert!(YekConfig::project_config_files(&app.join("src")).is_empty());
+    }
+
+    #[test]
+    fn test_explicit_config_file_skips_project_files() {
+        let temp_dir = tempdir().unwrap();
+        git2::Repository::init(temp_dir.path()).unwrap();
+        write(temp_dir.path(), "yek.yaml", "tokens: 10k\n");
+        let explicit = temp_dir.path().join("other.yaml");
+        write(temp_dir.path(), "other.yaml", "tokens: 20k\n");
+
+        let input = temp_dir.path().to_string_lossy().to_string();
+        let files = YekConfig::config_files(&[input], Some(&explicit));
+        assert_eq!(files.last(), Some(&explicit));
+        assert!(!files.contains(&temp_dir.path().join("yek.yaml")));
+    }
+
+    #[test]
+    fn test_merge_config_files() {
+        let temp_dir = tempdir().unwrap();
+        let outer = temp_dir.path().join("yek.yaml");
+        let inner = temp_dir.path().join("sub/yek.json");
+        write(
+            temp_dir.path(),
+            "yek.yaml",
+            "tokens: 1000\nline_numbers: true\nprofiles:\n  review:\n    tokens: 5k\n",
+        );
+        write(
+            temp_dir.path(),
+            "sub/yek.json",
+            r#"{"tokens": "2k", "profiles": {"overview": {"tree_only": true}}}"#,
+        );
+
+        let merged = YekConfig::merge_config_files(&[outer, inner]).unwrap();
+        assert_eq!(merged["tokens"], "2k");
+        assert_eq!(merged["line_numbers"], true);
+        let profiles = merged["profiles"].as_object().unwrap();
+        assert!(profiles.contains_key("review"));
+        assert!(profiles.contains_key("overview"));
+    }
+
+    #[test]
+    fn test_scoped_ignore_and_priority() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write(
+            root,
+            "services/a/yek.yaml",
+            "ignore_patterns:\n  - \"*.gen.js\"\npriority_rules:\n  - pattern: \"^api/\"\n    score: 50\n",
+        );
+        write(root, "services/a/client.gen.js", "export const a = 1;\n");
+        write(root, "services/b/client.gen.js", "export const b = 1;\n");
+        write(
+            root,
+            "services/a/api/handler.js",
+            "export function a() {}\n",
+        );
+        write(
+            root,
+            "services/b/api/handler.js",
+            "export function b() {}\n",
+        );
+
+        let result = Yek::builder()
+            .path(root)
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        let paths: Vec<&str> = result
+            .selected
+            .iter()
+            .map(|e| e.rel_path.as_str())
+            .collect();
+        assert!(!paths.contains(&"services/a/client.gen.js"), "{:?}", paths);
+        assert!(paths.contains(&"services/b/client.gen.js"), "{:?}", paths);
+        // The per-directory config file itself is never part of the output
+        assert!(!paths.contains(&"services/a/yek.yaml"), "{:?}", paths);
+
+        let excluded = result
+            .excluded
+            .iter()
+            .find(|file| file.rel_path == "services/a/client.gen.js")
+            .unwrap();
+        assert_eq!(
+            excluded.detail.as_deref(),
+            Some("services/a/yek.yaml: *.gen.js")
+        );
+
+        // Higher priority files come last
+        let position = |path: &str| paths.iter().position(|p| *p == path).unwrap();
+        assert!(position("services/a/api/handler.js") > position("services/b/api/handler.js"));
+        assert_eq!(paths.last(), Some(&"services/a/api/handler.js"));
+    }
+
+    #[test]
+    fn test_config_in_input_directory_is_not_scoped() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write(root, "app/yek.yaml", "ignore_patterns:\n  - \"*.gen.js\"\n");
+        write(root, "app/client.gen.js", "export const a = 1;\n");
+
+        // Passed as the input, app/ is not a subdirectory of the walk
+        let result = Yek::builder()
+            .path(root.join("app"))
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        assert_eq!(result.selected.len(), 1);
+    }
+
+    #[test]
+    fn test_cli_layers_user_and_repository_configs() {
+        let temp_dir = tempdir().unwrap();
+        let config_home = temp_dir.path().join("config");
+        let repo = temp_dir.path().join("repo");
+        git2::Repository::init(&repo).unwrap();
+        write(
+            &config_home,
+            "yek/config.yaml",
+            "output_template: \"USER FILE_PATH\\nFILE_CONTENT\"\nline_numbers: true\n",
+        );
+        write(
+            &repo,
+            "yek.yaml",
+            "output_template: \"REPO FILE_PATH\\nFILE_CONTENT\"\ntokens: 1000\n",
+        );
+        write(&repo, "services/api/yek.yaml", "line_numbers: false\n");
+        write(&repo, "services/api/main.rs", "fn main() {}\n");
+
+        // Run from a subdirectory holding its own config file
+        let api = repo.join("services/api");
+        let output = yek(&api, &config_home, &["."]);
+        assert!(output.status.success(), "{:?}", output);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("REPO main.rs\nfn main() {}"), "{}", stdout);
+
+        // The user config applies where the repository sets nothing
+        let output = yek(&repo, &config_home, &["."]);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            stdout.contains("REPO services/api/main.rs\n  1 | fn main() {}"),
+            "{}",
+            stdout
+        );
+
+        // The command line wins over every file
+        let output = yek(
+            &api,
+            &config_home,
+            &[".", "--output-template", "CLI FILE_PATH\nFILE_CONTENT"],
+        );
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("CLI main.rs"), "{}", stdout);
+
+        // --no-config leaves all files out
+        let output = yek(&api, &config_home, &[".", "--no-config"]);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(">>>> main.rs"), "{}", stdout);
+    }
+
+    const EXEC_CONFIG: &str = "allow_exec: true\nstages:\n  - type: exec\n    command: \"tr a-z A-Z\"\n  - type: regex_replace\n    pattern: main\n    replacement: start\nprofiles:\n  loud:\n    stages:\n      - type: exec\n        command: \"tr a-z A-Z\"\n";
+
+    #[test]
+    fn test_project_exec_stages_need_allow_exec() {
+        let temp_dir = tempdir().unwrap();
+        git2::Repository::init(temp_dir.path()).unwrap();
+        write(temp_dir.path(), "yek.yaml", EXEC_CONFIG);
+        let input = temp_dir.path().to_string_lossy().to_string();
+
+        // The repository cannot allow itself to run commands
+        let mut config = YekConfig {
+            input_paths: vec![input.clone()],
+            ..YekConfig::default()
+        };
+        config
+            .apply_layers(false, None, &HashSet::new(), Vec::new())
+            .unwrap();
+        assert!(!config.allow_exec);
+        assert_eq!(config.stages.len(), 1);
+        assert!(matches!(config.stages[0], StageConfig::RegexReplace { .. }));
+        let profiles = serde_json::to_string(&config.profiles).unwrap();
+        assert!(!profiles.contains("exec"), "{}", profiles);
+
+        let mut config = YekConfig {
+            input_paths: vec![input],
+            allow_exec: true,
+            ..YekConfig::default()
+        };
+        config
+            .apply_layers(
+                false,
+                None,
+                &HashSet::from(["allow_exec".to_string()]),
+                Vec::new(),
+            )
+            .unwrap();
+        assert_eq!(config.stages.len(), 2);
+        assert!(matches!(config.stages[0], StageConfig::Exec { .. }));
+    }
+
+    #[test]
+    fn test_cli_allow_exec() {
+        let temp_dir = tempdir().unwrap();
+        let config_home = temp_dir.path().join("config");
+        let repo = temp_dir.path().join("repo");
+        git2::Repository::init(&repo).unwrap();
+        write(&repo, "yek.yaml", EXEC_CONFIG);
+        write(&repo, "main.rs", "fn main() {}\n");
+
+        let output = yek(&repo, &config_home, &["."]);
+        assert!(output.status.success(), "{:?}", output);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("fn start() {}"), "{}", stdout);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            stderr.contains("skipping exec stage 'tr a-z A-Z'"),
+            "{}",
+            stderr
+        );
+
+        let output = yek(&repo, &config_home, &[".", "--allow-exec"]);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("FN MAIN() {}"), "{}", stdout);
+
+        // Or allowed once and for all in the user config
+        write(&config_home, "yek/config.yaml", "allow_exec: true\n");
+        let output = yek(&repo, &config_home, &["."]);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("FN MAIN() {}"), "{}", stdout);
+    }
+}
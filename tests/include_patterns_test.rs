@@ -0,0 +1,190 @@
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use yek::{config::YekConfig, serialize_repo_with_report, Yek};
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod include_patterns_tests {
+    use super::*;
+
+    fn write_files(root: &Path, files: &[&str]) {
+        for path in files {
+            let full = root.join(path);
+            fs::create_dir_all(full.parent().unwrap()).unwrap();
+            fs::write(full, format!("// {path}\n")).unwrap();
+        }
+    }
+
+    fn included_paths(config: &YekConfig) -> Vec<String> {
+        serialize_repo_with_report(config)
+            .unwrap()
+            .files
+            .into_iter()
+            .map(|f| f.rel_path)
+            .collect()
+    }
+
+    fn config_for(inputs: &[&Path], include: &[&str]) -> YekConfig {
+        let mut config = YekConfig::default();
+        config.input_paths = inputs
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        config.include_patterns = include.iter().map(|p| p.to_string()).collect();
+        config.stream = true;
+        config
+    }
+
+    #[test]
+    fn test_include_patterns_restrict_discovery() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write_files(
+            root,
+            &[
+                "Cargo.toml",
+                "README.md",
+                "src/main.rs",
+                "src/util/mod.rs",
+                "scripts/build.sh",
+                "tests/it.rs",
+            ],
+        );
+
+        let config = config_for(&[root], &["src/**/*.rs", "*.toml"]);
+        assert_eq!(
+            included_paths(&config),
+            vec!["Cargo.toml", "src/main.rs", "src/util/mod.rs"]
+        );
+    }
+
+    #[test]
+    fn test_include_patterns_apply_to_each_input() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write_files(
+            root,
+            &[
+                "crates/a/src/lib.rs",
+                "crates/a/notes.md",
+                "crates/b/src/main.rs",
+                "crates/b/build.rs",
+            ],
+        );
+
+        let config = config_for(
+            &[&root.join("crates/a"), &root.join("crates/b")],
+            &["src/**/*.rs"],
+        );
+        assert_eq!(
+            included_paths(&config),
+            vec!["a/src/lib.rs", "b/src/main.rs"]
+        );
+    }
+
+    #[test]
+    fn test_include_patterns_compose_with_ignores_and_negations() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write_files(
+            root,
+            &[
+                "lib.rs",
+                "gen/api.rs",
+                "tests/it.rs",
+                "benches/b.rs",
+                "notes.txt",
+            ],
+        );
+        fs::write(root.join(".gitignore"), "gen/\n").unwrap();
+
+        let mut config = config_for(&[root], &["*.rs", "!tests/"]);
+        config.ignore_patterns = vec!["benches/**".to_string()];
+        assert_eq!(included_paths(&config), vec!["lib.rs"]);
+    }
+
+    #[test]
+    fn test_explicit_files_bypass_include_patterns() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write_files(root, &["src/main.rs", "notes.txt"]);
+
+        let config = config_for(&[&root.join("src"), &root.join("notes.txt")], &["*.rs"]);
+        assert_eq!(included_paths(&config), vec!["notes.txt", "src/main.rs"]);
+    }
+
+    #[test]
+    fn test_include_patterns_from_builder() {
+        let temp_dir = tempdir().unwrap();
+        write_files(temp_dir.path(), &["a.rs", "b.py"]);
+
+        let result = Yek::builder()
+            .path(temp_dir.path())
+            .include("*.py")
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        let paths: Vec<_> = result
+            .selected
+            .iter()
+            .map(|f| f.rel_path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["b.py"]);
+    }
+
+    #[test]
+    fn test_invalid_include_pattern_fails_validation() {
+        let mut config = config_for(&[], &["src/[.rs"]);
+        config.stream = true;
+        let err = config.validate().unwrap_err().to_string();
+        assert!(
+            err.starts_with("include_patterns: Invalid pattern"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_cli_include_flag() {
+        let temp_dir = tempdir().unwrap();
+        write_files(temp_dir.path(), &["keep.rs", "drop.md", "sub/also.rs"]);
+
+        let output = Command::cargo_bin("yek")
+            .unwrap()
+            .arg(temp_dir.path())
+            .args(["--include", "*.rs"])
+            .env("TERM", "dumb")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(">>>> keep.rs"), "{}", stdout);
+        assert!(stdout.contains(">>>> sub/also.rs"), "{}", stdout);
+        assert!(!stdout.contains("drop.md"), "{}", stdout);
+    }
+
+    #[test]
+    fn test_cli_include_adds_to_config_include_patterns() {
+        let temp_dir = tempdir().unwrap();
+        write_files(temp_dir.path(), &["a.rs", "b.md", "c.py"]);
+        fs::write(
+            temp_dir.path().join("yek.yaml"),
+            "include_patterns:\n  - \"*.md\"\n",
+        )
+        .unwrap();
+
+        let output = Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .args(["--include", "*.py"])
+            .env("TERM", "dumb")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(">>>> b.md"), "{}", stdout);
+        assert!(stdout.contains(">>>> c.py"), "{}", stdout);
+        assert!(!stdout.contains("a.rs"), "{}", stdout);
+    }
+}
@@ -0,0 +1,187 @@
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use yek::{
+    config::{ConfigFormat, YekConfig},
+    init::{analyze, run, InitArgs, SkippedDir},
+    priority::PriorityRule,
+};
+
+#[cfg(test)]
+mod init_tests {
+    use super::*;
+
+    const GENERATED: &str = "// Code generated by protoc-gen-go. DO NOT EDIT.\npackage api\n";
+
+    fn write(root: &Path, rel_path: &str, content: &str) {
+        let path = root.join(rel_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// A repository with most of its source in `src/` and a test suite as large
+    fn sample_repo(root: &Path) {
+        let code = "fn main() {}\n".repeat(50);
+        write(root, "src/main.rs", &code);
+        write(root, "src/lib.rs", &code);
+        write(root, "tests/a_test.rs", &code.repeat(3));
+        write(root, "scripts/run.sh", "echo hi\n");
+        write(root, "README.md", "# Sample\n");
+        write(root, "third_party/zlib/zlib.c", "int x;\n");
+        write(root, "vendor/dep.go", "package dep\n");
+        for name in ["a.pb.go", "b.pb.go", "c.pb.go"] {
+            write(root, &format!("api/proto/{}", name), GENERATED);
+        }
+        write(root, ".gitignore", "scratch/\n");
+        write(root, "scratch/notes.rs", &code.repeat(10));
+    }
+
+    fn args(path: &Path, format: ConfigFormat, force: bool) -> InitArgs {
+        InitArgs {
+            format,
+            force,
+            path: path.to_path_buf(),
+        }
+    }
+
+    #[test]
+    fn test_analyze() {
+        let temp_dir = tempdir().unwrap();
+        sample_repo(temp_dir.path());
+
+        let analysis = analyze(temp_dir.path()).unwrap();
+        assert!(analysis.gitignore);
+        assert_eq!(
+            analysis.skipped_dirs,
+            vec![
+                SkippedDir {
+                    path: "api/proto".to_string(),
+                    reason: "3 of 3 files generated".to_string(),
+                },
+                SkippedDir {
+                    path: "third_party".to_string(),
+                    reason: "vendored code".to_string(),
+                },
+            ]
+        );
+        // Ignored files and suggested directories are left out of the mix
+        assert_eq!(analysis.files, 5);
+        assert_eq!(analysis.source_dirs, vec!["src"]);
+        assert_eq!(analysis.largest_dirs[0].0, "tests");
+        assert!(!analysis
+            .largest_dirs
+            .iter()
+            .any(|(dir, _)| dir == "scratch"));
+
+        // Tests are over a third of the repository, so their weight is halved
+        let weights = analysis.category_weights();
+        assert_eq!(weights.test, 5);
+        assert_eq!(weights.source, 20);
+        assert_eq!(weights.documentation, 15);
+    }
+
+    #[test]
+    fn test_rendered_configs_load() {
+        let temp_dir = tempdir().unwrap();
+        sample_repo(temp_dir.path());
+        let analysis = analyze(temp_dir.path()).unwrap();
+
+        for (format, ext) in [
+            (ConfigFormat::Yaml, "yaml"),
+            (ConfigFormat::Toml, "toml"),
+            (ConfigFormat::Json, "json"),
+        ] {
+            let rendered = analysis.render(&format);
+            if ext != "json" {
+                assert!(rendered.starts_with("# Generated by `yek init`"));
+                assert!(rendered.contains("# vendored code"), "{}", rendered);
+            }
+            let file = temp_dir.path().join(format!("config.{}", ext));
+            fs::write(&file, &rendered).unwrap();
+
+            let config = YekConfig::load_config_file(&file).unwrap();
+            assert_eq!(
+                config.ignore_patterns,
+                vec!["api/proto/**", "third_party/**"],
+                "{}",
+                rendered
+            );
+            assert_eq!(
+                config.priority_rules,
+                vec![PriorityRule {
+                    pattern: "^src/".to_string(),
+                    score: 100
+                }]
+            );
+            assert_eq!(config.category_weights.unwrap().test, 5);
+        }
+    }
+
+    #[test]
+    fn test_empty_repository() {
+        let temp_dir = tempdir().unwrap();
+        let analysis = analyze(temp_dir.path()).unwrap();
+        let rendered = analysis.render(&ConfigFormat::Toml);
+        assert!(rendered.contains("nothing to analyze"));
+        assert!(rendered.contains("No .gitignore found"));
+
+        let file = temp_dir.path().join("config.toml");
+        fs::write(&file, &rendered).unwrap();
+        let config = YekConfig::load_config_file(&file).unwrap();
+        assert!(config.ignore_patterns.is_empty());
+        assert!(config.priority_rules.is_empty());
+    }
+
+    #[test]
+    fn test_run_refuses_to_overwrite() {
+        let temp_dir = tempdir().unwrap();
+        sample_repo(temp_dir.path());
+        write(temp_dir.path(), "yek.toml", "tokens = \"10k\"\n");
+
+        let err = run(&args(temp_dir.path(), ConfigFormat::Toml, false)).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("already exists (use --force to overwrite)"));
+        // Another format counts too
+        assert!(run(&args(temp_dir.path(), ConfigFormat::Yaml, false)).is_err());
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("yek.toml")).unwrap(),
+            "tokens = \"10k\"\n"
+        );
+
+        let written = run(&args(temp_dir.path(), ConfigFormat::Toml, true)).unwrap();
+        assert_eq!(written, temp_dir.path().join("yek.toml"));
+        assert!(fs::read_to_string(written)
+            .unwrap()
+            .contains("[category_weights]"));
+    }
+
+    #[test]
+    fn test_cli_init() {
+        let temp_dir = tempdir().unwrap();
+        sample_repo(temp_dir.path());
+
+        let output = Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .args(["init", "--format", "json"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "./yek.json\n");
+        let config: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(temp_dir.path().join("yek.json")).unwrap())
+                .unwrap();
+        assert_eq!(config["priority_rules"][0]["pattern"], "^src/");
+
+        let output = Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .args(["init"])
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(!temp_dir.path().join("yek.yaml").exists());
+    }
+}
@@ -451,3 +451,43 @@ fn test_main_help_includes_update_flag() {
         .stdout(predicate::str::contains("--update"))
         .stdout(predicate::str::contains("Update yek to the latest version"));
 }
+
+#[test]
+fn test_main_help_lists_subcommands() {
+    use predicates::prelude::*;
+
+    Command::cargo_bin("yek")
+        .expect("Binary 'yek' not found")
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Commands:"))
+        .stdout(predicate::str::contains("serve"))
+        .stdout(predicate::str::contains("init"));
+}
+
+#[test]
+fn test_main_subcommand_names_as_input_paths() {
+    use std::fs;
+    use tempfile::tempdir;
+
+    let temp_dir = tempdir().unwrap();
+    for dir in ["src", "serve"] {
+        fs::create_dir(temp_dir.path().join(dir)).unwrap();
+        fs::write(temp_dir.path().join(dir).join("file.txt"), dir).unwrap();
+    }
+
+    // After another input path, or after `--`, a subcommand name is a path
+    for args in [&["src", "serve"][..], &["--", "serve"]] {
+        let output = Command::cargo_bin("yek")
+            .expect("Binary 'yek' not found")
+            .current_dir(temp_dir.path())
+            .args(args)
+            .env("TERM", "dumb")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("file.txt\nserve"), "{}", stdout);
+    }
+}
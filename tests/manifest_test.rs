@@ -0,0 +1,196 @@
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use yek::{
+    config::YekConfig,
+    manifest::{manifest_path_for, Manifest},
+    models::ExclusionReason,
+    serialize_repo_with_report,
+};
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod manifest_tests {
+    use super::*;
+
+    fn config_for(dir: &Path) -> YekConfig {
+        let mut config = YekConfig::default();
+        config.input_paths = vec![dir.to_string_lossy().to_string()];
+        config.ignore_patterns = vec!["*.log".to_string()];
+        config
+    }
+
+    #[test]
+    fn test_report_entries_point_into_output() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "alpha\nbeta\n").unwrap();
+        fs::write(temp_dir.path().join("b.txt"), "gamma").unwrap();
+
+        let config = config_for(temp_dir.path());
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        assert_eq!(report.entries.len(), 2);
+        let lines: Vec<&str> = report.output.split('\n').collect();
+        for entry in &report.entries {
+            let text = &report.output[entry.byte_start..entry.byte_end];
+            assert!(text.starts_with(&format!(">>>> {}", entry.rel_path)));
+            assert_eq!(
+                lines[entry.line_start - 1],
+                format!(">>>> {}", entry.rel_path)
+            );
+        }
+
+        let a = report
+            .entries
+            .iter()
+            .find(|e| e.rel_path == "a.txt")
+            .unwrap();
+        assert_eq!(a.line_end - a.line_start, 2);
+        assert_eq!(lines[a.line_end - 1], "beta");
+    }
+
+    #[test]
+    fn test_report_entries_with_tree_header() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "alpha").unwrap();
+
+        let mut config = config_for(temp_dir.path());
+        config.tree_header = true;
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        let entry = &report.entries[0];
+        assert!(entry.byte_start > 0);
+        assert_eq!(&report.output[entry.byte_start..], ">>>> a.txt\nalpha");
+        assert_eq!(
+            report.output.split('\n').nth(entry.line_start - 1),
+            Some(">>>> a.txt")
+        );
+    }
+
+    #[test]
+    fn test_json_output_is_unchanged_by_entry_tracking() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "alpha\n\"quoted\"").unwrap();
+        fs::write(temp_dir.path().join("b.txt"), "beta").unwrap();
+
+        let mut config = config_for(temp_dir.path());
+        config.json = true;
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        let parsed: serde_json::Value = serde_json::from_str(&report.output).unwrap();
+        assert_eq!(
+            report.output,
+            serde_json::to_string_pretty(&parsed).unwrap()
+        );
+        for entry in &report.entries {
+            let object: serde_json::Value =
+                serde_json::from_str(&report.output[entry.byte_start..entry.byte_end]).unwrap();
+            assert_eq!(object["filename"], entry.rel_path.as_str());
+        }
+    }
+
+    #[test]
+    fn test_report_lists_exclusions_with_reasons() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("keep.txt"), "keep").unwrap();
+        fs::write(temp_dir.path().join("debug.log"), "noise").unwrap();
+        fs::write(temp_dir.path().join("blob.xyz"), [0u8, 159, 146, 150]).unwrap();
+
+        let config = config_for(temp_dir.path());
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        let reason_for = |path: &str| {
+            report
+                .excluded
+                .iter()
+                .find(|e| e.rel_path == path)
+                .map(|e| e.reason)
+        };
+        assert_eq!(reason_for("debug.log"), Some(ExclusionReason::Ignored));
+        assert_eq!(reason_for("blob.xyz"), Some(ExclusionReason::BinaryContent));
+        assert_eq!(reason_for("keep.txt"), None);
+    }
+
+    #[test]
+    fn test_report_marks_files_over_budget() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "a".repeat(80)).unwrap();
+        fs::write(temp_dir.path().join("b.txt"), "b".repeat(80)).unwrap();
+
+        let mut config = config_for(temp_dir.path());
+        config.max_size = "100B".to_string();
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        assert_eq!(report.entries.len(), 1);
+        let over_budget: Vec<_> = report
+            .excluded
+            .iter()
+            .filter(|e| e.reason == ExclusionReason::OverBudget)
+            .collect();
+        assert_eq!(over_budget.len(), 1);
+        assert_ne!(over_budget[0].rel_path, report.entries[0].rel_path);
+    }
+
+    #[test]
+    fn test_manifest_fills_token_counts() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "hello world").unwrap();
+
+        let config = config_for(temp_dir.path());
+        let report = serialize_repo_with_report(&config).unwrap();
+        let manifest = Manifest::new(&config, &report, None).unwrap();
+
+        assert_eq!(manifest.tokenizer, yek::TOKENIZER_MODEL);
+        assert_eq!(manifest.total_bytes, report.output.len());
+        assert!(manifest.included.iter().all(|e| e.tokens.is_some()));
+        assert!(manifest.repositories.is_empty());
+    }
+
+    #[test]
+    fn test_manifest_path_for() {
+        assert_eq!(
+            manifest_path_for(Path::new("/tmp/out/yek-output-1234.txt")),
+            Path::new("/tmp/out/yek-output-1234.manifest.json")
+        );
+        assert_eq!(
+            manifest_path_for(Path::new("bundle.json")),
+            Path::new("bundle.manifest.json")
+        );
+    }
+
+    #[test]
+    fn test_cli_writes_manifest_next_to_output() {
+        let repo = tempdir().unwrap();
+        let output_dir = tempdir().unwrap();
+        std::process::Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(repo.path())
+            .output()
+            .unwrap();
+        fs::write(repo.path().join("main.rs"), "fn main() {}\n").unwrap();
+
+        let assert = Command::cargo_bin("yek")
+            .unwrap()
+            .env("FORCE_TTY", "1")
+            .arg("--manifest")
+            .arg("--output-dir")
+            .arg(output_dir.path())
+            .arg(repo.path())
+            .assert()
+            .success();
+
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let output_path = Path::new(stdout.trim());
+        let manifest_path = manifest_path_for(output_path);
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&manifest_path).unwrap()).unwrap();
+
+        assert_eq!(manifest["yek_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(manifest["output_file"], stdout.trim());
+        assert_eq!(manifest["included"][0]["rel_path"], "main.rs");
+        assert_eq!(manifest["repositories"].as_array().unwrap().len(), 1);
+        assert!(manifest["repositories"][0]["head"].is_null());
+        assert!(manifest["config"]["manifest"].as_bool().unwrap());
+    }
+}
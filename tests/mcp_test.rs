@@ -0,0 +1,231 @@
+use assert_cmd::Command;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use yek::{config::SubcommandConfigArgs, mcp::McpServer};
+
+#[cfg(test)]
+mod mcp_tests {
+    use super::*;
+
+    fn setup_repo(dir: &Path) {
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {\n    run();\n}\n").unwrap();
+        fs::write(dir.join("src/util.rs"), "pub fn helper() {}\n").unwrap();
+        fs::write(dir.join("README.md"), "# Project\n").unwrap();
+    }
+
+    fn server_for(dir: &Path) -> McpServer {
+        let args = SubcommandConfigArgs {
+            no_config: true,
+            input_paths: vec![dir.to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        McpServer::new(args.load().unwrap())
+    }
+
+    fn call_tool(server: &McpServer, name: &str, arguments: Value) -> Value {
+        let response = server
+            .handle_message(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": { "name": name, "arguments": arguments },
+            }))
+            .unwrap();
+        response["result"].clone()
+    }
+
+    fn tool_text(result: &Value) -> &str {
+        result["content"][0]["text"].as_str().unwrap()
+    }
+
+    #[test]
+    fn test_initialize_and_list_tools() {
+        let temp_dir = tempdir().unwrap();
+        let server = server_for(temp_dir.path());
+
+        let response = server
+            .handle_message(
+                &json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+            )
+            .unwrap();
+        assert_eq!(
+            response["result"]["protocolVersion"],
+            yek::mcp::MCP_PROTOCOL_VERSION
+        );
+        assert_eq!(response["result"]["serverInfo"]["name"], "yek");
+
+        let response = server
+            .handle_message(&json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}))
+            .unwrap();
+        let names: Vec<&str> = response["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["serialize", "list_files", "read_file", "tree"]);
+    }
+
+    #[test]
+    fn test_notifications_and_unknown_methods() {
+        let temp_dir = tempdir().unwrap();
+        let server = server_for(temp_dir.path());
+
+        assert!(server
+            .handle_message(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+            .is_none());
+
+        let response = server
+            .handle_message(&json!({"jsonrpc": "2.0", "id": 7, "method": "resources/list"}))
+            .unwrap();
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["error"]["code"], -32601);
+
+        let response = server
+            .handle_message(&json!({
+                "jsonrpc": "2.0",
+                "id": 8,
+                "method": "tools/call",
+                "params": { "name": "nope" },
+            }))
+            .unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+    }
+
+    #[test]
+    fn test_serialize_tool_with_query_and_tokens() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+        let server = server_for(temp_dir.path());
+
+        let result = call_tool(&server, "serialize", json!({}));
+        assert_eq!(result["isError"], false);
+        assert!(tool_text(&result).contains(">>>> src/util.rs"));
+        assert!(tool_text(&result).contains(">>>> README.md"));
+
+        let result = call_tool(&server, "serialize", json!({ "query": "HELPER" }));
+        assert_eq!(tool_text(&result), ">>>> src/util.rs\npub fn helper() {}\n");
+
+        let result = call_tool(&server, "serialize", json!({ "tokens": "12" }));
+        assert!(tool_text(&result).matches(">>>> ").count() < 3);
+    }
+
+    #[test]
+    fn test_list_files_and_tree_tools() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+        let server = server_for(temp_dir.path());
+
+        let result = call_tool(&server, "list_files", json!({}));
+        assert_eq!(tool_text(&result), "README.md\nsrc/main.rs\nsrc/util.rs");
+
+        let src = temp_dir.path().join("src").to_string_lossy().to_string();
+        let result = call_tool(&server, "tree", json!({ "paths": [src] }));
+        let tree = tool_text(&result);
+        assert!(tree.contains("main.rs"));
+        assert!(!tree.contains("README.md"));
+    }
+
+    #[test]
+    fn test_read_file_stays_inside_served_paths() {
+        let temp_dir = tempdir().unwrap();
+        let outside = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+        fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+        let server = server_for(temp_dir.path());
+
+        let main_rs = temp_dir.path().join("src/main.rs");
+        let result = call_tool(
+            &server,
+            "read_file",
+            json!({ "path": main_rs.to_string_lossy() }),
+        );
+        assert_eq!(result["isError"], false);
+        assert_eq!(tool_text(&result), "fn main() {\n    run();\n}\n");
+
+        let secret = outside.path().join("secret.txt");
+        let result = call_tool(
+            &server,
+            "read_file",
+            json!({ "path": secret.to_string_lossy() }),
+        );
+        assert_eq!(result["isError"], true);
+        assert!(tool_text(&result).contains("outside of the served paths"));
+
+        let result = call_tool(
+            &server,
+            "serialize",
+            json!({ "paths": [outside.path().to_string_lossy()] }),
+        );
+        assert_eq!(result["isError"], true);
+    }
+
+    #[test]
+    fn test_paths_cannot_escape_through_patterns() {
+        let temp_dir = tempdir().unwrap();
+        let served = temp_dir.path().join("served");
+        setup_repo(&served);
+        fs::write(temp_dir.path().join("secret.txt"), "secret").unwrap();
+        std::os::unix::fs::symlink(temp_dir.path(), served.join("parent")).unwrap();
+        let server = server_for(&served);
+
+        // The fixed part before the wildcard is inside the served path
+        for (pattern, error) in [
+            (
+                format!("{}/*/../../secret.txt", served.display()),
+                "'..' is not allowed",
+            ),
+            (
+                format!("{}/par*", served.display()),
+                "outside of the served paths",
+            ),
+        ] {
+            let result = call_tool(&server, "serialize", json!({ "paths": [pattern] }));
+            assert_eq!(result["isError"], true, "{}", pattern);
+            assert!(tool_text(&result).contains(error), "{}", tool_text(&result));
+        }
+
+        let pattern = format!("{}/src/*.rs", served.display());
+        let result = call_tool(&server, "list_files", json!({ "paths": [pattern] }));
+        assert_eq!(result["isError"], false);
+        assert_eq!(tool_text(&result), "main.rs\nutil.rs");
+    }
+
+    #[test]
+    fn test_stdio_session() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+
+        let requests = [
+            json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"protocolVersion": "2025-03-26"}}),
+            json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {"name": "read_file", "arguments": {"path": "README.md"}}}),
+        ]
+        .iter()
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+            + "\nnot json\n";
+
+        let assert = Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .args(["mcp", "--no-config"])
+            .write_stdin(requests)
+            .assert()
+            .success();
+
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let responses: Vec<Value> = stdout
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["result"]["protocolVersion"], "2025-03-26");
+        assert_eq!(responses[1]["result"]["content"][0]["text"], "# Project\n");
+        assert_eq!(responses[2]["error"]["code"], -32700);
+    }
+}
@@ -0,0 +1,203 @@
+use serde_json::json;
+use std::fs;
+use tempfile::tempdir;
+use yek::{
+    category::{categorize_file, FileCategory},
+    config::YekConfig,
+    models::NotebookOutputs,
+    notebook::{is_notebook, render_notebook},
+    serialize_repo_with_report,
+};
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod notebook_tests {
+    use super::*;
+
+    fn sample_notebook() -> String {
+        json!({
+            "nbformat": 4,
+            "nbformat_minor": 5,
+            "metadata": {"kernelspec": {"language": "python"}},
+            "cells": [
+                {
+                    "cell_type": "markdown",
+                    "metadata": {},
+                    "source": ["# Analysis\n", "Load the data."]
+                },
+                {
+                    "cell_type": "code",
+                    "metadata": {},
+                    "execution_count": 1,
+                    "source": "import pandas as pd\ndf = pd.read_csv('data.csv')\ndf.shape",
+                    "outputs": [
+                        {"output_type": "stream", "name": "stdout", "text": ["loaded\n"]},
+                        {
+                            "output_type": "execute_result",
+                            "execution_count": 1,
+                            "metadata": {},
+                            "data": {
+                                "text/plain": ["(3, 2)"],
+                                "text/html": "<b>(3, 2)</b>"
+                            }
+                        }
+                    ]
+                },
+                {
+                    "cell_type": "code",
+                    "metadata": {},
+                    "execution_count": 2,
+                    "source": ["df.plot()"],
+                    "outputs": [
+                        {
+                            "output_type": "display_data",
+                            "metadata": {},
+                            "data": {
+                                "image/png": "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNk",
+                                "text/plain": ["<Figure size 640x480>"]
+                            }
+                        },
+                        {
+                            "output_type": "error",
+                            "ename": "KeyError",
+                            "evalue": "'b'",
+                            "traceback": ["\u{1b}[0;31mKeyError\u{1b}[0m"]
+                        }
+                    ]
+                }
+            ]
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_render_notebook_summarizes_outputs() {
+        let rendered = render_notebook(&sample_notebook(), NotebookOutputs::Summarize).unwrap();
+        assert_eq!(
+            rendered,
+            "# %% [markdown]\n\
+             # # Analysis\n\
+             # Load the data.\n\
+             \n\
+             # %%\n\
+             import pandas as pd\n\
+             df = pd.read_csv('data.csv')\n\
+             df.shape\n\
+             # Output:\n\
+             # loaded\n\
+             # (3, 2)\n\
+             \n\
+             # %%\n\
+             df.plot()\n\
+             # Output:\n\
+             # <Figure size 640x480>\n\
+             # [image/png output omitted]\n\
+             # KeyError: 'b'\n"
+        );
+    }
+
+    #[test]
+    fn test_render_notebook_drops_outputs() {
+        let rendered = render_notebook(&sample_notebook(), NotebookOutputs::Drop).unwrap();
+        assert!(!rendered.contains("Output"));
+        assert!(!rendered.contains("iVBOR"));
+        assert!(rendered.contains("# %%\ndf.plot()\n"));
+    }
+
+    #[test]
+    fn test_markdown_cells_use_the_kernel_comment() {
+        let notebook = json!({
+            "nbformat": 4,
+            "metadata": {"language_info": {"name": "javascript"}},
+            "cells": [
+                {"cell_type": "markdown", "source": ["## Setup\n", "\n", "Run first."]},
+                {
+                    "cell_type": "code",
+                    "source": "console.log(1)",
+                    "outputs": [{"output_type": "stream", "name": "stdout", "text": "1\n"}]
+                }
+            ]
+        });
+        let rendered = render_notebook(&notebook.to_string(), NotebookOutputs::Summarize).unwrap();
+        assert_eq!(
+            rendered,
+            "// %% [markdown]\n\
+             // ## Setup\n\
+             //\n\
+             // Run first.\n\
+             \n\
+             // %%\n\
+             console.log(1)\n\
+             // Output:\n\
+             // 1\n"
+        );
+    }
+
+    #[test]
+    fn test_long_outputs_are_truncated() {
+        let lines: Vec<String> = (0..50).map(|i| format!("line {i}\n")).collect();
+        let notebook = json!({
+            "nbformat": 4,
+            "cells": [{
+                "cell_type": "code",
+                "source": "for i in range(50): print(f'line {i}')",
+                "outputs": [{"output_type": "stream", "name": "stdout", "text": lines}]
+            }]
+        });
+        let rendered = render_notebook(&notebook.to_string(), NotebookOutputs::Summarize).unwrap();
+        assert!(rendered.contains("# line 19\n# ... 30 more lines\n"));
+        assert!(!rendered.contains("line 20"));
+    }
+
+    #[test]
+    fn test_invalid_notebooks_are_rejected() {
+        assert!(render_notebook("not json", NotebookOutputs::Summarize).is_err());
+        let v3 = json!({"nbformat": 3, "worksheets": []}).to_string();
+        assert!(render_notebook(&v3, NotebookOutputs::Summarize).is_err());
+    }
+
+    #[test]
+    fn test_notebooks_are_source() {
+        assert!(is_notebook("analysis/Explore.IPYNB"));
+        assert!(!is_notebook("analysis/explore.py"));
+        assert_eq!(
+            categorize_file("analysis/explore.ipynb"),
+            FileCategory::Source
+        );
+    }
+
+    #[test]
+    fn test_notebooks_are_converted_in_pipeline() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("explore.ipynb"), sample_notebook()).unwrap();
+        fs::write(temp_dir.path().join("broken.ipynb"), "{ not json").unwrap();
+
+        let mut config = YekConfig::default();
+        config.input_paths = vec![temp_dir.path().to_string_lossy().to_string()];
+        config.stream = true;
+        config.notebook_outputs = NotebookOutputs::Drop;
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        let notebook = report
+            .files
+            .iter()
+            .find(|f| f.rel_path == "explore.ipynb")
+            .unwrap();
+        assert!(notebook
+            .content
+            .starts_with("# %% [markdown]\n# # Analysis\n"));
+        assert_eq!(notebook.category, FileCategory::Source);
+        assert_eq!(notebook.size_bytes, notebook.content.len());
+        assert!(report
+            .output
+            .contains(">>>> explore.ipynb\n# %% [markdown]"));
+
+        // Notebooks that cannot be parsed are kept as they are
+        let broken = report
+            .files
+            .iter()
+            .find(|f| f.rel_path == "broken.ipynb")
+            .unwrap();
+        assert_eq!(broken.content, "{ not json");
+    }
+}
@@ -5,11 +5,12 @@ use std::sync::Arc;
 use tempfile::tempdir;
 use yek::models::{InputConfig, OutputConfig, ProcessedFile, ProcessingConfig, RepositoryInfo};
 use yek::pipeline::{
-    ContentFilteringStage, FileDiscoveryStage, OutputFormattingStage, ProcessingContext,
-    ProcessingPipeline, ProcessingPipelineBuilder, ProcessingStage,
+    CategoryWeightingStage, ContentFilteringStage, FileDiscoveryStage, OutputFormattingStage,
+    ProcessingContext, ProcessingPipeline, ProcessingPipelineBuilder, ProcessingStage,
 };
 use yek::priority::PriorityRule;
 use yek::repository::RealFileSystem;
+use yek::{config::YekConfig, models::ExclusionReason, serialize_repo_with_stages};
 
 #[cfg(test)]
 mod pipeline_tests {
@@ -43,6 +44,18 @@ mod pipeline_tests {
         InputConfig {
             input_paths: paths,
             ignore_patterns: Vec::new(),
+            include_patterns: Vec::new(),
+            max_file_size: None,
+            max_file_lines: None,
+            include_generated: false,
+            follow_symlinks: Default::default(),
+            legacy_encoding: None,
+            strip_bom: false,
+            normalize_newlines: false,
+            summarize_patterns: Vec::new(),
+            summary_rows: 10,
+            notebook_outputs: Default::default(),
+            submodules: Default::default(),
             binary_extensions: HashSet::new(),
             max_git_depth: 100,
             git_boost_max: Some(100),
@@ -292,4 +305,129 @@ mod pipeline_tests {
         // Should not panic, even if no files are found
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_file_discovery_stage_uses_common_base_for_multiple_inputs() {
+        let temp = tempdir().unwrap();
+        let base_dir = temp.path();
+        fs::create_dir_all(base_dir.join("app/src")).unwrap();
+        fs::create_dir_all(base_dir.join("lib/src")).unwrap();
+        fs::write(base_dir.join("app/src/main.rs"), "fn main() {}").unwrap();
+        fs::write(base_dir.join("lib/src/main.rs"), "pub fn lib() {}").unwrap();
+        fs::write(
+            base_dir.join("app/logo.png"),
+            b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR",
+        )
+        .unwrap();
+
+        let input_config = input_config_with_paths(vec![
+            base_dir.join("app").to_string_lossy().to_string(),
+            base_dir.join("lib").to_string_lossy().to_string(),
+        ]);
+
+        let context = create_test_context_with_configs(
+            input_config,
+            OutputConfig::default(),
+            ProcessingConfig::default(),
+            repository_info_for(base_dir),
+        );
+        let files = FileDiscoveryStage::new()
+            .process(Vec::new(), &context)
+            .unwrap();
+
+        let rel_paths: Vec<&str> = files.iter().map(|f| f.rel_path.as_str()).collect();
+        assert_eq!(rel_paths, vec!["app/src/main.rs", "lib/src/main.rs"]);
+
+        let excluded = context.get_excluded_files();
+        assert_eq!(excluded.len(), 1);
+        assert_eq!(excluded[0].rel_path, "app/logo.png");
+        assert_eq!(excluded[0].reason, ExclusionReason::BinaryContent);
+    }
+
+    #[test]
+    fn test_file_discovery_stage_binary_extensions_only_apply_to_file_arguments() {
+        let temp = tempdir().unwrap();
+        let base_dir = temp.path();
+        fs::create_dir_all(base_dir.join("web/src")).unwrap();
+        fs::write(base_dir.join("web/src/app.ts"), "export const app = 1;").unwrap();
+        fs::write(base_dir.join("clip.ts"), "export const clip = 1;").unwrap();
+
+        let mut input_config = input_config_with_paths(vec![
+            base_dir.join("web").to_string_lossy().to_string(),
+            base_dir.join("clip.ts").to_string_lossy().to_string(),
+        ]);
+        input_config.binary_extensions.insert("ts".to_string());
+
+        let context = create_test_context_with_configs(
+            input_config,
+            OutputConfig::default(),
+            ProcessingConfig::default(),
+            repository_info_for(base_dir),
+        );
+        let files = FileDiscoveryStage::new()
+            .process(Vec::new(), &context)
+            .unwrap();
+
+        // TypeScript found by walking is read, only the file argument is skipped
+        let rel_paths: Vec<&str> = files.iter().map(|f| f.rel_path.as_str()).collect();
+        assert_eq!(rel_paths, vec!["web/src/app.ts"]);
+
+        let excluded = context.get_excluded_files();
+        assert_eq!(excluded.len(), 1);
+        assert_eq!(excluded[0].rel_path, "clip.ts");
+        assert_eq!(excluded[0].reason, ExclusionReason::BinaryExtension);
+    }
+
+    #[test]
+    fn test_category_weighting_stage_adds_offsets() {
+        let context = create_baseline_context();
+        let file = ProcessedFile::new("src/lib.rs".to_string(), "".to_string(), 3, 0);
+        let files = CategoryWeightingStage
+            .process(vec![file], &context)
+            .unwrap();
+        let expected = 3 + context
+            .processing_config
+            .category_weights
+            .get_offset(files[0].category);
+        assert_eq!(files[0].priority, expected);
+    }
+
+    struct UppercaseStage;
+
+    impl ProcessingStage for UppercaseStage {
+        fn process(
+            &self,
+            files: Vec<ProcessedFile>,
+            _context: &ProcessingContext,
+        ) -> anyhow::Result<Vec<ProcessedFile>> {
+            Ok(files
+                .into_iter()
+                .filter(|f| !f.rel_path.ends_with(".md"))
+                .map(|mut f| {
+                    f.content = f.content.to_uppercase();
+                    f
+                })
+                .collect())
+        }
+
+        fn name(&self) -> &'static str {
+            "Uppercase"
+        }
+    }
+
+    #[test]
+    fn test_custom_stage_changes_serialized_output() {
+        let temp = tempdir().unwrap();
+        fs::write(temp.path().join("main.rs"), "fn main() {}").unwrap();
+        fs::write(temp.path().join("README.md"), "# readme").unwrap();
+
+        let mut config = YekConfig::extend_config_with_defaults(
+            vec![temp.path().to_string_lossy().to_string()],
+            ".".to_string(),
+        );
+        config.stream = true;
+
+        let report = serialize_repo_with_stages(&config, vec![Box::new(UppercaseStage)]).unwrap();
+        assert_eq!(report.output, ">>>> main.rs\nFN MAIN() {}");
+    }
 }
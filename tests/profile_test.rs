@@ -0,0 +1,175 @@
+use assert_cmd::Command;
+use serde_json::json;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use tempfile::tempdir;
+use yek::config::{SubcommandConfigArgs, YekConfig};
+
+#[cfg(test)]
+mod profile_tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+ignore_patterns:
+  - "scratch/**"
+profiles:
+  review:
+    tokens: "50k"
+    ignore_patterns:
+      - "docs/**"
+    output_template: "REVIEW FILE_PATH\nFILE_CONTENT"
+  overview:
+    tree_only: true
+"#;
+
+    fn config_with_profiles(profiles: serde_json::Value) -> YekConfig {
+        let profiles: BTreeMap<String, serde_json::Map<String, serde_json::Value>> =
+            serde_json::from_value(profiles).unwrap();
+        YekConfig {
+            profiles,
+            ..YekConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_apply_profile_overrides_options() {
+        let mut config = config_with_profiles(json!({
+            "review": {"tokens": "50k", "ignore_patterns": ["docs/**"], "line_numbers": true}
+        }));
+        config.ignore_patterns = vec!["scratch/**".to_string()];
+        let file = json!({"ignore_patterns": ["scratch/**"]});
+
+        config
+            .apply_profile("review", &file, &HashSet::new())
+            .unwrap();
+        assert_eq!(config.tokens, "50k");
+        assert!(config.line_numbers);
+        assert_eq!(config.ignore_patterns, vec!["docs/**"]);
+        assert_eq!(config.profile.as_deref(), Some("review"));
+        // The profiles stay available
+        assert!(config.profiles.contains_key("review"));
+    }
+
+    #[test]
+    fn test_command_line_options_win_over_profile() {
+        let mut config = config_with_profiles(json!({
+            "review": {"tokens": "50k", "ignore_patterns": ["docs/**"]}
+        }));
+        // Set on the command line: tokens, plus one extra ignore pattern
+        config.tokens = "10k".to_string();
+        config.ignore_patterns = vec!["scratch/**".to_string(), "tmp/**".to_string()];
+        let file = json!({"ignore_patterns": ["scratch/**"]});
+        let cli_keys = HashSet::from(["tokens".to_string(), "ignore_patterns".to_string()]);
+
+        config.apply_profile("review", &file, &cli_keys).unwrap();
+        assert_eq!(config.tokens, "10k");
+        assert_eq!(config.ignore_patterns, vec!["docs/**", "tmp/**"]);
+    }
+
+    #[test]
+    fn test_unknown_profile() {
+        let mut config = config_with_profiles(json!({"review": {}, "overview": {}}));
+        let err = config
+            .apply_profile("tests", &json!({}), &HashSet::new())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "profile: Unknown profile 'tests' (defined: overview, review)"
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_profile_keys() {
+        let config = config_with_profiles(json!({"review": {"tokenz": "50k"}}));
+        let err = config.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "profiles: Unknown option 'tokenz' in profile 'review'"
+        );
+
+        let config = config_with_profiles(json!({"review": {"profiles": {}}}));
+        assert!(config.validate().is_err());
+
+        let config = config_with_profiles(json!({"review": {"line_numbers": "yes please"}}));
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().starts_with("profiles.review:"), "{}", err);
+
+        let config = config_with_profiles(json!({"review": {"tokens": "50k"}}));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_subcommand_config_with_profile() {
+        let temp_dir = tempdir().unwrap();
+        let config_file = temp_dir.path().join("yek.yaml");
+        fs::write(&config_file, CONFIG).unwrap();
+
+        let args = SubcommandConfigArgs {
+            config_file: Some(config_file),
+            profile: Some("review".to_string()),
+            input_paths: vec![temp_dir.path().to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        let config = args.load().unwrap();
+        assert_eq!(config.tokens, "50k");
+        assert!(config.token_mode);
+        assert!(config.ignore_patterns.contains(&"docs/**".to_string()));
+        assert!(!config.ignore_patterns.contains(&"scratch/**".to_string()));
+    }
+
+    #[test]
+    fn test_cli_profile() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("yek.yaml"), CONFIG).unwrap();
+        fs::create_dir(temp_dir.path().join("docs")).unwrap();
+        fs::write(temp_dir.path().join("docs/guide.md"), "# Guide\n").unwrap();
+        fs::write(temp_dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+
+        let output = Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .args([".", "--profile", "review"])
+            .env("TERM", "dumb")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            stdout.contains("REVIEW main.rs\nfn main() {}"),
+            "{}",
+            stdout
+        );
+        assert!(!stdout.contains("guide.md"), "{}", stdout);
+
+        // Options given on the command line win over the profile
+        let output = Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .args([
+                ".",
+                "--profile",
+                "review",
+                "--output-template",
+                "CLI FILE_PATH\nFILE_CONTENT",
+            ])
+            .env("TERM", "dumb")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("CLI main.rs"), "{}", stdout);
+
+        let output = Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .args([".", "--profile", "missing"])
+            .env("TERM", "dumb")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            stderr.contains("Unknown profile 'missing' (defined: overview, review)"),
+            "{}",
+            stderr
+        );
+    }
+}
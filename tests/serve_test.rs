@@ -0,0 +1,235 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tempfile::tempdir;
+use yek::{config::SubcommandConfigArgs, serve::BundleServer};
+
+#[cfg(test)]
+mod serve_tests {
+    use super::*;
+
+    fn setup_repo(dir: &Path) {
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.join("README.md"), "# Project\n").unwrap();
+    }
+
+    fn server_for(dir: &Path) -> BundleServer {
+        let args = SubcommandConfigArgs {
+            no_config: true,
+            input_paths: vec![dir.to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        BundleServer::new(args.load().unwrap())
+    }
+
+    /// Serve `dir` on a free local port in the background, returning its address
+    fn listen_on(dir: &Path) -> SocketAddr {
+        let server = server_for(dir);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || server.serve(listener));
+        address
+    }
+
+    fn request(address: SocketAddr, request: &[u8]) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_bundle_is_cached_until_content_changes() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+        let server = server_for(temp_dir.path());
+
+        let first = server.handle("GET", "/bundle");
+        assert_eq!(first.status, 200);
+        assert_eq!(first.header("X-Yek-Cache"), Some("miss"));
+        assert!(first.body.contains(">>>> src/main.rs"));
+
+        let second = server.handle("GET", "/bundle");
+        assert_eq!(second.header("X-Yek-Cache"), Some("hit"));
+        assert_eq!(first.body, second.body);
+
+        fs::write(temp_dir.path().join("src/main.rs"), "fn main() { run() }\n").unwrap();
+        let third = server.handle("GET", "/bundle");
+        assert_eq!(third.header("X-Yek-Cache"), Some("miss"));
+        assert!(third.body.contains("run()"));
+        assert_ne!(
+            first.header("X-Yek-Checksum"),
+            third.header("X-Yek-Checksum")
+        );
+    }
+
+    #[test]
+    fn test_bundle_cache_sees_edits_keeping_size_and_mtime() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+        let server = server_for(temp_dir.path());
+        let main_rs = temp_dir.path().join("src/main.rs");
+        let modified = fs::metadata(&main_rs).unwrap().modified().unwrap();
+
+        let first = server.handle("GET", "/bundle");
+        assert_eq!(first.header("X-Yek-Cache"), Some("miss"));
+
+        // Same length, and the modification time put back
+        fs::write(&main_rs, "fn mian() {}\n").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&main_rs)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let second = server.handle("GET", "/bundle");
+        assert_eq!(second.header("X-Yek-Cache"), Some("miss"));
+        assert!(second.body.contains("fn mian()"));
+    }
+
+    #[test]
+    fn test_bundle_query_parameters() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+        let server = server_for(temp_dir.path());
+
+        let src = temp_dir
+            .path()
+            .join("src")
+            .to_string_lossy()
+            .replace('/', "%2F");
+        let response = server.handle("GET", &format!("/bundle?paths={}&format=json", src));
+        assert_eq!(response.status, 200);
+        assert_eq!(response.content_type, "application/json");
+        let parsed: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(parsed.as_array().unwrap().len(), 1);
+        assert_eq!(parsed[0]["filename"], "main.rs");
+
+        let response = server.handle("GET", "/bundle?format=xml");
+        assert_eq!(response.status, 400);
+
+        let response = server.handle("GET", "/bundle?tokens=abc");
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn test_tree_stats_and_errors() {
+        let temp_dir = tempdir().unwrap();
+        let outside = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+        let server = server_for(temp_dir.path());
+
+        let tree = server.handle("GET", "/tree");
+        assert_eq!(tree.status, 200);
+        assert!(tree.body.starts_with("Directory structure:"));
+        assert!(tree.body.contains("main.rs"));
+
+        let stats = server.handle("GET", "/stats");
+        let stats: serde_json::Value = serde_json::from_str(&stats.body).unwrap();
+        assert_eq!(stats["files"], 2);
+        assert_eq!(stats["included"], 2);
+        assert_eq!(stats["checksum"].as_str().unwrap().len(), 8);
+
+        let escape = format!("/bundle?paths={}", outside.path().display());
+        assert_eq!(server.handle("GET", &escape).status, 400);
+        assert_eq!(server.handle("GET", "/nope").status, 404);
+        assert_eq!(server.handle("POST", "/bundle").status, 405);
+    }
+
+    #[test]
+    fn test_bundle_paths_cannot_escape_through_patterns() {
+        let temp_dir = tempdir().unwrap();
+        let served = temp_dir.path().join("served");
+        setup_repo(&served);
+        fs::write(temp_dir.path().join("secret.txt"), "secret").unwrap();
+        std::os::unix::fs::symlink(temp_dir.path(), served.join("parent")).unwrap();
+        let server = server_for(&served);
+
+        for pattern in ["*/../../secret.txt", "par*"] {
+            let paths = format!("{}/{}", served.display(), pattern).replace('/', "%2F");
+            let response = server.handle("GET", &format!("/bundle?paths={}", paths));
+            assert_eq!(response.status, 400, "{}", pattern);
+            assert!(!response.body.contains(">>>>"), "{}", response.body);
+        }
+
+        let paths = format!("{}/src/*.rs", served.display()).replace('/', "%2F");
+        let response = server.handle("GET", &format!("/bundle?paths={}", paths));
+        assert_eq!(response.status, 200);
+        assert!(response.body.contains(">>>> main.rs"), "{}", response.body);
+    }
+
+    #[test]
+    fn test_serve_over_http() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+
+        let mut child = Command::new(env!("CARGO_BIN_EXE_yek"))
+            .current_dir(temp_dir.path())
+            .args(["serve", "--port", "0", "--no-config"])
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        // The first line on stderr tells us which port was picked
+        let mut stderr = BufReader::new(child.stderr.take().unwrap());
+        let mut line = String::new();
+        stderr.read_line(&mut line).unwrap();
+        let address = line.trim().rsplit("http://").next().unwrap().to_string();
+
+        let mut stream = TcpStream::connect(&address).unwrap();
+        stream
+            .write_all(b"GET /bundle?paths=src HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("X-Yek-Cache: miss\r\n"));
+        assert!(response.ends_with("\r\n\r\n>>>> main.rs\nfn main() {}\n"));
+    }
+
+    #[test]
+    fn test_serve_rejects_oversized_request_head() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+        let address = listen_on(temp_dir.path());
+
+        // Exactly the limit, without a line ending, so the server reads all of it
+        let mut oversized = b"GET /bundle?paths=".to_vec();
+        oversized.resize(8 * 1024, b'a');
+        let response = request(address, &oversized);
+        assert!(
+            response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"),
+            "{}",
+            response
+        );
+
+        let response = request(address, b"GET /stats HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    }
+
+    #[test]
+    fn test_serve_handles_more_clients_than_workers() {
+        let temp_dir = tempdir().unwrap();
+        setup_repo(temp_dir.path());
+        let address = listen_on(temp_dir.path());
+
+        let clients: Vec<_> = (0..40)
+            .map(|_| {
+                std::thread::spawn(move || {
+                    request(address, b"GET /tree HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                })
+            })
+            .collect();
+        for client in clients {
+            let response = client.join().unwrap();
+            assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use yek::{config::YekConfig, serialize_repo_with_report, stages::StageConfig};
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod stages_tests {
+    use super::*;
+
+    fn config_for(dir: &Path, stages: Vec<StageConfig>) -> YekConfig {
+        let mut config = YekConfig::default();
+        config.input_paths = vec![dir.to_string_lossy().to_string()];
+        config.stream = true;
+        config.stages = stages;
+        config
+    }
+
+    fn exec(command: &str, match_pattern: Option<&str>, timeout_secs: Option<u64>) -> StageConfig {
+        StageConfig::Exec {
+            command: command.to_string(),
+            match_pattern: match_pattern.map(str::to_string),
+            timeout_secs,
+        }
+    }
+
+    fn content_of<'a>(report: &'a yek::SerializationReport, rel_path: &str) -> &'a str {
+        &report
+            .files
+            .iter()
+            .find(|f| f.rel_path == rel_path)
+            .unwrap()
+            .content
+    }
+
+    #[test]
+    fn test_stages_load_from_yaml() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("yek.yaml");
+        fs::write(
+            &config_path,
+            r#"
+stages:
+  - type: exec
+    command: "prettier --stdin-filepath {path}"
+    match: "*.ts"
+    timeout_secs: 10
+  - type: regex_replace
+    pattern: "secret=\\w+"
+    replacement: "secret=***"
+"#,
+        )
+        .unwrap();
+
+        let config = YekConfig::load_config_file(&config_path).unwrap();
+        assert_eq!(
+            config.stages,
+            vec![
+                exec("prettier --stdin-filepath {path}", Some("*.ts"), Some(10)),
+                StageConfig::RegexReplace {
+                    pattern: "secret=\\w+".to_string(),
+                    replacement: "secret=***".to_string(),
+                    match_pattern: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_regex_replace_stage_rewrites_matching_files() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("app.env"), "secret=hunter2\n").unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "secret=visible\n").unwrap();
+
+        let config = config_for(
+            temp_dir.path(),
+            vec![StageConfig::RegexReplace {
+                pattern: "secret=\\w+".to_string(),
+                replacement: "secret=***".to_string(),
+                match_pattern: Some("*.env".to_string()),
+            }],
+        );
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        assert!(report.output.contains("secret=***"));
+        assert!(report.output.contains("secret=visible"));
+        assert!(!report.output.contains("hunter2"));
+
+        let stats = report.stages.last().unwrap();
+        assert_eq!(stats.name, "regex_replace");
+        assert_eq!(stats.files_in, 2);
+        assert_eq!(stats.files_out, 2);
+        assert_eq!(stats.files_changed, 1);
+        assert!(stats.errors.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_stage_pipes_content_through_command() {
+        let temp_dir = tempdir().unwrap();
+        fs::create_dir(temp_dir.path().join("src")).unwrap();
+        fs::write(temp_dir.path().join("src/it's.js"), "let x = 1;\n").unwrap();
+        fs::write(temp_dir.path().join("README.md"), "# readme\n").unwrap();
+
+        let config = config_for(
+            temp_dir.path(),
+            vec![exec("tr a-z A-Z; printf '%s' {path}", Some("*.js"), None)],
+        );
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        assert_eq!(
+            content_of(&report, "src/it's.js"),
+            "LET X = 1;\nsrc/it's.js"
+        );
+        assert_eq!(content_of(&report, "README.md"), "# readme\n");
+        assert_eq!(report.stages.last().unwrap().files_changed, 1);
+        assert!(report.warnings.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_stage_failures_keep_content_and_warn() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "alpha\n").unwrap();
+        fs::write(temp_dir.path().join("b.txt"), "beta\n").unwrap();
+
+        let config = config_for(
+            temp_dir.path(),
+            vec![
+                exec("echo broken >&2; exit 3", Some("a.txt"), None),
+                exec("sleep 5", Some("b.txt"), Some(1)),
+            ],
+        );
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        assert_eq!(content_of(&report, "a.txt"), "alpha\n");
+        assert_eq!(content_of(&report, "b.txt"), "beta\n");
+
+        let exec_stats: Vec<_> = report.stages.iter().filter(|s| s.name == "exec").collect();
+        assert_eq!(exec_stats.len(), 2);
+        assert_eq!(exec_stats[0].errors.len(), 1);
+        assert!(exec_stats[0].errors[0].starts_with("a.txt: "));
+        assert!(exec_stats[0].errors[0].ends_with(": broken"));
+        assert!(exec_stats[1].errors[0].contains("timed out after 1s"));
+        assert_eq!(report.warnings.len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_stage_timeout_kills_background_processes() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.txt"), "alpha\n").unwrap();
+        let marker = temp_dir.path().join("marker");
+
+        // The background process keeps stdout open after `cat` exits
+        let command = format!("(sleep 2; touch '{}') & cat", marker.display());
+        let config = config_for(temp_dir.path(), vec![exec(&command, None, Some(1))]);
+        let started = std::time::Instant::now();
+        let report = serialize_repo_with_report(&config).unwrap();
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+
+        assert_eq!(content_of(&report, "a.txt"), "alpha\n");
+        let stats = report.stages.iter().find(|s| s.name == "exec").unwrap();
+        assert!(
+            stats.errors[0].contains("timed out after 1s"),
+            "{:?}",
+            stats
+        );
+
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        assert!(!marker.exists());
+    }
+
+    #[test]
+    fn test_invalid_stages_fail_validation() {
+        let mut config = YekConfig::default();
+        config.stages = vec![StageConfig::RegexReplace {
+            pattern: "(".to_string(),
+            replacement: String::new(),
+            match_pattern: None,
+        }];
+        config.stream = true;
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.starts_with("stages[0]: invalid regex"), "{}", err);
+
+        config.stages = vec![exec("cat", None, Some(0))];
+        let err = config.validate().unwrap_err().to_string();
+        assert_eq!(err, "stages[0]: timeout_secs: cannot be 0");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cli_runs_stages_from_config_file() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(
+            temp_dir.path().join("yek.yaml"),
+            "stages:\n  - type: exec\n    command: \"tr a-z A-Z\"\n",
+        )
+        .unwrap();
+
+        let output = Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .arg("--allow-exec")
+            .env("TERM", "dumb")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("FN MAIN() {}"), "{}", stdout);
+    }
+}
@@ -0,0 +1,199 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+use yek::{
+    config::YekConfig,
+    models::{ExclusionReason, Submodules},
+    priority::get_commit_times_by_submodule,
+    repository::GitCheckout,
+    serialize_repo_with_report, SerializationReport,
+};
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod submodule_tests {
+    use super::*;
+
+    fn git_available() -> bool {
+        Command::new("git").arg("--version").output().is_ok()
+    }
+
+    fn git(dir: &Path, args: &[&str], date: &str) {
+        let output = Command::new("git")
+            .current_dir(dir)
+            .args(["-c", "protocol.file.allow=always"])
+            .args(args)
+            .env("GIT_AUTHOR_NAME", "test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .env("GIT_AUTHOR_DATE", date)
+            .env("GIT_COMMITTER_DATE", date)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    fn commit_all(dir: &Path, date: &str) {
+        git(dir, &["add", "-A"], date);
+        git(dir, &["commit", "-q", "-m", "commit"], date);
+    }
+
+    /// A repository with `old.rs` and a submodule at `lib/sub` holding the
+    /// more recently committed `sub.rs`
+    fn fixture() -> (tempfile::TempDir, std::path::PathBuf) {
+        let temp_dir = tempdir().unwrap();
+        let upstream = temp_dir.path().join("upstream");
+        let root = temp_dir.path().join("repo");
+        fs::create_dir_all(&upstream).unwrap();
+        fs::create_dir_all(&root).unwrap();
+
+        git(&upstream, &["init", "-q"], "2020-01-01T00:00:00");
+        fs::write(upstream.join("sub.rs"), "pub fn sub() {}\n").unwrap();
+        commit_all(&upstream, "2024-01-01T00:00:00");
+
+        git(&root, &["init", "-q"], "2020-01-01T00:00:00");
+        fs::write(root.join("old.rs"), "fn old() {}\n").unwrap();
+        commit_all(&root, "2020-01-01T00:00:00");
+        git(
+            &root,
+            &["submodule", "add", "-q", "../upstream", "lib/sub"],
+            "2020-01-02T00:00:00",
+        );
+        commit_all(&root, "2020-01-02T00:00:00");
+        (temp_dir, root)
+    }
+
+    fn serialize(root: &Path, submodules: Submodules) -> SerializationReport {
+        let mut config = YekConfig::default();
+        config.input_paths = vec![root.to_string_lossy().to_string()];
+        config.submodules = submodules;
+        config.stream = true;
+        serialize_repo_with_report(&config).unwrap()
+    }
+
+    #[test]
+    fn test_detect_checkouts() {
+        if !git_available() {
+            return;
+        }
+        let (temp_dir, root) = fixture();
+        git(
+            &root,
+            &["worktree", "add", "-q", "../linked"],
+            "2020-01-03T00:00:00",
+        );
+
+        assert_eq!(GitCheckout::detect(&root), Some(GitCheckout::Repository));
+        assert_eq!(
+            GitCheckout::detect(&root.join("lib/sub")),
+            Some(GitCheckout::Submodule)
+        );
+        assert_eq!(
+            GitCheckout::detect(&temp_dir.path().join("linked")),
+            Some(GitCheckout::Worktree)
+        );
+        assert_eq!(GitCheckout::detect(&root.join("lib")), None);
+    }
+
+    #[test]
+    fn test_commit_times_keyed_by_submodule() {
+        if !git_available() {
+            return;
+        }
+        let (_temp_dir, root) = fixture();
+
+        let maps = get_commit_times_by_submodule(&root, 100);
+        let mut prefixes: Vec<_> = maps.keys().map(String::as_str).collect();
+        prefixes.sort();
+        assert_eq!(prefixes, vec!["", "lib/sub/"]);
+        assert!(maps[""].contains_key("old.rs"));
+        assert!(maps["lib/sub/"].contains_key("sub.rs"));
+    }
+
+    #[test]
+    fn test_recurse_boosts_submodule_files() {
+        if !git_available() {
+            return;
+        }
+        let (_temp_dir, root) = fixture();
+
+        let report = serialize(&root, Submodules::Recurse);
+        let priority = |rel_path: &str| {
+            report
+                .files
+                .iter()
+                .find(|f| f.rel_path == rel_path)
+                .unwrap_or_else(|| panic!("{rel_path} missing"))
+                .priority
+        };
+        assert!(priority("lib/sub/sub.rs") > priority("old.rs"));
+    }
+
+    #[test]
+    fn test_skip_submodules() {
+        if !git_available() {
+            return;
+        }
+        let (_temp_dir, root) = fixture();
+
+        let report = serialize(&root, Submodules::Skip);
+        let included: Vec<_> = report.files.iter().map(|f| f.rel_path.as_str()).collect();
+        assert!(!included.contains(&"lib/sub/sub.rs"));
+        assert!(included.contains(&"old.rs"));
+        let skipped = report
+            .excluded
+            .iter()
+            .find(|e| e.rel_path == "lib/sub/")
+            .unwrap();
+        assert_eq!(skipped.reason, ExclusionReason::Ignored);
+        assert_eq!(skipped.detail.as_deref(), Some("git submodule"));
+    }
+
+    #[test]
+    fn test_nested_worktree_is_skipped() {
+        if !git_available() {
+            return;
+        }
+        let (_temp_dir, root) = fixture();
+        git(
+            &root,
+            &["worktree", "add", "-q", "wt"],
+            "2020-01-03T00:00:00",
+        );
+
+        let report = serialize(&root, Submodules::Recurse);
+        assert!(report.files.iter().all(|f| !f.rel_path.starts_with("wt/")));
+        let skipped = report
+            .excluded
+            .iter()
+            .find(|e| e.rel_path == "wt/")
+            .unwrap();
+        assert_eq!(skipped.detail.as_deref(), Some("git worktree"));
+    }
+
+    #[test]
+    fn test_cli_submodules_flag() {
+        if !git_available() {
+            return;
+        }
+        let (_temp_dir, root) = fixture();
+
+        let output = assert_cmd::Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(&root)
+            .args([".", "--submodules", "skip"])
+            .env("TERM", "dumb")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(">>>> old.rs"), "{}", stdout);
+        assert!(!stdout.contains(">>>> lib/sub/sub.rs"), "{}", stdout);
+    }
+}
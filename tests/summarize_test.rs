@@ -0,0 +1,234 @@
+use assert_cmd::Command;
+use std::fs;
+use tempfile::tempdir;
+use yek::{api::Yek, summarize::summarize};
+
+#[cfg(test)]
+mod summarize_tests {
+    use super::*;
+
+    fn lines(summary: &str) -> Vec<&str> {
+        summary.lines().skip(1).collect()
+    }
+
+    #[test]
+    fn test_cargo_and_poetry_lock() {
+        let cargo = r#"# This file is automatically @generated by Cargo.
+version = 4
+
+[[package]]
+name = "serde"
+version = "1.0.200"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "anyhow"
+version = "1.0.86"
+"#;
+        let summary = summarize("Cargo.lock", cargo, 10).unwrap();
+        assert!(summary.starts_with("# Cargo.lock: 2 packages (summarized)\n"));
+        assert_eq!(lines(&summary), vec!["anyhow@1.0.86", "serde@1.0.200"]);
+
+        let poetry = "[[package]]\nname = \"requests\"\nversion = \"2.31.0\"\n";
+        let summary = summarize("api/poetry.lock", poetry, 10).unwrap();
+        assert_eq!(lines(&summary), vec!["requests@2.31.0"]);
+    }
+
+    #[test]
+    fn test_npm_lock_versions() {
+        let v3 = r#"{
+  "lockfileVersion": 3,
+  "packages": {
+    "": {"name": "app", "version": "1.0.0"},
+    "node_modules/react": {"version": "18.2.0"},
+    "node_modules/@babel/core": {"version": "7.24.0"},
+    "node_modules/a/node_modules/react": {"version": "17.0.2"}
+  }
+}"#;
+        let summary = summarize("package-lock.json", v3, 10).unwrap();
+        assert_eq!(
+            lines(&summary),
+            vec!["@babel/core@7.24.0", "react@17.0.2", "react@18.2.0"]
+        );
+
+        let v1 = r#"{"dependencies": {"left-pad": {"version": "1.3.0",
+            "dependencies": {"tiny": {"version": "0.1.0"}}}}}"#;
+        let summary = summarize("package-lock.json", v1, 10).unwrap();
+        assert_eq!(lines(&summary), vec!["left-pad@1.3.0", "tiny@0.1.0"]);
+    }
+
+    #[test]
+    fn test_yarn_and_pnpm_locks() {
+        let yarn = r#"# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.
+# yarn lockfile v1
+
+
+"@babel/code-frame@^7.0.0", "@babel/code-frame@^7.22.13":
+  version "7.22.13"
+  resolved "https://registry.yarnpkg.com/@babel/code-frame/-/code-frame-7.22.13.tgz"
+
+lodash@^4.17.21:
+  version "4.17.21"
+"#;
+        let summary = summarize("yarn.lock", yarn, 10).unwrap();
+        assert_eq!(
+            lines(&summary),
+            vec!["@babel/code-frame@7.22.13", "lodash@4.17.21"]
+        );
+
+        let berry = "__metadata:\n  version: 6\n\n\"lodash@npm:^4.17.21\":\n  version: 4.17.21\n";
+        let summary = summarize("yarn.lock", berry, 10).unwrap();
+        assert_eq!(lines(&summary), vec!["lodash@4.17.21"]);
+
+        let pnpm = r#"lockfileVersion: '9.0'
+packages:
+  '@types/node@20.11.0':
+    resolution: {integrity: sha512-x}
+  react-dom@18.2.0(react@18.2.0):
+    resolution: {integrity: sha512-y}
+  /lodash/4.17.21:
+    resolution: {integrity: sha512-z}
+"#;
+        let summary = summarize("pnpm-lock.yaml", pnpm, 10).unwrap();
+        assert_eq!(
+            lines(&summary),
+            vec!["@types/node@20.11.0", "lodash@4.17.21", "react-dom@18.2.0"]
+        );
+    }
+
+    #[test]
+    fn test_other_lockfiles() {
+        let gemfile = "GEM\n  remote: https://rubygems.org/\n  specs:\n    rack (3.0.8)\n    rails (7.1.2)\n      rack (>= 2.2.4)\n\nDEPENDENCIES\n  rails\n\nBUNDLED WITH\n   2.4.10\n";
+        let summary = summarize("Gemfile.lock", gemfile, 10).unwrap();
+        assert_eq!(lines(&summary), vec!["rack@3.0.8", "rails@7.1.2"]);
+
+        let go_sum =
+            "golang.org/x/text v0.14.0 h1:abc=\ngolang.org/x/text v0.14.0/go.mod h1:def=\n";
+        let summary = summarize("go.sum", go_sum, 10).unwrap();
+        assert_eq!(lines(&summary), vec!["golang.org/x/text@v0.14.0"]);
+
+        let composer = r#"{"packages": [{"name": "monolog/monolog", "version": "3.5.0"}],
+            "packages-dev": [{"name": "phpunit/phpunit", "version": "10.5.0"}]}"#;
+        let summary = summarize("composer.lock", composer, 10).unwrap();
+        assert_eq!(
+            lines(&summary),
+            vec!["monolog/monolog@3.5.0", "phpunit/phpunit@10.5.0"]
+        );
+
+        let pipfile = r#"{"default": {"flask": {"version": "==3.0.0"}}, "develop": {}}"#;
+        let summary = summarize("Pipfile.lock", pipfile, 10).unwrap();
+        assert_eq!(lines(&summary), vec!["flask@3.0.0"]);
+    }
+
+    #[test]
+    fn test_csv_summary() {
+        let mut csv = String::from("id,name,score,active\n");
+        for i in 0..25 {
+            csv.push_str(&format!("{i},\"Doe, J{i}\",{}.5,true\n", i));
+        }
+        let summary = summarize("data/scores.csv", &csv, 3).unwrap();
+        assert_eq!(
+            summary,
+            "# data/scores.csv: 4 columns, 25 rows (summarized)\n\
+             # columns: id (integer), name (text), score (number), active (boolean)\n\
+             id,name,score,active\n\
+             0,\"Doe, J0\",0.5,true\n\
+             1,\"Doe, J1\",1.5,true\n\
+             2,\"Doe, J2\",2.5,true\n\
+             # ... 22 more rows\n"
+        );
+
+        let tsv = "a\tb\n1\tx\n";
+        let summary = summarize("t.tsv", tsv, 10).unwrap();
+        assert!(summary.contains("# columns: a (integer), b (text)\n"));
+        assert!(!summary.contains("more rows"));
+    }
+
+    #[test]
+    fn test_json_summary() {
+        let items: Vec<String> = (0..12)
+            .map(|i| format!(r#"{{"id": {i}, "tags": ["a", "b", "c"]}}"#))
+            .collect();
+        let json = format!("[{}]", items.join(","));
+        let summary = summarize("fixtures/users.json", &json, 2).unwrap();
+        let mut parts = summary.splitn(3, '\n');
+        assert_eq!(
+            parts.next().unwrap(),
+            "# fixtures/users.json: array of 12 items (summarized)"
+        );
+        assert_eq!(
+            parts.next().unwrap(),
+            r#"# schema: [{"id": integer, "tags": [string]}]"#
+        );
+        let sample: serde_json::Value = serde_json::from_str(parts.next().unwrap()).unwrap();
+        assert_eq!(
+            sample,
+            serde_json::json!([
+                {"id": 0, "tags": ["a", "b", "... 1 more items"]},
+                {"id": 1, "tags": ["a", "b", "... 1 more items"]},
+                "... 10 more items"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_unknown_and_invalid_files_are_not_summarized() {
+        assert!(summarize("src/main.rs", "fn main() {}", 10).is_none());
+        assert!(summarize("data.json", "{ not json", 10).is_none());
+        assert!(summarize("Cargo.lock", "not = [toml", 10).is_none());
+    }
+
+    #[test]
+    fn test_summarized_lockfile_is_unignored() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::write(
+            root.join("Cargo.lock"),
+            "[[package]]\nname = \"anyhow\"\nversion = \"1.0.86\"\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("yarn.lock"),
+            "lodash@^4:\n  version \"4.17.21\"\n",
+        )
+        .unwrap();
+        fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+
+        let result = Yek::builder()
+            .path(root)
+            .summarize("Cargo.lock")
+            .build()
+            .unwrap()
+            .serialize()
+            .unwrap();
+        assert!(result
+            .output
+            .contains(">>>> Cargo.lock\n# Cargo.lock: 1 packages (summarized)\nanyhow@1.0.86\n"));
+        // Lockfiles not selected for summarizing stay ignored
+        assert!(!result.output.contains("yarn.lock"));
+    }
+
+    #[test]
+    fn test_cli_summarize_patterns() {
+        let temp_dir = tempdir().unwrap();
+        let mut csv = String::from("x,y\n");
+        for i in 0..100 {
+            csv.push_str(&format!("{i},{i}\n"));
+        }
+        fs::write(temp_dir.path().join("points.csv"), csv).unwrap();
+
+        let output = Command::cargo_bin("yek")
+            .unwrap()
+            .current_dir(temp_dir.path())
+            .args([".", "--summarize-patterns", "*.csv", "--summary-rows", "2"])
+            .env("TERM", "dumb")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            stdout.contains("x,y\n0,0\n1,1\n# ... 98 more rows\n"),
+            "{}",
+            stdout
+        );
+    }
+}
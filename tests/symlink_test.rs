@@ -1,4 +1,5 @@
 #[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
 mod symlink_tests {
     use std::collections::HashMap;
     use std::fs;
@@ -43,6 +44,160 @@ mod symlink_tests {
         );
     }
 
+    #[cfg(unix)]
+    fn symlink_fixture() -> (tempfile::TempDir, std::path::PathBuf) {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap().join("repo");
+        let outside = temp_dir.path().canonicalize().unwrap().join("outside");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("shared")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("shared/util.rs"), "pub fn util() {}\n").unwrap();
+        fs::write(outside.join("secret.txt"), "secret\n").unwrap();
+
+        symlink("main.rs", root.join("src/alias.rs")).unwrap();
+        symlink("../shared", root.join("src/linked")).unwrap();
+        symlink(&outside, root.join("external")).unwrap();
+        symlink("missing.rs", root.join("src/broken.rs")).unwrap();
+        (temp_dir, root)
+    }
+
+    #[cfg(unix)]
+    fn serialize_with(
+        root: &std::path::Path,
+        follow: yek::models::FollowSymlinks,
+    ) -> yek::SerializationReport {
+        let mut config = YekConfig::default();
+        config.input_paths = vec![root.to_string_lossy().to_string()];
+        config.follow_symlinks = follow;
+        config.tree_header = true;
+        config.stream = true;
+        yek::serialize_repo_with_report(&config).unwrap()
+    }
+
+    #[cfg(unix)]
+    fn included(report: &yek::SerializationReport) -> Vec<&str> {
+        let mut paths: Vec<_> = report.files.iter().map(|f| f.rel_path.as_str()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[cfg(unix)]
+    fn skipped_links(report: &yek::SerializationReport) -> Vec<(&str, &str)> {
+        report
+            .excluded
+            .iter()
+            .filter(|e| e.reason == yek::models::ExclusionReason::Symlink)
+            .map(|e| (e.rel_path.as_str(), e.detail.as_deref().unwrap_or("")))
+            .collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_symlinks_never() {
+        let (_temp_dir, root) = symlink_fixture();
+        let report = serialize_with(&root, yek::models::FollowSymlinks::Never);
+
+        assert_eq!(included(&report), vec!["shared/util.rs", "src/main.rs"]);
+        let skipped = skipped_links(&report);
+        assert_eq!(skipped.len(), 4, "{:?}", skipped);
+        assert!(skipped.contains(&("src/alias.rs", "-> main.rs (follow_symlinks is never)")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_symlinks_within_root() {
+        let (_temp_dir, root) = symlink_fixture();
+        let report = serialize_with(&root, yek::models::FollowSymlinks::WithinRoot);
+
+        assert_eq!(
+            included(&report),
+            vec![
+                "shared/util.rs",
+                "src/alias.rs",
+                "src/linked/util.rs",
+                "src/main.rs"
+            ]
+        );
+        let outside = root.parent().unwrap().join("outside");
+        assert_eq!(
+            skipped_links(&report),
+            vec![
+                (
+                    "external",
+                    format!("-> {} (outside {})", outside.display(), root.display()).as_str()
+                ),
+                ("src/broken.rs", "-> missing.rs (broken link)"),
+            ]
+        );
+
+        let tree = report.output.split(">>>> ").next().unwrap();
+        assert!(tree.contains("linked/ -> ../shared\n"), "{}", tree);
+        assert!(tree.contains("alias.rs -> main.rs\n"), "{}", tree);
+        assert!(tree.contains("main.rs\n"), "{}", tree);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_symlinks_always() {
+        let (_temp_dir, root) = symlink_fixture();
+        let report = serialize_with(&root, yek::models::FollowSymlinks::Always);
+
+        assert!(included(&report).contains(&"external/secret.txt"));
+        assert_eq!(
+            skipped_links(&report),
+            vec![("src/broken.rs", "-> missing.rs (broken link)")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_cycles_are_detected() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/b/file.rs"), "// file\n").unwrap();
+        std::os::unix::fs::symlink("../..", root.join("a/b/up")).unwrap();
+
+        for follow in [
+            yek::models::FollowSymlinks::WithinRoot,
+            yek::models::FollowSymlinks::Always,
+        ] {
+            let report = serialize_with(&root, follow);
+            assert_eq!(included(&report), vec!["a/b/file.rs"]);
+            assert_eq!(skipped_links(&report), vec![("a/b/up", "cycle back to ./")]);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cli_follow_symlinks_flag() {
+        let (_temp_dir, root) = symlink_fixture();
+        let run = |args: &[&str]| {
+            let output = assert_cmd::Command::cargo_bin("yek")
+                .unwrap()
+                .current_dir(&root)
+                .args(args)
+                .env("TERM", "dumb")
+                .output()
+                .unwrap();
+            String::from_utf8(output.stdout).unwrap()
+        };
+
+        let stdout = run(&[]);
+        assert!(!stdout.contains("src/linked/util.rs"), "{}", stdout);
+        let stdout = run(&["--follow-symlinks", "within-root"]);
+        assert!(stdout.contains(">>>> src/linked/util.rs"), "{}", stdout);
+        assert!(!stdout.contains("secret"), "{}", stdout);
+
+        fs::write(root.join("yek.yaml"), "follow_symlinks: always\n").unwrap();
+        let stdout = run(&[]);
+        assert!(stdout.contains(">>>> external/secret.txt"), "{}", stdout);
+    }
+
     // For non-unix systems, we skip the symlink test.
     #[cfg(not(unix))]
     #[test]
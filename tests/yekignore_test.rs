@@ -0,0 +1,172 @@
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use yek::{config::YekConfig, models::ExclusionReason, serialize_repo_with_report};
+
+#[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
+mod yekignore_tests {
+    use super::*;
+
+    fn write_files(root: &Path, files: &[(&str, &str)]) {
+        for (path, content) in files {
+            let full = root.join(path);
+            fs::create_dir_all(full.parent().unwrap()).unwrap();
+            fs::write(full, content).unwrap();
+        }
+    }
+
+    fn ignored(report: &yek::SerializationReport) -> Vec<(String, String)> {
+        report
+            .excluded
+            .iter()
+            .filter(|e| e.reason == ExclusionReason::Ignored)
+            .map(|e| (e.rel_path.clone(), e.detail.clone().unwrap_or_default()))
+            .collect()
+    }
+
+    #[test]
+    fn test_yekignore_at_any_level() {
+        let temp_dir = tempdir().unwrap();
+        write_files(
+            temp_dir.path(),
+            &[
+                (".yekignore", "fixtures/\n"),
+                ("src/.yekignore", "*.snap\n!keep.snap\n"),
+                ("src/lib.rs", "pub fn f() {}\n"),
+                ("src/lib.snap", "snapshot\n"),
+                ("src/keep.snap", "kept\n"),
+                ("src/tests/out.snap", "nested snapshot\n"),
+                ("fixtures/big.json", "{}\n"),
+            ],
+        );
+
+        let mut config = YekConfig::default();
+        config.input_paths = vec![temp_dir.path().to_string_lossy().to_string()];
+        config.stream = true;
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        let included: Vec<_> = report.files.iter().map(|f| f.rel_path.as_str()).collect();
+        assert_eq!(included, vec!["src/keep.snap", "src/lib.rs"]);
+        assert_eq!(
+            ignored(&report),
+            vec![
+                ("fixtures/".to_string(), ".yekignore: fixtures/".to_string()),
+                (
+                    "src/lib.snap".to_string(),
+                    "src/.yekignore: *.snap".to_string()
+                ),
+                (
+                    "src/tests/out.snap".to_string(),
+                    "src/.yekignore: *.snap".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_yekignore_overrides_gitignore() {
+        let temp_dir = tempdir().unwrap();
+        write_files(
+            temp_dir.path(),
+            &[
+                (".gitignore", "*.gen.rs\n"),
+                (".yekignore", "!api.gen.rs\nnotes.md\n"),
+                ("sub/.gitignore", "!notes.md\n"),
+                ("api.gen.rs", "// api\n"),
+                ("db.gen.rs", "// db\n"),
+                ("notes.md", "root notes\n"),
+                ("sub/notes.md", "sub notes\n"),
+            ],
+        );
+
+        let mut config = YekConfig::default();
+        config.input_paths = vec![temp_dir.path().to_string_lossy().to_string()];
+        config.stream = true;
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        let included: Vec<_> = report.files.iter().map(|f| f.rel_path.as_str()).collect();
+        assert_eq!(included, vec!["api.gen.rs"]);
+        assert_eq!(
+            ignored(&report),
+            vec![
+                ("db.gen.rs".to_string(), ".gitignore: *.gen.rs".to_string()),
+                ("notes.md".to_string(), ".yekignore: notes.md".to_string()),
+                (
+                    "sub/notes.md".to_string(),
+                    ".yekignore: notes.md".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_configured_patterns_win_over_negations() {
+        let temp_dir = tempdir().unwrap();
+        write_files(
+            temp_dir.path(),
+            &[
+                (".gitignore", "*.tmp\n!debug.log\n"),
+                (".yekignore", "!trace.log\n"),
+                ("debug.log", "debug\n"),
+                ("trace.log", "trace\n"),
+                ("main.rs", "fn main() {}\n"),
+            ],
+        );
+
+        let mut config = YekConfig::default();
+        config.input_paths = vec![temp_dir.path().to_string_lossy().to_string()];
+        config.ignore_patterns = vec!["*.log".to_string()];
+        config.stream = true;
+        let report = serialize_repo_with_report(&config).unwrap();
+
+        let included: Vec<_> = report.files.iter().map(|f| f.rel_path.as_str()).collect();
+        assert_eq!(included, vec!["main.rs"]);
+        assert_eq!(
+            ignored(&report),
+            vec![
+                ("debug.log".to_string(), "*.log".to_string()),
+                ("trace.log".to_string(), "*.log".to_string()),
+            ]
+        );
+
+        // The same from the command line
+        let output = Command::cargo_bin("yek")
+            .unwrap()
+            .arg(temp_dir.path())
+            .args(["--ignore-patterns", "*.log"])
+            .env("TERM", "dumb")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(">>>> main.rs"), "{}", stdout);
+        assert!(!stdout.contains(".log"), "{}", stdout);
+    }
+
+    #[test]
+    fn test_yekignore_reincludes_default_ignored_files() {
+        let temp_dir = tempdir().unwrap();
+        write_files(
+            temp_dir.path(),
+            &[
+                (".yekignore", "!Cargo.lock\n"),
+                ("Cargo.lock", "# lock\n"),
+                ("yarn.lock", "# yarn\n"),
+                ("main.rs", "fn main() {}\n"),
+            ],
+        );
+
+        let output = Command::cargo_bin("yek")
+            .unwrap()
+            .arg(temp_dir.path())
+            .env("TERM", "dumb")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(">>>> Cargo.lock"), "{}", stdout);
+        assert!(stdout.contains(">>>> main.rs"), "{}", stdout);
+        assert!(!stdout.contains("yarn.lock"), "{}", stdout);
+        assert!(!stdout.contains(".yekignore"), "{}", stdout);
+    }
+}